@@ -1,5 +1,3 @@
-#![feature(portable_simd)]
-
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use parry3d::math::{Isometry, Real, Vector};
 use parry3d::na::Vector3;
@@ -10,7 +8,6 @@ use squad::test_utils::generate_test_data;
 use squad::utils::simd_permutations;
 use squad::*;
 use std::ops::Range;
-use std::simd::Simd;
 
 fn criterion_benchmark(c: &mut Criterion) {
     const RANGE: Range<f32> = -1000.0..1000.0;
@@ -45,13 +42,17 @@ fn criterion_benchmark(c: &mut Criterion) {
     let mut group = c.benchmark_group("Intersections");
     group.bench_function("aabb_aabb_intersections_simd", |b| {
         b.iter(|| {
-            black_box(simd_permutations::<LANES, 6, 6, _>(
-                &simd_aabbs,
-                &simd_aabbs,
-                |a, b| {
-                    wide_intersections::aabb_aabb::<LANES>(a, b);
-                },
-            ))
+            simd_permutations::<LANES, 6, 6, _>(&simd_aabbs, &simd_aabbs, |a, b| {
+                black_box(wide_intersections::aabb_aabb::<f32, LANES>(a, b));
+            })
+        })
+    });
+
+    group.bench_function("aabb_aabb_intersections_dispatch", |b| {
+        b.iter(|| {
+            dispatch::aabb_aabb_pairs(&simd_aabbs, &simd_aabbs, |a, b| {
+                black_box((a, b));
+            })
         })
     });
 
@@ -77,8 +78,7 @@ fn criterion_benchmark(c: &mut Criterion) {
 
     let mut parry_positions = vec![];
     let mut parry_cubiods = vec![];
-    for i in 0..AABB_COUNT {
-        let a = aabbs[i];
+    for a in &aabbs {
         let a_c = parry3d::shape::Cuboid::new(Vector::<Real>::new(
             (a[0][0] - a[1][0]).abs() / 2.0,
             (a[0][1] - a[1][1]).abs() / 2.0,
@@ -112,22 +112,16 @@ fn criterion_benchmark(c: &mut Criterion) {
 
     group.bench_function("ray_aabb_intersections_simd", |b| {
         b.iter(|| {
-            black_box(simd_permutations::<LANES, 6, 6, _>(
-                &simd_rays,
-                &simd_aabbs,
-                |a, b| {
-                    wide_intersections::ray_aabb_time::<LANES>(a, b);
-                },
-            ))
+            simd_permutations::<LANES, 6, 6, _>(&simd_rays, &simd_aabbs, |a, b| {
+                black_box(wide_intersections::ray_aabb_time::<f32, LANES>(a, b));
+            })
         })
     });
 
     group.bench_function("ray_aabb_intersections", |b| {
         b.iter(|| {
-            for i in 0..RAY_COUNT {
-                for j in 0..AABB_COUNT {
-                    let a = rays[i];
-                    let b = aabbs[j];
+            for a in &rays {
+                for b in &aabbs {
                     let a = Ray {
                         origin: a[0],
                         direction: a[1],
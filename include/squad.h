@@ -0,0 +1,177 @@
+#ifndef SQUAD_H
+#define SQUAD_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+
+/**
+ * A bvh over an array of aabbs, created with `squad_bvh_new` and released
+ * with `squad_bvh_free`.
+ */
+typedef struct SquadBvh SquadBvh;
+
+typedef struct SquadAabb {
+  float min[3];
+  float max[3];
+} SquadAabb;
+
+typedef struct SquadVec3 {
+  float x;
+  float y;
+  float z;
+} SquadVec3;
+
+typedef struct SquadSphere {
+  float center[3];
+  float radius;
+} SquadSphere;
+
+typedef struct SquadObb {
+  float center[3];
+  float half_extents[3];
+  float axes[3][3];
+} SquadObb;
+
+typedef struct SquadRay {
+  float origin[3];
+  float direction[3];
+} SquadRay;
+
+/**
+ * A rigid transform: rotate by `rotation` (a unit quaternion stored as
+ * `[x, y, z, w]`), then translate by `translation`.
+ */
+typedef struct SquadIsometry {
+  float translation[3];
+  float rotation[4];
+} SquadIsometry;
+
+/**
+ * Called once per hit by the batch functions, with the `user_data` pointer
+ * passed alongside it.
+ */
+typedef void (*SquadPairCallback)(void *user_data, size_t a, size_t b);
+
+typedef void (*SquadRayCallback)(void *user_data, size_t ray, size_t aabb, float time);
+
+bool squad_aabb_aabb(struct SquadAabb a, struct SquadAabb b);
+
+bool squad_aabb_point(struct SquadAabb aabb, struct SquadVec3 point);
+
+bool squad_aabb_sphere(struct SquadAabb aabb, struct SquadSphere sphere);
+
+bool squad_obb_obb(struct SquadObb a, struct SquadObb b);
+
+bool squad_obb_point(struct SquadObb obb, struct SquadVec3 point);
+
+bool squad_obb_sphere(struct SquadObb obb, struct SquadSphere sphere);
+
+bool squad_sphere_sphere(struct SquadSphere a, struct SquadSphere b);
+
+bool squad_sphere_point(struct SquadSphere sphere, struct SquadVec3 point);
+
+/**
+ * Time of impact along the ray, or a negative value on a miss.
+ */
+float squad_ray_aabb(struct SquadRay ray, struct SquadAabb aabb);
+
+/**
+ * Time of impact along the ray, or a negative value on a miss.
+ */
+float squad_ray_obb(struct SquadRay ray, struct SquadObb obb);
+
+struct SquadObb squad_obb_transformed(struct SquadObb obb, struct SquadIsometry iso);
+
+/**
+ * Calls `callback` for every intersecting pair of `a` and `b`. When `a` and
+ * `b` are the same array each pair is reported once, with `a < b`.
+ *
+ * # Safety
+ *
+ * `a` and `b` must point to `a_len` and `b_len` valid aabbs.
+ */
+void squad_aabb_aabb_pairs(const struct SquadAabb *a,
+                           size_t a_len,
+                           const struct SquadAabb *b,
+                           size_t b_len,
+                           SquadPairCallback callback,
+                           void *user_data);
+
+/**
+ * Calls `callback` for every intersecting aabb and sphere.
+ *
+ * # Safety
+ *
+ * `aabbs` and `spheres` must point to `aabbs_len` and `spheres_len` valid
+ * shapes.
+ */
+void squad_aabb_sphere_pairs(const struct SquadAabb *aabbs,
+                             size_t aabbs_len,
+                             const struct SquadSphere *spheres,
+                             size_t spheres_len,
+                             SquadPairCallback callback,
+                             void *user_data);
+
+/**
+ * Calls `callback` for every intersecting pair of `a` and `b`. When `a` and
+ * `b` are the same array each pair is reported once, with `a < b`.
+ *
+ * # Safety
+ *
+ * `a` and `b` must point to `a_len` and `b_len` valid spheres.
+ */
+void squad_sphere_sphere_pairs(const struct SquadSphere *a,
+                               size_t a_len,
+                               const struct SquadSphere *b,
+                               size_t b_len,
+                               SquadPairCallback callback,
+                               void *user_data);
+
+/**
+ * Casts every ray against every aabb, calling `callback` with the time of
+ * impact of each hit.
+ *
+ * # Safety
+ *
+ * `rays` and `aabbs` must point to `rays_len` and `aabbs_len` valid values.
+ */
+void squad_ray_aabb_pairs(const struct SquadRay *rays,
+                          size_t rays_len,
+                          const struct SquadAabb *aabbs,
+                          size_t aabbs_len,
+                          SquadRayCallback callback,
+                          void *user_data);
+
+/**
+ * Builds a bvh over a copy of `aabbs`, identifying each by its index.
+ *
+ * # Safety
+ *
+ * `aabbs` must point to `len` valid aabbs.
+ */
+struct SquadBvh *squad_bvh_new(const struct SquadAabb *aabbs, size_t len);
+
+/**
+ * Time of impact of the closest aabb hit by `ray` within `max_time`, with its
+ * index stored in `index`, or a negative value on a miss.
+ *
+ * # Safety
+ *
+ * `bvh` must come from `squad_bvh_new` and `index` must be valid to write.
+ */
+float squad_bvh_cast_ray(const struct SquadBvh *bvh,
+                         struct SquadRay ray,
+                         float max_time,
+                         size_t *index);
+
+/**
+ * # Safety
+ *
+ * `bvh` must come from `squad_bvh_new` and not be used afterwards. Null is
+ * ignored.
+ */
+void squad_bvh_free(struct SquadBvh *bvh);
+
+#endif  /* SQUAD_H */
@@ -0,0 +1,388 @@
+use crate::simd::{Mask, Simd, SimdElement, SimdPartialOrd};
+use crate::wide_intersections;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Loads `LANES` items starting at `start`, zero padding past the end.
+#[inline(always)]
+pub fn load<E: SimdElement + Default, const LANES: usize, const WIDTH: usize>(
+    soa: &[Vec<E>; WIDTH],
+    start: usize,
+) -> [Simd<E, LANES>; WIDTH] {
+    core::array::from_fn(|k| {
+        let column = &soa[k][start..];
+        if column.len() >= LANES {
+            Simd::from_slice(column)
+        } else {
+            let mut padded = [E::default(); LANES];
+            padded[..column.len()].copy_from_slice(column);
+            Simd::from_array(padded)
+        }
+    })
+}
+
+#[inline(always)]
+pub fn splat<E: SimdElement, const LANES: usize, const WIDTH: usize>(
+    soa: &[Vec<E>; WIDTH],
+    index: usize,
+) -> [Simd<E, LANES>; WIDTH] {
+    core::array::from_fn(|k| Simd::splat(soa[k][index]))
+}
+
+/// Tests every item of `input_a` against every item of `input_b`, calling
+/// `hit` with the indices of each intersecting pair. When both inputs are
+/// the same data only pairs with `a < b` are tested.
+#[inline(always)]
+pub fn pairs<const LANES: usize, const WIDTH: usize, const WIDTH2: usize>(
+    input_a: &[Vec<f32>; WIDTH],
+    input_b: &[Vec<f32>; WIDTH2],
+    test: impl Fn(
+        [Simd<f32, LANES>; WIDTH],
+        [Simd<f32, LANES>; WIDTH2],
+    ) -> Mask<i32, LANES>,
+    hit: impl FnMut(usize, usize),
+) {
+    pairs_in(input_a, input_b, 0..input_b[0].len(), test, hit)
+}
+
+/// Like `pairs`, only testing the items of `input_b` within `columns`.
+#[inline(always)]
+pub fn pairs_in<const LANES: usize, const WIDTH: usize, const WIDTH2: usize>(
+    input_a: &[Vec<f32>; WIDTH],
+    input_b: &[Vec<f32>; WIDTH2],
+    columns: Range<usize>,
+    test: impl Fn(
+        [Simd<f32, LANES>; WIDTH],
+        [Simd<f32, LANES>; WIDTH2],
+    ) -> Mask<i32, LANES>,
+    mut hit: impl FnMut(usize, usize),
+) {
+    let same_inputs = input_a.as_ptr() == input_b.as_ptr();
+    for i in 0..input_a[0].len() {
+        let a = splat(input_a, i);
+        let first = if same_inputs {
+            columns.start.max(i + 1)
+        } else {
+            columns.start
+        };
+        for start in (first..columns.end).step_by(LANES) {
+            let mask = test(a, load(input_b, start)).to_array();
+            let valid = (columns.end - start).min(LANES);
+            for (lane, _) in mask[..valid].iter().enumerate().filter(|(_, m)| **m) {
+                hit(i, start + lane);
+            }
+        }
+    }
+}
+
+/// Like `pairs_in`, also rejecting pairs whose collision groups don't
+/// interact. Each input comes with `[memberships, filter]` group columns,
+/// which are ANDed onto the shape test's mask rather than branched on.
+#[inline(always)]
+pub fn filtered_pairs_in<
+    const LANES: usize,
+    const WIDTH: usize,
+    const WIDTH2: usize,
+>(
+    (input_a, groups_a): (&[Vec<f32>; WIDTH], &[Vec<u32>; 2]),
+    (input_b, groups_b): (&[Vec<f32>; WIDTH2], &[Vec<u32>; 2]),
+    columns: Range<usize>,
+    test: impl Fn(
+        [Simd<f32, LANES>; WIDTH],
+        [Simd<f32, LANES>; WIDTH2],
+    ) -> Mask<i32, LANES>,
+    mut hit: impl FnMut(usize, usize),
+) {
+    let same_inputs = input_a.as_ptr() == input_b.as_ptr();
+    for i in 0..input_a[0].len() {
+        let (a, a_groups) = (splat(input_a, i), splat(groups_a, i));
+        let first = if same_inputs {
+            columns.start.max(i + 1)
+        } else {
+            columns.start
+        };
+        for start in (first..columns.end).step_by(LANES) {
+            let mask = test(a, load(input_b, start))
+                & wide_intersections::groups(a_groups, load(groups_b, start));
+            let mask = mask.to_array();
+            let valid = (columns.end - start).min(LANES);
+            for (lane, _) in mask[..valid].iter().enumerate().filter(|(_, m)| **m) {
+                hit(i, start + lane);
+            }
+        }
+    }
+}
+
+#[inline(always)]
+pub fn filtered_pairs<
+    const LANES: usize,
+    const WIDTH: usize,
+    const WIDTH2: usize,
+>(
+    a: (&[Vec<f32>; WIDTH], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; WIDTH2], &[Vec<u32>; 2]),
+    test: impl Fn(
+        [Simd<f32, LANES>; WIDTH],
+        [Simd<f32, LANES>; WIDTH2],
+    ) -> Mask<i32, LANES>,
+    hit: impl FnMut(usize, usize),
+) {
+    filtered_pairs_in(a, b, 0..b.0[0].len(), test, hit)
+}
+
+#[inline(always)]
+pub fn aabb_aabb_pairs<const LANES: usize>(
+    a: &[Vec<f32>; 6],
+    b: &[Vec<f32>; 6],
+    hit: impl FnMut(usize, usize),
+) {
+    pairs::<LANES, 6, 6>(a, b, wide_intersections::aabb_aabb, hit)
+}
+
+#[inline(always)]
+pub fn aabb_sphere_pairs<const LANES: usize>(
+    aabbs: &[Vec<f32>; 6],
+    spheres: &[Vec<f32>; 4],
+    hit: impl FnMut(usize, usize),
+) {
+    pairs::<LANES, 6, 4>(aabbs, spheres, wide_intersections::aabb_sphere, hit)
+}
+
+#[inline(always)]
+pub fn sphere_sphere_pairs<const LANES: usize>(
+    a: &[Vec<f32>; 4],
+    b: &[Vec<f32>; 4],
+    hit: impl FnMut(usize, usize),
+) {
+    pairs::<LANES, 4, 4>(a, b, wide_intersections::sphere_sphere, hit)
+}
+
+#[inline(always)]
+pub fn aabb_aabb_filtered_pairs<const LANES: usize>(
+    a: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    hit: impl FnMut(usize, usize),
+) {
+    filtered_pairs::<LANES, 6, 6>(a, b, wide_intersections::aabb_aabb, hit)
+}
+
+#[inline(always)]
+pub fn aabb_sphere_filtered_pairs<const LANES: usize>(
+    aabbs: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    spheres: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    hit: impl FnMut(usize, usize),
+) {
+    filtered_pairs::<LANES, 6, 4>(
+        aabbs,
+        spheres,
+        wide_intersections::aabb_sphere,
+        hit,
+    )
+}
+
+#[inline(always)]
+pub fn sphere_sphere_filtered_pairs<const LANES: usize>(
+    a: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    hit: impl FnMut(usize, usize),
+) {
+    filtered_pairs::<LANES, 4, 4>(a, b, wide_intersections::sphere_sphere, hit)
+}
+
+/// Casts every ray against every aabb, calling `hit` with the ray index,
+/// aabb index and time of impact of each hit.
+#[inline(always)]
+pub fn ray_aabb_pairs<const LANES: usize>(
+    rays: &[Vec<f32>; 6],
+    aabbs: &[Vec<f32>; 6],
+    hit: impl FnMut(usize, usize, f32),
+) {
+    ray_aabb_pairs_in::<LANES>(rays, aabbs, 0..aabbs[0].len(), hit)
+}
+
+/// Like `ray_aabb_pairs`, only testing the aabbs within `columns`.
+#[inline(always)]
+pub fn ray_aabb_pairs_in<const LANES: usize>(
+    rays: &[Vec<f32>; 6],
+    aabbs: &[Vec<f32>; 6],
+    columns: Range<usize>,
+    mut hit: impl FnMut(usize, usize, f32),
+) {
+    let zero = Simd::splat(0.0);
+    for i in 0..rays[0].len() {
+        let ray = splat(rays, i);
+        for start in columns.clone().step_by(LANES) {
+            let times = wide_intersections::ray_aabb_time::<f32, LANES>(
+                ray,
+                load(aabbs, start),
+            );
+            let mask = times.simd_ge(zero).to_array();
+            let times = times.to_array();
+            let valid = (columns.end - start).min(LANES);
+            for (lane, _) in mask[..valid].iter().enumerate().filter(|(_, m)| **m) {
+                hit(i, start + lane, times[lane]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groups::CollisionGroups;
+    use crate::intersections;
+    use crate::ray::Ray;
+    use crate::shapes::{Aabb, Sphere};
+    use crate::test_utils::{generate_soa_data, generate_test_data};
+    use rand::Rng;
+    use std::ops::Range;
+
+    #[test]
+    fn pairs_match_scalar() {
+        const LANES: usize = 8;
+        const RANGE: Range<f32> = -4.0..4.0;
+        const COUNT: usize = 61;
+        let mut rng = rand::thread_rng();
+        let (aabbs, aos_aabbs) = generate_test_data(COUNT, |_| {
+            [0.0, 0.0, 0.0, 1.0, 1.0, 1.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let spheres = generate_soa_data(COUNT + 3, |_| {
+            [
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(0.1..1.0),
+            ]
+        });
+        let to_aabb = |i: usize| Aabb {
+            min: aos_aabbs[i][0],
+            max: aos_aabbs[i][1],
+        };
+        let to_sphere = |i: usize| Sphere {
+            center: [spheres[0][i], spheres[1][i], spheres[2][i]],
+            radius: spheres[3][i],
+        };
+
+        let mut hits = vec![];
+        aabb_aabb_pairs::<LANES>(&aabbs, &aabbs, |a, b| hits.push((a, b)));
+        let mut expected = vec![];
+        for a in 0..COUNT {
+            for b in (a + 1)..COUNT {
+                if intersections::aabb_aabb(to_aabb(a), to_aabb(b)) {
+                    expected.push((a, b));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(hits, expected);
+
+        let mut hits = vec![];
+        aabb_sphere_pairs::<LANES>(&aabbs, &spheres, |a, b| hits.push((a, b)));
+        let mut expected = vec![];
+        for a in 0..COUNT {
+            for b in 0..COUNT + 3 {
+                if intersections::aabb_sphere(to_aabb(a), to_sphere(b)) {
+                    expected.push((a, b));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(hits, expected);
+
+        let mut hits = vec![];
+        aabb_aabb_pairs::<LANES>(&aabbs, &aabbs.clone(), |a, b| hits.push((a, b)));
+        assert_eq!(
+            hits.len(),
+            expected_all_pairs(COUNT, |a, b| {
+                intersections::aabb_aabb(to_aabb(a), to_aabb(b))
+            })
+        );
+    }
+
+    #[test]
+    fn filtered_pairs_match_scalar() {
+        const LANES: usize = 8;
+        const RANGE: Range<f32> = -4.0..4.0;
+        const COUNT: usize = 83;
+        let mut rng = rand::thread_rng();
+        let (aabbs, aos_aabbs) = generate_test_data(COUNT, |_| {
+            [0.0, 0.0, 0.0, 1.0, 1.0, 1.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let groups: Vec<CollisionGroups> = (0..COUNT)
+            .map(|_| {
+                CollisionGroups::new(1 << rng.gen_range(0..3), rng.gen_range(0..8))
+            })
+            .collect();
+        let group_columns = [
+            groups.iter().map(|g| g.memberships).collect(),
+            groups.iter().map(|g| g.filter).collect(),
+        ];
+        let to_aabb = |i: usize| Aabb {
+            min: aos_aabbs[i][0],
+            max: aos_aabbs[i][1],
+        };
+
+        let mut hits = vec![];
+        let input = (&aabbs, &group_columns);
+        aabb_aabb_filtered_pairs::<LANES>(input, input, |a, b| hits.push((a, b)));
+        let mut expected = vec![];
+        let mut rejected = false;
+        for a in 0..COUNT {
+            for b in (a + 1)..COUNT {
+                if intersections::aabb_aabb(to_aabb(a), to_aabb(b)) {
+                    if groups[a].interacts(groups[b]) {
+                        expected.push((a, b));
+                    } else {
+                        rejected = true;
+                    }
+                }
+            }
+        }
+        assert!(!expected.is_empty() && rejected);
+        assert_eq!(hits, expected);
+    }
+
+    fn expected_all_pairs(
+        count: usize,
+        test: impl Fn(usize, usize) -> bool,
+    ) -> usize {
+        (0..count)
+            .flat_map(|a| (0..count).map(move |b| (a, b)))
+            .filter(|(a, b)| test(*a, *b))
+            .count()
+    }
+
+    #[test]
+    fn ray_pairs_match_scalar() {
+        const LANES: usize = 4;
+        const RANGE: Range<f32> = -4.0..4.0;
+        let mut rng = rand::thread_rng();
+        let (aabbs, aos_aabbs) = generate_test_data(37, |_| {
+            [0.0, 0.0, 0.0, 1.0, 1.0, 1.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let (rays, aos_rays) =
+            generate_test_data(9, |_| [0.0; 6].map(|_| rng.gen_range(RANGE)));
+
+        let mut hits = vec![];
+        ray_aabb_pairs::<LANES>(&rays, &aabbs, |r, a, t| hits.push((r, a, t)));
+        let mut expected = vec![];
+        for (r, ray) in aos_rays.iter().enumerate() {
+            for (a, aabb) in aos_aabbs.iter().enumerate() {
+                let ray = Ray {
+                    origin: ray[0],
+                    direction: ray[1],
+                };
+                let aabb = Aabb {
+                    min: aabb[0],
+                    max: aabb[1],
+                };
+                let t = intersections::ray_aabb_time(ray, aabb);
+                if t >= 0.0 {
+                    expected.push((r, a, t));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(hits, expected);
+    }
+}
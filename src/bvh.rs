@@ -0,0 +1,317 @@
+//! Bounding volume hierarchy over aabbs tagged with caller ids, built top
+//! down with median splits along the widest axis.
+
+use crate::intersections;
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::shapes::Aabb;
+use alloc::vec::Vec;
+
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+struct Node<T: Real> {
+    aabb: Aabb<T>,
+    /// First child for interior nodes (the second follows it), first leaf
+    /// otherwise.
+    start: usize,
+    /// Zero for interior nodes.
+    count: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bvh<T: Real = f32> {
+    nodes: Vec<Node<T>>,
+    leaves: Vec<(Aabb<T>, usize)>,
+}
+
+impl<T: Real> Bvh<T> {
+    pub fn new(items: impl IntoIterator<Item = (Aabb<T>, usize)>) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            leaves: items.into_iter().collect(),
+        };
+        if !bvh.leaves.is_empty() {
+            bvh.nodes.push(bvh.leaf(0, bvh.leaves.len()));
+            bvh.subdivide(0);
+        }
+        bvh
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    fn leaf(&self, start: usize, end: usize) -> Node<T> {
+        let aabb = self.leaves[start..end]
+            .iter()
+            .map(|(aabb, _)| *aabb)
+            .reduce(|a, b| a.merged(&b))
+            .unwrap();
+        Node {
+            aabb,
+            start,
+            count: end - start,
+        }
+    }
+
+    fn subdivide(&mut self, node: usize) {
+        let Node { start, count, .. } = self.nodes[node];
+        if count <= LEAF_SIZE {
+            return;
+        }
+        // Centroids are kept doubled, which doesn't change their order
+        let centroid = |aabb: &Aabb<T>, i: usize| aabb.min[i] + aabb.max[i];
+        let leaves = &mut self.leaves[start..start + count];
+        let (low, high) = leaves.iter().fold(
+            ([T::INFINITY; 3], [-T::INFINITY; 3]),
+            |(low, high), (aabb, _)| {
+                (
+                    core::array::from_fn(|i| low[i].min(centroid(aabb, i))),
+                    core::array::from_fn(|i| high[i].max(centroid(aabb, i))),
+                )
+            },
+        );
+        let axis = (0..3)
+            .max_by(|&a, &b| (high[a] - low[a]).total_cmp(&(high[b] - low[b])))
+            .unwrap();
+        let half = count / 2;
+        // Ties go by id, so the split doesn't depend on the selection
+        // algorithm or the order items came in
+        leaves.select_nth_unstable_by(half, |(a, a_id), (b, b_id)| {
+            centroid(a, axis)
+                .total_cmp(&centroid(b, axis))
+                .then(a_id.cmp(b_id))
+        });
+
+        let left = self.nodes.len();
+        self.nodes.push(self.leaf(start, start + half));
+        self.nodes.push(self.leaf(start + half, start + count));
+        self.nodes[node].start = left;
+        self.nodes[node].count = 0;
+        self.subdivide(left);
+        self.subdivide(left + 1);
+    }
+
+    /// Calls `hit` with the id of every item whose aabb passes `overlaps`.
+    /// `overlaps` is also used to prune the tree, so it has to accept any
+    /// aabb enclosing one it accepts.
+    pub fn query(
+        &self,
+        mut overlaps: impl FnMut(&Aabb<T>) -> bool,
+        mut hit: impl FnMut(usize),
+    ) {
+        if !self.nodes.is_empty() {
+            self.visit(0, &mut overlaps, &mut hit);
+        }
+    }
+
+    fn visit(
+        &self,
+        node: usize,
+        overlaps: &mut impl FnMut(&Aabb<T>) -> bool,
+        hit: &mut impl FnMut(usize),
+    ) {
+        let node = &self.nodes[node];
+        if !overlaps(&node.aabb) {
+            return;
+        }
+        if node.count > 0 {
+            for (aabb, id) in &self.leaves[node.start..node.start + node.count] {
+                if overlaps(aabb) {
+                    hit(*id);
+                }
+            }
+        } else {
+            self.visit(node.start, overlaps, hit);
+            self.visit(node.start + 1, overlaps, hit);
+        }
+    }
+
+    /// Finds the closest hit along `ray` up to `max_time`. Nodes are visited
+    /// front to back and skipped once they start past the closest hit so
+    /// far; `hit` returns the exact time of impact against an item, if any.
+    pub fn cast_ray(
+        &self,
+        ray: Ray<T>,
+        max_time: T,
+        mut hit: impl FnMut(usize) -> Option<T>,
+    ) -> Option<(usize, T)> {
+        let mut closest = (None, max_time);
+        if !self.nodes.is_empty() {
+            self.cast(0, ray, &mut closest, &mut hit);
+        }
+        closest.0.map(|id| (id, closest.1))
+    }
+
+    fn cast(
+        &self,
+        node: usize,
+        ray: Ray<T>,
+        closest: &mut (Option<usize>, T),
+        hit: &mut impl FnMut(usize) -> Option<T>,
+    ) {
+        let node = &self.nodes[node];
+        if node.count > 0 {
+            for (aabb, id) in &self.leaves[node.start..node.start + node.count] {
+                if entry_time(ray, aabb).is_some_and(|t| t <= closest.1) {
+                    match hit(*id) {
+                        Some(time) if time <= closest.1 => {
+                            *closest = (Some(*id), time)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            return;
+        }
+        let mut children = [node.start, node.start + 1]
+            .map(|child| (child, entry_time(ray, &self.nodes[child].aabb)));
+        if let [(_, Some(a)), (_, Some(b))] = children {
+            if b < a {
+                children.swap(0, 1);
+            }
+        }
+        for (child, entry) in children {
+            if entry.is_some_and(|t| t <= closest.1) {
+                self.cast(child, ray, closest, hit);
+            }
+        }
+    }
+
+    /// Calls `hit` once for every pair of items with overlapping aabbs.
+    pub fn pairs(&self, mut hit: impl FnMut(usize, usize)) {
+        if !self.nodes.is_empty() {
+            self.self_pairs(0, &mut hit);
+        }
+    }
+
+    fn self_pairs(&self, node: usize, hit: &mut impl FnMut(usize, usize)) {
+        let Node { start, count, .. } = self.nodes[node];
+        if count > 0 {
+            let leaves = &self.leaves[start..start + count];
+            for (i, (a, id_a)) in leaves.iter().enumerate() {
+                for (b, id_b) in &leaves[i + 1..] {
+                    if intersections::aabb_aabb(*a, *b) {
+                        hit(*id_a, *id_b);
+                    }
+                }
+            }
+        } else {
+            self.self_pairs(start, hit);
+            self.self_pairs(start + 1, hit);
+            self.cross_pairs(start, start + 1, hit);
+        }
+    }
+
+    fn cross_pairs(&self, a: usize, b: usize, hit: &mut impl FnMut(usize, usize)) {
+        let (node_a, node_b) = (&self.nodes[a], &self.nodes[b]);
+        if !intersections::aabb_aabb(node_a.aabb, node_b.aabb) {
+            return;
+        }
+        match (node_a.count > 0, node_b.count > 0) {
+            (true, true) => {
+                let leaves_b =
+                    &self.leaves[node_b.start..node_b.start + node_b.count];
+                for (aabb_a, id_a) in
+                    &self.leaves[node_a.start..node_a.start + node_a.count]
+                {
+                    for (aabb_b, id_b) in leaves_b {
+                        if intersections::aabb_aabb(*aabb_a, *aabb_b) {
+                            hit(*id_a, *id_b);
+                        }
+                    }
+                }
+            }
+            (false, true) => {
+                self.cross_pairs(node_a.start, b, hit);
+                self.cross_pairs(node_a.start + 1, b, hit);
+            }
+            _ => {
+                self.cross_pairs(a, node_b.start, hit);
+                self.cross_pairs(a, node_b.start + 1, hit);
+            }
+        }
+    }
+}
+
+/// Where `ray` enters `aabb`, or zero if it starts inside.
+fn entry_time<T: Real>(ray: Ray<T>, aabb: &Aabb<T>) -> Option<T> {
+    let (mut enter, mut exit) = (T::ZERO, T::INFINITY);
+    for i in 0..3 {
+        let inverse = T::ONE / ray.direction[i];
+        let t1 = (aabb.min[i] - ray.origin[i]) * inverse;
+        let t2 = (aabb.max[i] - ray.origin[i]) * inverse;
+        enter = enter.max(t1.min(t2));
+        exit = exit.min(t1.max(t2));
+    }
+    (enter <= exit).then_some(enter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::add;
+    use rand::Rng;
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = rand::thread_rng();
+        let aabbs: Vec<Aabb> = (0..500)
+            .map(|_| {
+                let min = [0.0; 3].map(|_| rng.gen_range(-30.0..30.0));
+                let size = [0.0; 3].map(|_| rng.gen_range(0.1..3.0));
+                Aabb::new(min, add(min, size))
+            })
+            .collect();
+        let bvh = Bvh::new(aabbs.iter().copied().zip(0..));
+        assert_eq!(bvh.len(), aabbs.len());
+
+        let mut pairs = vec![];
+        bvh.pairs(|a, b| pairs.push((a.min(b), a.max(b))));
+        pairs.sort_unstable();
+        let mut expected = vec![];
+        for i in 0..aabbs.len() {
+            for j in i + 1..aabbs.len() {
+                if intersections::aabb_aabb(aabbs[i], aabbs[j]) {
+                    expected.push((i, j));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(pairs, expected);
+
+        let area = Aabb::new([-5.0, -5.0, -5.0], [5.0, 5.0, 5.0]);
+        let mut found = vec![];
+        bvh.query(
+            |aabb| intersections::aabb_aabb(area, *aabb),
+            |id| found.push(id),
+        );
+        found.sort_unstable();
+        let expected: Vec<usize> = (0..aabbs.len())
+            .filter(|&i| intersections::aabb_aabb(area, aabbs[i]))
+            .collect();
+        assert_eq!(found, expected);
+
+        for _ in 0..50 {
+            let origin = [0.0; 3].map(|_| rng.gen_range(-40.0..40.0));
+            let direction = [0.0; 3].map(|_| rng.gen_range(-1.0..1.0));
+            let ray = Ray::new(origin, direction);
+            let time = |i: usize| {
+                let t = intersections::ray_aabb_time(ray, aabbs[i]);
+                (t >= 0.0).then_some(t)
+            };
+            let expected = (0..aabbs.len())
+                .filter_map(|i| time(i).map(|t| (i, t)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            assert_eq!(
+                bvh.cast_ray(ray, f32::INFINITY, time).map(|(_, t)| t),
+                expected.map(|(_, t)| t)
+            );
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! A kinematic capsule that walks through a `World` using shape casts,
+//! climbing steps and gentle slopes and sliding along everything else.
+
+use crate::contacts;
+use crate::isometry::Isometry;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::shapes::{Capsule, Shape};
+use crate::world::{ColliderHandle, QueryFilter, RayHit, World};
+use crate::Vec3;
+use alloc::vec::Vec;
+
+const DEPENETRATION_PASSES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharacterController<T: Real = f32> {
+    /// The character's shape, in its local space.
+    pub capsule: Capsule<T>,
+    /// Unit vector pointing away from gravity.
+    pub up: [T; 3],
+    /// Steepest slope, in radians, the character can stand on and walk up.
+    pub max_slope: T,
+    /// Tallest ledge the character steps onto without jumping.
+    pub step_height: T,
+    /// How far the character drops to stay on the ground when walking down
+    /// slopes and stairs.
+    pub snap_distance: T,
+    /// Gap kept between the capsule and whatever it slides along.
+    pub skin: T,
+    /// Most surfaces the character slides along in one move.
+    pub max_slides: usize,
+}
+
+/// Something the character ran into while moving.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharacterCollision<T: Real = f32> {
+    pub collider: ColliderHandle,
+    /// The collider's surface normal, facing the character.
+    pub normal: [T; 3],
+    /// Where the character was when it touched.
+    pub translation: [T; 3],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CharacterMovement<T: Real = f32> {
+    /// How far the character actually moved.
+    pub translation: [T; 3],
+    /// The walkable surface the character ended up standing on, if any.
+    pub ground: Option<CharacterCollision<T>>,
+    /// In the order they were hit.
+    pub collisions: Vec<CharacterCollision<T>>,
+}
+
+impl<T: Real> CharacterMovement<T> {
+    pub fn grounded(&self) -> bool {
+        self.ground.is_some()
+    }
+}
+
+impl<T: Real> CharacterController<T> {
+    pub fn new(capsule: Capsule<T>) -> Self {
+        Self {
+            capsule,
+            up: [T::ZERO, T::ONE, T::ZERO],
+            max_slope: T::PI / T::from_f64(4.0),
+            step_height: T::from_f64(0.3),
+            snap_distance: T::from_f64(0.3),
+            skin: T::from_f64(0.01),
+            max_slides: 4,
+        }
+    }
+
+    pub fn with_up(mut self, up: impl Into<Vec3<T>>) -> Self {
+        self.up = normalize(up.into().into());
+        self
+    }
+
+    pub fn with_max_slope(mut self, max_slope: T) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    pub fn with_step_height(mut self, step_height: T) -> Self {
+        self.step_height = step_height;
+        self
+    }
+
+    pub fn with_snap_distance(mut self, snap_distance: T) -> Self {
+        self.snap_distance = snap_distance;
+        self
+    }
+
+    /// Whether the character can stand on a surface facing `normal`.
+    pub fn walkable(&self, normal: [T; 3]) -> bool {
+        dot(normal, self.up) >= cos(self.max_slope) - T::from_f64(1e-4)
+    }
+
+    /// Moves the character at `transform` by up to `desired`, sliding along
+    /// the colliders passing `filter`, which should leave out the
+    /// character's own collider if it has one. The character is first
+    /// pushed out of anything it overlaps. `world` must be up to date.
+    pub fn move_and_slide(
+        &self,
+        world: &World<T>,
+        transform: &Isometry<T>,
+        desired: impl Into<Vec3<T>>,
+        filter: QueryFilter<T>,
+    ) -> CharacterMovement<T> {
+        let mut mover = Mover {
+            controller: self,
+            world,
+            rotation: transform.rotation,
+            shape: Shape::Capsule(self.capsule),
+            filter,
+            collisions: Vec::new(),
+        };
+        let start = transform.translation;
+        let desired: [T; 3] = desired.into().into();
+        let rise = dot(desired, self.up);
+        let vertical = scale(self.up, rise);
+        let horizontal = sub(desired, vertical);
+
+        let mut position = mover.depenetrate(start);
+        let was_grounded = mover.ground(position).is_some();
+        // Across first, so a step can be climbed before falling onto it
+        position = mover.walk(position, horizontal, rise <= T::ZERO);
+        position = mover.slide(position, vertical, Slide::Fall).0;
+        let mut ground = mover.ground(position);
+        if ground.is_none() && was_grounded && rise <= T::ZERO {
+            let drop = scale(self.up, -self.snap_distance);
+            if let Some(hit) = mover.cast(position, drop) {
+                if self.walkable(hit.normal) {
+                    position = mover.advance(position, drop, hit.time);
+                    ground = mover.ground(position);
+                }
+            }
+        }
+        CharacterMovement {
+            translation: sub(position, start),
+            ground,
+            collisions: mover.collisions,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Slide {
+    /// Sliding sideways, where too steep slopes act as walls.
+    Walk,
+    /// Moving along `up`, stopping on walkable ground.
+    Fall,
+}
+
+struct Mover<'a, T: Real> {
+    controller: &'a CharacterController<T>,
+    world: &'a World<T>,
+    rotation: [T; 4],
+    shape: Shape<T>,
+    filter: QueryFilter<'a, T>,
+    collisions: Vec<CharacterCollision<T>>,
+}
+
+impl<T: Real> Mover<'_, T> {
+    fn at(&self, position: [T; 3]) -> Isometry<T> {
+        Isometry {
+            translation: position,
+            rotation: self.rotation,
+        }
+    }
+
+    fn cast(&self, position: [T; 3], motion: [T; 3]) -> Option<RayHit<T>> {
+        let transform = self.at(position);
+        self.world
+            .cast_shape(&self.shape, &transform, motion, T::ONE, self.filter)
+    }
+
+    /// Where a cast along `motion` that hit at `time` stops, `skin` short of
+    /// the hit.
+    fn advance(&self, position: [T; 3], motion: [T; 3], time: T) -> [T; 3] {
+        let distance = length(motion);
+        let time = (time - self.controller.skin / distance).max(T::ZERO);
+        add(position, scale(motion, time))
+    }
+
+    fn collision(&self, position: [T; 3], hit: &RayHit<T>) -> CharacterCollision<T> {
+        CharacterCollision {
+            collider: hit.collider,
+            normal: hit.normal,
+            translation: position,
+        }
+    }
+
+    /// Pushes the capsule out of the deepest overlap a few times over.
+    fn depenetrate(&self, mut position: [T; 3]) -> [T; 3] {
+        for _ in 0..DEPENETRATION_PASSES {
+            let transform = self.at(position);
+            let mut deepest: Option<([T; 3], T)> = None;
+            self.world.intersect_shape(
+                &self.shape,
+                &transform,
+                self.filter,
+                |handle| {
+                    let collider = self.world.get(handle).unwrap();
+                    let Some(manifold) = contacts::contact(
+                        &self.shape,
+                        &transform,
+                        &collider.shape,
+                        &collider.transform,
+                    ) else {
+                        return;
+                    };
+                    let depth = manifold.max_depth();
+                    if deepest.is_none_or(|(_, d)| depth > d) {
+                        deepest = Some((manifold.normal, depth));
+                    }
+                },
+            );
+            let Some((normal, depth)) = deepest else {
+                break;
+            };
+            let push = depth + self.controller.skin * T::HALF;
+            position = sub(position, scale(normal, push));
+        }
+        position
+    }
+
+    /// Moves along `motion`, sliding along whatever gets in the way. Also
+    /// returns whether a wall blocked the way.
+    fn slide(
+        &mut self,
+        mut position: [T; 3],
+        motion: [T; 3],
+        mode: Slide,
+    ) -> ([T; 3], bool) {
+        let controller = self.controller;
+        let up = controller.up;
+        let mut remaining = motion;
+        let mut blocked = false;
+        let mut previous: Option<[T; 3]> = None;
+        for _ in 0..controller.max_slides {
+            if length(remaining) <= T::EPSILON {
+                break;
+            }
+            let Some(hit) = self.cast(position, remaining) else {
+                position = add(position, remaining);
+                break;
+            };
+            position = self.advance(position, remaining, hit.time);
+            self.collisions.push(self.collision(position, &hit));
+            let walkable = controller.walkable(hit.normal);
+            if mode == Slide::Fall && walkable {
+                break;
+            }
+            let mut normal = hit.normal;
+            if mode == Slide::Walk && !walkable {
+                blocked = true;
+                // Too steep to climb, so only its sideways push counts
+                let flat = sub(normal, scale(up, dot(normal, up)));
+                if length(flat) > T::EPSILON {
+                    normal = normalize(flat);
+                }
+            }
+            let left = scale(remaining, T::ONE - hit.time.min(T::ONE));
+            let mut projected = sub(left, scale(normal, dot(left, normal)));
+            // Wedged between two surfaces, so follow the crease between them
+            if let Some(previous) = previous.filter(|p| dot(projected, *p) < T::ZERO)
+            {
+                let crease = cross(previous, normal);
+                let crease_length = length(crease);
+                projected = if crease_length > T::EPSILON {
+                    let crease = scale(crease, T::ONE / crease_length);
+                    scale(crease, dot(left, crease))
+                } else {
+                    [T::ZERO; 3]
+                };
+            }
+            previous = Some(normal);
+            remaining = projected;
+        }
+        (position, blocked)
+    }
+
+    /// Slides sideways, stepping up onto ledges no taller than `step_height`
+    /// when that gets further.
+    fn walk(&mut self, position: [T; 3], motion: [T; 3], can_step: bool) -> [T; 3] {
+        let controller = self.controller;
+        let collisions = self.collisions.len();
+        let (walked, blocked) = self.slide(position, motion, Slide::Walk);
+        if !can_step || !blocked || controller.step_height <= T::ZERO {
+            return walked;
+        }
+        let walked_collisions = self.collisions.split_off(collisions);
+
+        let lift = scale(controller.up, controller.step_height);
+        let raised = match self.cast(position, lift) {
+            Some(hit) => self.advance(position, lift, hit.time),
+            None => add(position, lift),
+        };
+        let (across, _) = self.slide(raised, motion, Slide::Walk);
+        let drop = sub(position, raised);
+        let landed = self
+            .cast(across, drop)
+            .filter(|hit| self.stands_on(position, across, drop, hit))
+            .map(|hit| self.advance(across, drop, hit.time));
+
+        let direction = normalize(motion);
+        let progress = |p: [T; 3]| dot(sub(p, position), direction);
+        match landed {
+            Some(landed)
+                if progress(landed) > progress(walked) + T::from_f64(1e-4) =>
+            {
+                landed
+            }
+            _ => {
+                self.collisions.truncate(collisions);
+                self.collisions.extend(walked_collisions);
+                walked
+            }
+        }
+    }
+
+    /// Whether a capsule stepping up from `from` and dropped from `position`
+    /// along `drop` lands on walkable ground no higher than `step_height`.
+    /// Landing on the very edge of a step gives a rounded normal, so the
+    /// surface just past the touching point gets a second look with a ray.
+    fn stands_on(
+        &self,
+        from: [T; 3],
+        position: [T; 3],
+        drop: [T; 3],
+        hit: &RayHit<T>,
+    ) -> bool {
+        let controller = self.controller;
+        let up = controller.up;
+        let (feet, _) = self.bottom(from);
+        let (bottom, radius) = self.bottom(add(position, scale(drop, hit.time)));
+        let touching = sub(bottom, scale(hit.normal, radius));
+        let height = dot(sub(touching, feet), up) + radius;
+        if height > controller.step_height + controller.skin {
+            return false;
+        }
+        if controller.walkable(hit.normal) {
+            return true;
+        }
+        let inwards = sub(scale(up, dot(hit.normal, up)), hit.normal);
+        if length(inwards) <= T::EPSILON {
+            return false;
+        }
+        let origin = add(
+            add(touching, scale(normalize(inwards), controller.skin)),
+            scale(up, controller.skin),
+        );
+        let ray = Ray::new(origin, scale(up, -T::ONE));
+        self.world
+            .cast_ray(ray, controller.skin * T::TWO, self.filter)
+            .is_some_and(|ground| controller.walkable(ground.normal))
+    }
+
+    /// The centre of the capsule's lower cap at `position`, and its radius.
+    fn bottom(&self, position: [T; 3]) -> ([T; 3], T) {
+        let up = self.controller.up;
+        let capsule = self.controller.capsule.transformed(&self.at(position));
+        let bottom = if dot(capsule.a, up) < dot(capsule.b, up) {
+            capsule.a
+        } else {
+            capsule.b
+        };
+        (bottom, capsule.radius)
+    }
+
+    /// The walkable surface right under the capsule, if there is one.
+    fn ground(&self, position: [T; 3]) -> Option<CharacterCollision<T>> {
+        let probe = scale(self.controller.up, -self.controller.skin * T::TWO);
+        self.cast(position, probe)
+            .filter(|hit| self.controller.walkable(hit.normal))
+            .map(|hit| self.collision(position, &hit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Aabb, Obb};
+    use crate::world::Collider;
+
+    fn level() -> (World, ColliderHandle) {
+        let mut world = World::new();
+        let ground = world.insert(Collider::new(
+            Aabb::new([-10.0, -1.0, -10.0], [10.0, 0.0, 10.0]),
+            Isometry::identity(),
+        ));
+        (world, ground)
+    }
+
+    /// A slope rising along x from the ground at x = 1.
+    fn ramp(world: &mut World, angle: f32) {
+        let (sin, cos) = angle.sin_cos();
+        let center = [1.0 + 5.0 * cos + 0.5 * sin, 5.0 * sin - 0.5 * cos, 0.0];
+        let rotation = Isometry::from_axis_angle([0.0, 0.0, 1.0], angle).rotation;
+        world.insert(Collider::new(
+            Obb::from_rotation(center, [5.0, 0.5, 5.0], rotation),
+            Isometry::identity(),
+        ));
+    }
+
+    /// Walks by `step` with a little gravity `count` times.
+    fn walk(
+        controller: &CharacterController,
+        world: &World,
+        mut position: [f32; 3],
+        step: [f32; 3],
+        count: usize,
+    ) -> ([f32; 3], CharacterMovement) {
+        let mut movement = None;
+        for _ in 0..count {
+            let moved = controller.move_and_slide(
+                world,
+                &Isometry::from_translation(position),
+                add(step, [0.0, -0.05, 0.0]),
+                QueryFilter::default(),
+            );
+            position = add(position, moved.translation);
+            movement = Some(moved);
+        }
+        (position, movement.unwrap())
+    }
+
+    #[test]
+    fn walls_and_ground() {
+        let (mut world, ground) = level();
+        let wall = world.insert(Collider::new(
+            Aabb::new([2.0, 0.0, -10.0], [3.0, 3.0, 10.0]),
+            Isometry::identity(),
+        ));
+        world.update();
+        let controller = CharacterController::new(Capsule::upright(0.5, 0.3));
+        let all = QueryFilter::default();
+
+        let falling = Isometry::from_translation([0.0, 3.0, 0.0]);
+        let moved =
+            controller.move_and_slide(&world, &falling, [0.0, -5.0, 0.0], all);
+        assert!((moved.translation[1] + 2.2).abs() < 0.02, "{:?}", moved);
+        assert_eq!(moved.collisions[0].collider, ground);
+        assert_eq!(moved.ground.unwrap().normal, [0.0, 1.0, 0.0]);
+
+        let standing = Isometry::from_translation([0.0, 0.81, 0.0]);
+        let moved =
+            controller.move_and_slide(&world, &standing, [3.0, 0.0, 1.0], all);
+        assert!((moved.translation[0] - 1.69).abs() < 0.02, "{:?}", moved);
+        assert!((moved.translation[2] - 1.0).abs() < 0.02);
+        assert!(moved.translation[1].abs() < 1e-3);
+        assert!(moved.collisions.iter().any(|c| c.collider == wall));
+        assert!(moved.grounded());
+
+        // Sunk into the floor, then pushed back out
+        let sunk = Isometry::from_translation([0.0, 0.6, 0.0]);
+        let moved = controller.move_and_slide(&world, &sunk, [0.0; 3], all);
+        assert!(moved.translation[1] > 0.2 && moved.translation[1] < 0.25);
+        assert!(moved.grounded());
+
+        let jumping =
+            controller.move_and_slide(&world, &standing, [0.0, 1.0, 0.0], all);
+        assert!(!jumping.grounded());
+        assert!(jumping.collisions.is_empty());
+    }
+
+    #[test]
+    fn steps_and_slopes() {
+        let controller = CharacterController::new(Capsule::upright(0.5, 0.3));
+        let start = [0.0, 0.81, 0.0];
+        let forwards = [0.1, 0.0, 0.0];
+
+        for (height, climbs) in [(0.25, true), (0.5, false)] {
+            let (mut world, _) = level();
+            world.insert(Collider::new(
+                Aabb::new([1.0, 0.0, -5.0], [3.0, height, 5.0]),
+                Isometry::identity(),
+            ));
+            world.update();
+            let (position, movement) =
+                walk(&controller, &world, start, forwards, 20);
+            assert!(movement.grounded());
+            if climbs {
+                assert!(position[0] > 1.5, "{:?}", position);
+                assert!((position[1] - 1.06).abs() < 0.02, "{:?}", position);
+            } else {
+                assert!(position[0] < 0.7, "{:?}", position);
+            }
+        }
+
+        for (degrees, climbs) in [(20.0f32, true), (60.0, false)] {
+            let (mut world, _) = level();
+            ramp(&mut world, degrees.to_radians());
+            world.update();
+            let (position, movement) =
+                walk(&controller, &world, start, forwards, 40);
+            if climbs {
+                assert!(position[0] > 3.0, "{:?}", position);
+                assert!(position[1] > 1.5, "{:?}", position);
+                assert!(movement.grounded());
+                assert!(movement.ground.unwrap().normal[0] < -0.3);
+            } else {
+                assert!(position[0] < 1.0, "{:?}", position);
+            }
+        }
+
+        // Walking quickly down a slope stays on it rather than launching off
+        let (mut world, _) = level();
+        ramp(&mut world, 20f32.to_radians());
+        world.update();
+        let (top, _) = walk(&controller, &world, [4.0, 3.0, 0.0], [0.0; 3], 60);
+        let mut position = top;
+        for _ in 0..10 {
+            let moved = controller.move_and_slide(
+                &world,
+                &Isometry::from_translation(position),
+                [-0.25, 0.0, 0.0],
+                QueryFilter::default(),
+            );
+            assert!(moved.grounded(), "{:?}", position);
+            position = add(position, moved.translation);
+        }
+        assert!(position[0] < 2.0);
+        let unsnapped = controller.with_snap_distance(0.0).move_and_slide(
+            &world,
+            &Isometry::from_translation(top),
+            [-0.25, 0.0, 0.0],
+            QueryFilter::default(),
+        );
+        assert!(!unsnapped.grounded());
+    }
+}
@@ -0,0 +1,1000 @@
+//! Contact manifolds between overlapping shapes, used by the dynamics
+//! solver.
+
+use crate::heightfield::Heightfield;
+use crate::intersections::{
+    closest_point_on_obb, closest_point_on_segment, closest_point_on_triangle,
+    closest_points_segment_obb, closest_points_segment_triangle,
+    closest_points_segments, distance_squared,
+};
+use crate::isometry::Isometry;
+use crate::math::*;
+use crate::real::Real;
+use crate::shapes::{Aabb, Capsule, Obb, Placed, Shape, Sphere, Triangle};
+use crate::voxels::VoxelGrid;
+use alloc::vec;
+use alloc::vec::Vec;
+
+const MAX_POINTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContactPoint<T: Real = f32> {
+    /// Halfway between the two surfaces, in world space.
+    pub point: [T; 3],
+    /// How far the shapes overlap along the normal.
+    pub depth: T,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContactManifold<T: Real = f32> {
+    /// Unit normal pointing from the first shape towards the second.
+    pub normal: [T; 3],
+    /// One to four points sharing `normal`, `contact` never returns an empty
+    /// manifold.
+    pub points: Vec<ContactPoint<T>>,
+}
+
+impl<T: Real> ContactManifold<T> {
+    pub(crate) fn single(normal: [T; 3], point: [T; 3], depth: T) -> Self {
+        Self {
+            normal,
+            points: vec![ContactPoint { point, depth }],
+        }
+    }
+
+    fn flipped(mut self) -> Self {
+        self.normal = scale(self.normal, -T::ONE);
+        self
+    }
+
+    pub fn max_depth(&self) -> T {
+        self.points
+            .iter()
+            .map(|p| p.depth)
+            .fold(-T::INFINITY, T::max)
+    }
+}
+
+/// The contact manifold between `a` and `b` placed at `a_iso` and `b_iso`,
+/// or `None` if they don't touch. Heightfields and voxel grids are static
+/// terrain, so a pair of them never gets a manifold even when they overlap;
+/// `Shape::intersects` tells whether they do.
+pub fn contact<T: Real>(
+    a: &Shape<T>,
+    a_iso: &Isometry<T>,
+    b: &Shape<T>,
+    b_iso: &Isometry<T>,
+) -> Option<ContactManifold<T>> {
+    use Placed::*;
+    match (a.placed(a_iso), b.placed(b_iso)) {
+        (Heightfield(..) | VoxelGrid(..), Heightfield(..) | VoxelGrid(..)) => None,
+        (Heightfield(field, field_iso), _) => {
+            heightfield(field, &field_iso, b, b_iso)
+        }
+        (_, Heightfield(field, field_iso)) => {
+            heightfield(field, &field_iso, a, a_iso).map(ContactManifold::flipped)
+        }
+        (VoxelGrid(grid, grid_iso), _) => voxel_grid(grid, &grid_iso, b, b_iso),
+        (_, VoxelGrid(grid, grid_iso)) => {
+            voxel_grid(grid, &grid_iso, a, a_iso).map(ContactManifold::flipped)
+        }
+        (a, b) => convex(a, b),
+    }
+}
+
+/// The contact manifold between two convex shapes.
+pub(crate) fn convex<T: Real>(
+    a: Placed<'_, T>,
+    b: Placed<'_, T>,
+) -> Option<ContactManifold<T>> {
+    use Placed::*;
+    match (a, b) {
+        (Sphere(a), Sphere(b)) => sphere_sphere(a, b),
+        (Obb(a), Sphere(b)) => obb_sphere(a, b),
+        (Sphere(a), Obb(b)) => obb_sphere(b, a).map(ContactManifold::flipped),
+        (Capsule(a), Sphere(b)) => capsule_sphere(a, b),
+        (Sphere(a), Capsule(b)) => {
+            capsule_sphere(b, a).map(ContactManifold::flipped)
+        }
+        (Capsule(a), Capsule(b)) => capsule_capsule(a, b),
+        (Obb(a), Capsule(b)) => obb_capsule(a, b),
+        (Capsule(a), Obb(b)) => obb_capsule(b, a).map(ContactManifold::flipped),
+        (Obb(a), Obb(b)) => obb_obb(a, b),
+        (Heightfield(..) | VoxelGrid(..), _)
+        | (_, Heightfield(..) | VoxelGrid(..)) => None,
+    }
+}
+
+pub fn sphere_sphere<T: Real>(
+    a: Sphere<T>,
+    b: Sphere<T>,
+) -> Option<ContactManifold<T>> {
+    let offset = sub(b.center, a.center);
+    let distance = length(offset);
+    let depth = a.radius + b.radius - distance;
+    if depth < T::ZERO {
+        return None;
+    }
+    let normal = if distance > T::EPSILON {
+        scale(offset, T::ONE / distance)
+    } else {
+        [T::ZERO, T::ONE, T::ZERO]
+    };
+    let point = add(a.center, scale(normal, a.radius - depth * T::HALF));
+    Some(ContactManifold::single(normal, point, depth))
+}
+
+pub fn obb_sphere<T: Real>(
+    obb: Obb<T>,
+    sphere: Sphere<T>,
+) -> Option<ContactManifold<T>> {
+    let closest = closest_point_on_obb(obb, sphere.center);
+    let offset = sub(sphere.center, closest);
+    let distance = length(offset);
+    if distance > T::EPSILON {
+        let depth = sphere.radius - distance;
+        if depth < T::ZERO {
+            return None;
+        }
+        let normal = scale(offset, T::ONE / distance);
+        let point = sub(closest, scale(normal, depth * T::HALF));
+        return Some(ContactManifold::single(normal, point, depth));
+    }
+    // The centre is inside, so push out through the nearest face
+    let local = obb
+        .axes
+        .map(|axis| dot(sub(sphere.center, obb.center), axis));
+    let face = (0..3)
+        .min_by(|&i, &j| {
+            let gap = |k: usize| obb.half_extents[k] - local[k].abs();
+            gap(i).total_cmp(&gap(j))
+        })
+        .unwrap();
+    let gap = obb.half_extents[face] - local[face].abs();
+    let normal = scale(obb.axes[face], local[face].signum());
+    let point = add(
+        sphere.center,
+        scale(normal, (gap - sphere.radius) * T::HALF),
+    );
+    Some(ContactManifold::single(normal, point, sphere.radius + gap))
+}
+
+pub fn capsule_sphere<T: Real>(
+    capsule: Capsule<T>,
+    sphere: Sphere<T>,
+) -> Option<ContactManifold<T>> {
+    let core = closest_point_on_segment(capsule.a, capsule.b, sphere.center);
+    sphere_sphere(Sphere::new(core, capsule.radius), sphere)
+}
+
+pub fn capsule_capsule<T: Real>(
+    a: Capsule<T>,
+    b: Capsule<T>,
+) -> Option<ContactManifold<T>> {
+    let (p, q) = closest_points_segments((a.a, a.b), (b.a, b.b));
+    sphere_sphere(Sphere::new(p, a.radius), Sphere::new(q, b.radius))
+}
+
+/// The closest point of the capsule's core decides the normal, and end caps
+/// touching the same face add points so a lying capsule doesn't rock.
+pub fn obb_capsule<T: Real>(
+    obb: Obb<T>,
+    capsule: Capsule<T>,
+) -> Option<ContactManifold<T>> {
+    let (core, _) = closest_points_segment_obb((capsule.a, capsule.b), obb);
+    let mut manifold = obb_sphere(obb, Sphere::new(core, capsule.radius))?;
+    let caps = [capsule.a, capsule.b]
+        .map(|end| obb_sphere(obb, Sphere::new(end, capsule.radius)));
+    add_cap_points(&mut manifold, capsule.radius, caps.into_iter().flatten());
+    Some(manifold)
+}
+
+/// Adds the points of `caps` pushing the same way as `manifold` and not
+/// already in it, for capsules of `radius` touching a face along their length.
+pub(crate) fn add_cap_points<T: Real>(
+    manifold: &mut ContactManifold<T>,
+    radius: T,
+    caps: impl IntoIterator<Item = ContactManifold<T>>,
+) {
+    let same_face = T::from_f64(0.95);
+    let distinct = T::from_f64(1e-4) * radius * radius;
+    for cap in caps {
+        let point = cap.points[0];
+        if dot(cap.normal, manifold.normal) > same_face
+            && manifold
+                .points
+                .iter()
+                .all(|p| distance_squared(p.point, point.point) > distinct)
+        {
+            manifold.points.push(point);
+        }
+    }
+}
+
+/// Contacts with the triangles of `field` under `shape`, worked out in the
+/// heightfield's local space. The deepest triangle decides the normal, and
+/// the others pushing the same way add their points.
+fn heightfield<T: Real>(
+    field: &Heightfield<T>,
+    field_iso: &Isometry<T>,
+    shape: &Shape<T>,
+    iso: &Isometry<T>,
+) -> Option<ContactManifold<T>> {
+    let local = field_iso.inverse() * *iso;
+    let placed = shape.placed(&local);
+    let manifolds = field
+        .triangles(&shape.aabb(&local))
+        .filter_map(|triangle| triangle_contact(triangle, placed))
+        .collect();
+    merged(manifolds, field_iso)
+}
+
+/// Contacts with the occupied cells of `grid` overlapping `shape`, worked out
+/// in the grid's local space and merged like `heightfield`. A shape buried so
+/// deep that every contact points into the solid is pushed out through the
+/// nearest open face instead, or along the deepest contact when none of the
+/// cells it overlaps has one.
+fn voxel_grid<T: Real>(
+    grid: &VoxelGrid<T>,
+    grid_iso: &Isometry<T>,
+    shape: &Shape<T>,
+    iso: &Isometry<T>,
+) -> Option<ContactManifold<T>> {
+    let local = grid_iso.inverse() * *iso;
+    let placed = shape.placed(&local);
+    let bounds = shape.aabb(&local);
+    let touching: Vec<_> = grid
+        .overlapping(&bounds)
+        .filter_map(|cell| {
+            let cube = Placed::Obb(Obb::from(grid.cell_aabb(cell)));
+            Some((cell, convex(cube, placed)?))
+        })
+        .collect();
+    let outside: Vec<_> = touching
+        .iter()
+        .filter(|(cell, manifold)| !faces_solid(grid, *cell, manifold.normal))
+        .map(|(_, manifold)| manifold.clone())
+        .collect();
+    if !outside.is_empty() || touching.is_empty() {
+        return merged(outside, grid_iso);
+    }
+    let cells = touching.iter().map(|(cell, _)| *cell);
+    match nearest_open_face(grid, cells, &bounds) {
+        Some(manifold) => merged(vec![manifold], grid_iso),
+        None => merged(touching.into_iter().map(|(_, m)| m).collect(), grid_iso),
+    }
+}
+
+/// The shallowest way out of `cells` for a shape inside `bounds`, through a
+/// face without an occupied cell behind it.
+fn nearest_open_face<T: Real>(
+    grid: &VoxelGrid<T>,
+    cells: impl Iterator<Item = [i32; 3]>,
+    bounds: &Aabb<T>,
+) -> Option<ContactManifold<T>> {
+    let center = bounds.center();
+    let mut best: Option<(T, ContactManifold<T>)> = None;
+    for cell in cells {
+        let cube = grid.cell_aabb(cell);
+        for axis in 0..3 {
+            for step in [1, -1] {
+                let mut neighbour = cell;
+                neighbour[axis] += step;
+                if grid.is_occupied(neighbour) {
+                    continue;
+                }
+                let (depth, face) = if step > 0 {
+                    (cube.max[axis] - bounds.min[axis], cube.max[axis])
+                } else {
+                    (bounds.max[axis] - cube.min[axis], cube.min[axis])
+                };
+                if best.as_ref().is_some_and(|(best, _)| *best <= depth) {
+                    continue;
+                }
+                let mut normal = [T::ZERO; 3];
+                normal[axis] = T::from_f64(step as f64);
+                let mut point = center;
+                point[axis] = face - normal[axis] * depth * T::HALF;
+                best = Some((depth, ContactManifold::single(normal, point, depth)));
+            }
+        }
+    }
+    best.map(|(_, manifold)| manifold)
+}
+
+/// Whether the neighbour of `cell` along the dominant axis of `normal` is
+/// occupied.
+fn faces_solid<T: Real>(
+    grid: &VoxelGrid<T>,
+    cell: [i32; 3],
+    normal: [T; 3],
+) -> bool {
+    let axis = (0..3)
+        .max_by(|&i, &j| normal[i].abs().total_cmp(&normal[j].abs()))
+        .unwrap();
+    let mut neighbour = cell;
+    neighbour[axis] += if normal[axis] > T::ZERO { 1 } else { -1 };
+    grid.is_occupied(neighbour)
+}
+
+/// The deepest of `manifolds` decides the normal, and the others pushing the
+/// same way add their points before the result is moved into world space.
+fn merged<T: Real>(
+    manifolds: Vec<ContactManifold<T>>,
+    iso: &Isometry<T>,
+) -> Option<ContactManifold<T>> {
+    let deepest = manifolds
+        .iter()
+        .max_by(|a, b| a.max_depth().total_cmp(&b.max_depth()))?;
+    let same_face = T::from_f64(0.95);
+    let mut points: Vec<ContactPoint<T>> = Vec::new();
+    for manifold in &manifolds {
+        if dot(manifold.normal, deepest.normal) <= same_face {
+            continue;
+        }
+        for point in &manifold.points {
+            // Neighbouring triangles and cells share edges and corners
+            if points
+                .iter()
+                .all(|p| distance_squared(p.point, point.point) > T::EPSILON)
+            {
+                points.push(*point);
+            }
+        }
+    }
+    reduce(&mut points);
+    Some(ContactManifold {
+        normal: iso.transform_vector(deepest.normal),
+        points: points
+            .into_iter()
+            .map(|p| ContactPoint {
+                point: iso.transform_point(p.point),
+                ..p
+            })
+            .collect(),
+    })
+}
+
+/// The contact between one cell of a voxel grid and a convex shape. Faces
+/// shared with an occupied neighbour are inside the solid, so contacts
+/// pushing out through them are dropped and boxes slide over seams.
+pub(crate) fn voxel_contact<T: Real>(
+    grid: &VoxelGrid<T>,
+    cell: [i32; 3],
+    placed: Placed<'_, T>,
+) -> Option<ContactManifold<T>> {
+    let cube = Placed::Obb(Obb::from(grid.cell_aabb(cell)));
+    let manifold = convex(cube, placed)?;
+    (!faces_solid(grid, cell, manifold.normal)).then_some(manifold)
+}
+
+/// The contact between a triangle of a heightfield and a convex shape. Only
+/// the side the normal faces is open, so shapes sunk below the triangle are
+/// pushed back out along it.
+pub(crate) fn triangle_contact<T: Real>(
+    triangle: Triangle<T>,
+    placed: Placed<'_, T>,
+) -> Option<ContactManifold<T>> {
+    match placed {
+        Placed::Sphere(sphere) => triangle_sphere(triangle, sphere),
+        Placed::Capsule(capsule) => triangle_capsule(triangle, capsule),
+        Placed::Obb(obb) => triangle_obb(triangle, obb),
+        Placed::Heightfield(..) | Placed::VoxelGrid(..) => None,
+    }
+}
+
+/// A sphere above the triangle touches its closest point, while one whose
+/// centre has sunk below it is pushed straight back up through the face.
+pub fn triangle_sphere<T: Real>(
+    triangle: Triangle<T>,
+    sphere: Sphere<T>,
+) -> Option<ContactManifold<T>> {
+    let face: [T; 3] = triangle.normal();
+    let height = dot(sub(sphere.center, triangle.a), face);
+    let projected = sub(sphere.center, scale(face, height));
+    let vertices = triangle.vertices();
+    let inside = (0..3).all(|i| {
+        let (v0, v1) = (vertices[i], vertices[(i + 1) % 3]);
+        dot(cross(sub(v1, v0), sub(projected, v0)), face) >= T::ZERO
+    });
+    let (normal, surface, gap) = if inside {
+        (face, projected, height)
+    } else {
+        let closest = closest_point_on_triangle(triangle, sphere.center);
+        let offset = sub(sphere.center, closest);
+        let distance = length(offset);
+        if height < T::ZERO || distance <= T::EPSILON {
+            return None;
+        }
+        (scale(offset, T::ONE / distance), closest, distance)
+    };
+    let depth = sphere.radius - gap;
+    if depth < T::ZERO {
+        return None;
+    }
+    let point = sub(surface, scale(normal, depth * T::HALF));
+    Some(ContactManifold::single(normal, point, depth))
+}
+
+/// Like `obb_capsule`, the closest point of the core decides the normal and
+/// end caps pushed the same way add points.
+pub fn triangle_capsule<T: Real>(
+    triangle: Triangle<T>,
+    capsule: Capsule<T>,
+) -> Option<ContactManifold<T>> {
+    let (core, _) =
+        closest_points_segment_triangle((capsule.a, capsule.b), triangle);
+    let mut manifold = triangle_sphere(triangle, Sphere::new(core, capsule.radius))?;
+    let caps = [capsule.a, capsule.b]
+        .map(|end| triangle_sphere(triangle, Sphere::new(end, capsule.radius)));
+    add_cap_points(&mut manifold, capsule.radius, caps.into_iter().flatten());
+    Some(manifold)
+}
+
+enum TriangleFeature {
+    Face,
+    BoxFace(usize),
+    Edge { side: usize, box_axis: usize },
+}
+
+/// Separating axis test over the triangle's face, the box's faces and the
+/// crossings of their edges. The triangle's face only pushes out the way it
+/// faces, and the other axes only count when they push that way too, so
+/// boxes never catch on the edges between neighbouring triangles.
+pub fn triangle_obb<T: Real>(
+    triangle: Triangle<T>,
+    obb: Obb<T>,
+) -> Option<ContactManifold<T>> {
+    let vertices = triangle.vertices();
+    let face: [T; 3] = triangle.normal();
+    let project = |axis: [T; 3]| {
+        (0..3)
+            .map(|k| obb.half_extents[k] * dot(obb.axes[k], axis).abs())
+            .sum::<T>()
+    };
+    // Overlap along `axis`, turned to push the box out of the triangle
+    let overlap = |axis: [T; 3]| {
+        let (low, high) = vertices
+            .iter()
+            .map(|v| dot(*v, axis))
+            .fold((T::INFINITY, -T::INFINITY), |(low, high), d| {
+                (low.min(d), high.max(d))
+            });
+        let (center, radius) = (dot(obb.center, axis), project(axis));
+        let (forward, backward) = (high - (center - radius), center + radius - low);
+        if forward <= backward {
+            (forward, axis)
+        } else {
+            (backward, scale(axis, -T::ONE))
+        }
+    };
+
+    let lowest = dot(obb.center, face) - project(face);
+    let depth = dot(triangle.a, face) - lowest;
+    if depth < T::ZERO {
+        return None;
+    }
+    let mut best = (depth, face, TriangleFeature::Face);
+    for axis in 0..3 {
+        let (depth, normal) = overlap(obb.axes[axis]);
+        if depth < T::ZERO {
+            return None;
+        }
+        if dot(normal, face) > T::ZERO
+            && depth < best.0 * T::from_f64(0.95) - T::from_f64(1e-3)
+        {
+            best = (depth, normal, TriangleFeature::BoxFace(axis));
+        }
+    }
+    let mut edge: Option<(T, [T; 3], TriangleFeature)> = None;
+    for side in 0..3 {
+        let (v0, v1) = (vertices[side], vertices[(side + 1) % 3]);
+        for box_axis in 0..3 {
+            let axis = cross(sub(v1, v0), obb.axes[box_axis]);
+            let axis_length = length(axis);
+            if axis_length < T::from_f64(1e-6) {
+                continue;
+            }
+            let (depth, normal) = overlap(scale(axis, T::ONE / axis_length));
+            if depth < T::ZERO {
+                return None;
+            }
+            if dot(normal, face) > T::ZERO
+                && edge.as_ref().is_none_or(|(best, ..)| depth < *best)
+            {
+                let feature = TriangleFeature::Edge { side, box_axis };
+                edge = Some((depth, normal, feature));
+            }
+        }
+    }
+    let (depth, normal, feature) = match edge {
+        Some(edge) if edge.0 < best.0 * T::from_f64(0.95) - T::from_f64(1e-3) => {
+            edge
+        }
+        _ => best,
+    };
+
+    match feature {
+        // Clip the box's face most opposed to the triangle's against the
+        // triangle's edges
+        TriangleFeature::Face => {
+            let incident = (0..3)
+                .max_by(|&i, &j| {
+                    let facing = |k: usize| dot(obb.axes[k], normal).abs();
+                    facing(i).total_cmp(&facing(j))
+                })
+                .unwrap();
+            let side = -dot(obb.axes[incident], normal).signum();
+            let center = add(
+                obb.center,
+                scale(obb.axes[incident], obb.half_extents[incident] * side),
+            );
+            let (k1, k2) = ((incident + 1) % 3, (incident + 2) % 3);
+            let u = scale(obb.axes[k1], obb.half_extents[k1]);
+            let v = scale(obb.axes[k2], obb.half_extents[k2]);
+            let mut polygon = vec![
+                add(add(center, u), v),
+                add(sub(center, u), v),
+                sub(sub(center, u), v),
+                sub(add(center, u), v),
+            ];
+            for i in 0..3 {
+                let (v0, v1) = (vertices[i], vertices[(i + 1) % 3]);
+                let outward = cross(sub(v1, v0), normal);
+                polygon = clip(&polygon, |p| dot(sub(p, v0), outward));
+            }
+            let points = polygon
+                .into_iter()
+                .filter_map(|p| {
+                    let depth = dot(sub(triangle.a, p), normal);
+                    (depth >= T::ZERO).then(|| ContactPoint {
+                        point: add(p, scale(normal, depth * T::HALF)),
+                        depth,
+                    })
+                })
+                .collect();
+            let corner = (0..3).fold(obb.center, |p, k| {
+                let side = -dot(obb.axes[k], normal).signum();
+                add(p, scale(obb.axes[k], obb.half_extents[k] * side))
+            });
+            let deepest = add(corner, scale(normal, depth * T::HALF));
+            Some(clipped(normal, points, deepest, depth))
+        }
+        // Clip the triangle against the sides of the box's face touching it
+        TriangleFeature::BoxFace(axis) => {
+            let face_center = sub(obb.center, scale(normal, obb.half_extents[axis]));
+            let mut polygon = vertices.to_vec();
+            for k in [(axis + 1) % 3, (axis + 2) % 3] {
+                for direction in [T::ONE, -T::ONE] {
+                    let plane = scale(obb.axes[k], direction);
+                    let limit = obb.half_extents[k];
+                    polygon =
+                        clip(&polygon, |p| dot(sub(p, obb.center), plane) - limit);
+                }
+            }
+            let points = polygon
+                .into_iter()
+                .filter_map(|p| {
+                    let depth = dot(sub(p, face_center), normal);
+                    (depth >= T::ZERO).then(|| ContactPoint {
+                        point: sub(p, scale(normal, depth * T::HALF)),
+                        depth,
+                    })
+                })
+                .collect();
+            let corner = vertices
+                .into_iter()
+                .max_by(|a, b| dot(*a, normal).total_cmp(&dot(*b, normal)))
+                .unwrap();
+            let deepest = sub(corner, scale(normal, depth * T::HALF));
+            Some(clipped(normal, points, deepest, depth))
+        }
+        TriangleFeature::Edge { side, box_axis } => {
+            // The box's edge lying furthest towards the triangle
+            let towards = scale(normal, -T::ONE);
+            let middle =
+                (0..3).filter(|&k| k != box_axis).fold(obb.center, |p, k| {
+                    let side = dot(obb.axes[k], towards).signum();
+                    add(p, scale(obb.axes[k], obb.half_extents[k] * side))
+                });
+            let half = scale(obb.axes[box_axis], obb.half_extents[box_axis]);
+            let (p, q) = closest_points_segments(
+                (vertices[side], vertices[(side + 1) % 3]),
+                (sub(middle, half), add(middle, half)),
+            );
+            let point = scale(add(p, q), T::HALF);
+            Some(ContactManifold::single(normal, point, depth))
+        }
+    }
+}
+
+/// A manifold from clipped `points`, falling back to the deepest point when
+/// rounding clipped them all away.
+fn clipped<T: Real>(
+    normal: [T; 3],
+    mut points: Vec<ContactPoint<T>>,
+    deepest: [T; 3],
+    depth: T,
+) -> ContactManifold<T> {
+    if points.is_empty() {
+        return ContactManifold::single(normal, deepest, depth);
+    }
+    reduce(&mut points);
+    ContactManifold { normal, points }
+}
+
+enum Feature {
+    Face { reference_is_a: bool, axis: usize },
+    Edge { a_axis: usize, b_axis: usize },
+}
+
+/// Separating axis test over the face and edge axes of both boxes. Face
+/// contacts clip the most anti-parallel face of one box against the sides
+/// of the other's touching face; edge contacts use the closest points of
+/// the two edges.
+pub fn obb_obb<T: Real>(a: Obb<T>, b: Obb<T>) -> Option<ContactManifold<T>> {
+    let offset = sub(b.center, a.center);
+    let project = |obb: &Obb<T>, axis: [T; 3]| {
+        (0..3)
+            .map(|k| obb.half_extents[k] * dot(obb.axes[k], axis).abs())
+            .sum::<T>()
+    };
+    // Overlap along `axis` and the axis turned to point from `a` to `b`
+    let overlap = |axis: [T; 3]| {
+        let distance = dot(offset, axis);
+        let depth = project(&a, axis) + project(&b, axis) - distance.abs();
+        let axis = if distance < T::ZERO {
+            scale(axis, -T::ONE)
+        } else {
+            axis
+        };
+        (depth >= T::ZERO).then_some((depth, axis))
+    };
+
+    let mut face: Option<(T, [T; 3], Feature)> = None;
+    for (reference_is_a, obb) in [(true, &a), (false, &b)] {
+        for axis in 0..3 {
+            let (depth, normal) = overlap(obb.axes[axis])?;
+            if face.as_ref().is_none_or(|(best, ..)| depth < *best) {
+                let feature = Feature::Face {
+                    reference_is_a,
+                    axis,
+                };
+                face = Some((depth, normal, feature));
+            }
+        }
+    }
+    let mut edge: Option<(T, [T; 3], Feature)> = None;
+    for a_axis in 0..3 {
+        for b_axis in 0..3 {
+            let axis = cross(a.axes[a_axis], b.axes[b_axis]);
+            let axis_length = length(axis);
+            if axis_length < T::from_f64(1e-6) {
+                continue;
+            }
+            let (depth, normal) = overlap(scale(axis, T::ONE / axis_length))?;
+            if edge.as_ref().is_none_or(|(best, ..)| depth < *best) {
+                edge = Some((depth, normal, Feature::Edge { a_axis, b_axis }));
+            }
+        }
+    }
+
+    // Faces give whole manifolds, so edges only win by a clear margin
+    let (depth, normal, feature) = match (face, edge) {
+        (Some(face), Some(edge))
+            if edge.0 < face.0 * T::from_f64(0.95) - T::from_f64(1e-3) =>
+        {
+            edge
+        }
+        (face, _) => face?,
+    };
+    match feature {
+        Feature::Face {
+            reference_is_a: true,
+            axis,
+        } => Some(clip_faces(&a, &b, axis, normal, depth)),
+        Feature::Face {
+            reference_is_a: false,
+            axis,
+        } => {
+            let reversed = scale(normal, -T::ONE);
+            Some(clip_faces(&b, &a, axis, reversed, depth).flipped())
+        }
+        Feature::Edge { a_axis, b_axis } => {
+            // The edges of each box that lie furthest towards the other one
+            let edge = |obb: &Obb<T>, axis: usize, towards: [T; 3]| {
+                let middle =
+                    (0..3).filter(|&k| k != axis).fold(obb.center, |p, k| {
+                        let side = dot(obb.axes[k], towards).signum();
+                        add(p, scale(obb.axes[k], obb.half_extents[k] * side))
+                    });
+                let half = scale(obb.axes[axis], obb.half_extents[axis]);
+                (sub(middle, half), add(middle, half))
+            };
+            let (p, q) = closest_points_segments(
+                edge(&a, a_axis, normal),
+                edge(&b, b_axis, scale(normal, -T::ONE)),
+            );
+            let point = scale(add(p, q), T::HALF);
+            Some(ContactManifold::single(normal, point, depth))
+        }
+    }
+}
+
+/// Clips the face of `incident` most opposed to `normal` against the face
+/// of `reference` along its `axis`. `normal` points from `reference` to
+/// `incident`.
+fn clip_faces<T: Real>(
+    reference: &Obb<T>,
+    incident: &Obb<T>,
+    axis: usize,
+    normal: [T; 3],
+    depth: T,
+) -> ContactManifold<T> {
+    let face_center = add(
+        reference.center,
+        scale(normal, reference.half_extents[axis]),
+    );
+
+    let incident_axis = (0..3)
+        .max_by(|&i, &j| {
+            let facing = |k: usize| dot(incident.axes[k], normal).abs();
+            facing(i).total_cmp(&facing(j))
+        })
+        .unwrap();
+    let side = -dot(incident.axes[incident_axis], normal).signum();
+    let incident_center = add(
+        incident.center,
+        scale(
+            incident.axes[incident_axis],
+            incident.half_extents[incident_axis] * side,
+        ),
+    );
+    let (k1, k2) = ((incident_axis + 1) % 3, (incident_axis + 2) % 3);
+    let u = scale(incident.axes[k1], incident.half_extents[k1]);
+    let v = scale(incident.axes[k2], incident.half_extents[k2]);
+    let mut polygon = vec![
+        add(add(incident_center, u), v),
+        add(sub(incident_center, u), v),
+        sub(sub(incident_center, u), v),
+        sub(add(incident_center, u), v),
+    ];
+
+    for k in [(axis + 1) % 3, (axis + 2) % 3] {
+        for direction in [T::ONE, -T::ONE] {
+            let plane = scale(reference.axes[k], direction);
+            let limit = reference.half_extents[k];
+            polygon =
+                clip(&polygon, |p| dot(sub(p, reference.center), plane) - limit);
+        }
+    }
+
+    let mut points: Vec<ContactPoint<T>> = polygon
+        .into_iter()
+        .filter_map(|p| {
+            let depth = -dot(sub(p, face_center), normal);
+            (depth >= T::ZERO).then(|| ContactPoint {
+                point: add(p, scale(normal, depth * T::HALF)),
+                depth,
+            })
+        })
+        .collect();
+    if points.is_empty() {
+        // Only reachable through rounding, fall back to the deepest corner
+        let corner = (0..3).fold(incident.center, |p, k| {
+            let side = -dot(incident.axes[k], normal).signum();
+            add(p, scale(incident.axes[k], incident.half_extents[k] * side))
+        });
+        let point = add(corner, scale(normal, depth * T::HALF));
+        return ContactManifold::single(normal, point, depth);
+    }
+    reduce(&mut points);
+    ContactManifold { normal, points }
+}
+
+/// Sutherland-Hodgman clipping of `polygon` to where `distance` is at most
+/// zero.
+fn clip<T: Real>(polygon: &[[T; 3]], distance: impl Fn([T; 3]) -> T) -> Vec<[T; 3]> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, &p) in polygon.iter().enumerate() {
+        let q = polygon[(i + 1) % polygon.len()];
+        let (dp, dq) = (distance(p), distance(q));
+        if dp <= T::ZERO {
+            clipped.push(p);
+        }
+        if (dp <= T::ZERO) != (dq <= T::ZERO) {
+            clipped.push(add(p, scale(sub(q, p), dp / (dp - dq))));
+        }
+    }
+    clipped
+}
+
+/// Keeps the deepest point, then repeatedly the point furthest from those
+/// already kept, until there are `MAX_POINTS`.
+fn reduce<T: Real>(points: &mut Vec<ContactPoint<T>>) {
+    if points.len() <= MAX_POINTS {
+        return;
+    }
+    let deepest = (0..points.len())
+        .max_by(|&i, &j| points[i].depth.total_cmp(&points[j].depth))
+        .unwrap();
+    let mut kept = vec![points.swap_remove(deepest)];
+    while kept.len() < MAX_POINTS {
+        let furthest = (0..points.len())
+            .max_by(|&i, &j| {
+                let spread = |p: &ContactPoint<T>| {
+                    kept.iter()
+                        .map(|k| distance_squared(k.point, p.point))
+                        .fold(T::INFINITY, T::min)
+                };
+                spread(&points[i]).total_cmp(&spread(&points[j]))
+            })
+            .unwrap();
+        kept.push(points.swap_remove(furthest));
+    }
+    *points = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-4),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn manifolds() {
+        let unit = Shape::Aabb(Aabb::new([-0.5; 3], [0.5; 3]));
+        let ground = Shape::Aabb(Aabb::new([-5.0, -1.0, -5.0], [5.0, 0.0, 5.0]));
+        let identity = Isometry::identity();
+
+        // A box sunk 0.1 into the ground touches with its four bottom corners
+        let resting = Isometry::from_translation([0.0, 0.4, 0.0]);
+        let manifold = contact(&ground, &identity, &unit, &resting).unwrap();
+        assert_close(manifold.normal, [0.0, 1.0, 0.0]);
+        assert_eq!(manifold.points.len(), 4);
+        for p in &manifold.points {
+            assert!((p.depth - 0.1).abs() < 1e-5);
+            assert!((p.point[1] + 0.05).abs() < 1e-5);
+            assert!((p.point[0].abs() - 0.5).abs() < 1e-5);
+        }
+        let flipped = contact(&unit, &resting, &ground, &identity).unwrap();
+        assert_close(flipped.normal, [0.0, -1.0, 0.0]);
+        assert!(contact(
+            &ground,
+            &identity,
+            &unit,
+            &resting.with_translation([0.0, 0.6, 0.0])
+        )
+        .is_none());
+
+        // Balanced on an edge, so only the two points along it remain
+        let tipped =
+            Isometry::from_axis_angle([0.0, 0.0, 1.0], core::f32::consts::FRAC_PI_4)
+                .with_translation([0.0, 0.7, 0.0]);
+        let manifold = contact(&ground, &identity, &unit, &tipped).unwrap();
+        assert_eq!(manifold.points.len(), 2);
+        let edge_depth = 0.5f32.sqrt() - 0.7;
+        assert!(manifold
+            .points
+            .iter()
+            .all(|p| (p.depth - edge_depth).abs() < 1e-4));
+
+        let sphere = Shape::Sphere(Sphere::new([0.0; 3], 0.5));
+        let inside = Isometry::from_translation([0.0, -0.2, 0.0]);
+        let manifold = contact(&ground, &identity, &sphere, &inside).unwrap();
+        assert_close(manifold.normal, [0.0, 1.0, 0.0]);
+        assert!((manifold.max_depth() - 0.7).abs() < 1e-5);
+
+        let capsule =
+            Shape::Capsule(Capsule::new([-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.5));
+        let lying = Isometry::from_translation([0.0, 0.45, 0.0]);
+        let manifold = contact(&ground, &identity, &capsule, &lying).unwrap();
+        assert_close(manifold.normal, [0.0, 1.0, 0.0]);
+        assert!(manifold.points.len() >= 2);
+        assert!(manifold
+            .points
+            .iter()
+            .all(|p| (p.depth - 0.05).abs() < 1e-4));
+
+        let other =
+            Shape::Capsule(Capsule::new([0.0, 0.0, -1.0], [0.0, 0.0, 1.0], 0.5));
+        let above = Isometry::from_translation([0.0, 1.4, 0.0]);
+        let manifold = contact(&capsule, &lying, &other, &above).unwrap();
+        assert_close(manifold.normal, [0.0, 1.0, 0.0]);
+        assert!((manifold.max_depth() - 0.05).abs() < 1e-4);
+    }
+
+    #[test]
+    fn edge_contact() {
+        let unit = Shape::Obb(Obb::from(Aabb::new([-0.5; 3], [0.5; 3])));
+        let identity = Isometry::identity();
+        // Two boxes turned 45 degrees about crossed axes meet edge to edge
+        let a =
+            Isometry::from_axis_angle([1.0, 0.0, 0.0], core::f32::consts::FRAC_PI_4);
+        let b =
+            Isometry::from_axis_angle([0.0, 0.0, 1.0], core::f32::consts::FRAC_PI_4)
+                .with_translation([0.0, 1.3, 0.0]);
+        let manifold = contact(&unit, &a, &unit, &b).unwrap();
+        assert_close(manifold.normal, [0.0, 1.0, 0.0]);
+        assert_eq!(manifold.points.len(), 1);
+        assert!((manifold.points[0].depth - (2.0f32.sqrt() - 1.3)).abs() < 1e-4);
+        assert_close(manifold.points[0].point, [0.0, 0.65, 0.0]);
+        assert!(contact(
+            &unit,
+            &identity,
+            &unit,
+            &b.with_translation([0.0, 1.5, 0.0])
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn terrain_pairs() {
+        let field =
+            Shape::from(Heightfield::new(2, 2, vec![0.0, 1.0, 1.0, 2.0], [2.0; 3]));
+        let grid = Shape::from(VoxelGrid::sparse(1.0).with_occupied([[0, 0, 0]]));
+        let sunk = Isometry::from_translation([0.0, -0.5, 0.0]);
+        let identity = Isometry::identity();
+        for (a, b) in [(&field, &field), (&field, &grid), (&grid, &grid)] {
+            assert!(a.intersects(&identity, b, &sunk));
+            assert!(contact(a, &identity, b, &sunk).is_none());
+        }
+    }
+
+    #[test]
+    fn buried_in_voxels() {
+        let block = (-2..3).flat_map(|x| {
+            (-4..0).flat_map(move |y| (-2..3).map(move |z| [x, y, z]))
+        });
+        let block = Shape::from(VoxelGrid::sparse(1.0).with_occupied(block));
+        let identity = Isometry::identity();
+        let cube = Shape::Obb(Obb::from(Aabb::new([-0.5; 3], [0.5; 3])));
+        let under = Isometry::from_translation([0.3, -0.7, 0.2]);
+        let manifold = contact(&block, &identity, &cube, &under).unwrap();
+        assert_close(manifold.normal, [0.0, 1.0, 0.0]);
+        assert!((manifold.max_depth() - 1.2).abs() < 1e-4);
+        // With no open face next to it, the deepest contact still holds on
+        let ball = Shape::Sphere(Sphere::new([0.0; 3], 0.2));
+        let deep = Isometry::from_translation([0.5, -2.5, 0.5]);
+        assert!(contact(&block, &identity, &ball, &deep).is_some());
+    }
+
+    #[test]
+    fn never_empty() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        let mut shape = || match rng.gen_range(0..3) {
+            0 => {
+                Shape::Obb(Obb::from(Aabb::new([-0.5, -1.0, -0.5], [0.5, 1.0, 0.5])))
+            }
+            1 => Shape::Capsule(Capsule::new([0.0; 3], [0.0, 1.0, 0.0], 0.4)),
+            _ => Shape::Sphere(Sphere::new([0.0; 3], 0.7)),
+        };
+        let pairs: Vec<_> = (0..2000).map(|_| (shape(), shape())).collect();
+        let mut touching = 0;
+        for (a, b) in pairs {
+            let axis = [0.0; 3].map(|_| rng.gen_range(-1.0..1.0));
+            let b_iso = Isometry::from_axis_angle(axis, rng.gen_range(0.0..3.0))
+                .with_translation([0.0; 3].map(|_| rng.gen_range(-1.5..1.5)));
+            let identity = Isometry::identity();
+            if let Some(manifold) = contact(&a, &identity, &b, &b_iso) {
+                assert!(!manifold.points.is_empty(), "{:?} {:?}", a, b);
+                touching += 1;
+            }
+            assert_eq!(
+                contact(&a, &identity, &b, &b_iso).is_some(),
+                a.intersects(&identity, &b, &b_iso)
+            );
+        }
+        assert!(touching > 500, "{}", touching);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::batch;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// The widest instruction set available on the running CPU, which picks
+/// the lane count the batch APIs run with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SimdLevel {
+    Scalar,
+    Neon,
+    Sse,
+    Avx2,
+    Avx512,
+}
+
+static DETECTED: AtomicU8 = AtomicU8::new(u8::MAX);
+
+// Without std there's no runtime detection, so only the features the crate
+// was compiled with count as supported.
+#[cfg(feature = "std")]
+macro_rules! feature_detected {
+    (x86, $feature:tt) => {
+        std::is_x86_feature_detected!($feature)
+    };
+    (aarch64, $feature:tt) => {
+        std::arch::is_aarch64_feature_detected!($feature)
+    };
+}
+
+#[cfg(not(feature = "std"))]
+macro_rules! feature_detected {
+    ($arch:ident, $feature:tt) => {
+        cfg!(target_feature = $feature)
+    };
+}
+
+macro_rules! dispatch {
+    ($level:expr, $f:ident($($arg:expr),*)) => {{
+        let level = $level;
+        assert!(level.is_supported(), "{:?} is not supported on this CPU", level);
+        match level {
+            // Safety: the target features were checked above
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Avx512 => unsafe { x86::avx512::$f($($arg),*) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Avx2 => unsafe { x86::avx2::$f($($arg),*) },
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Sse => unsafe { x86::sse::$f($($arg),*) },
+            SimdLevel::Scalar => batch::$f::<1>($($arg),*),
+            _ => batch::$f::<4>($($arg),*),
+        }
+    }};
+}
+
+impl SimdLevel {
+    pub const ALL: [SimdLevel; 5] = [
+        SimdLevel::Scalar,
+        SimdLevel::Neon,
+        SimdLevel::Sse,
+        SimdLevel::Avx2,
+        SimdLevel::Avx512,
+    ];
+
+    pub fn detect() -> Self {
+        let cached = DETECTED.load(Ordering::Relaxed);
+        if let Some(level) = Self::ALL.get(cached as usize) {
+            return *level;
+        }
+        let level = Self::ALL
+            .into_iter()
+            .rev()
+            .find(|level| level.is_supported())
+            .unwrap_or(SimdLevel::Scalar);
+        DETECTED.store(level as u8, Ordering::Relaxed);
+        level
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            SimdLevel::Scalar => true,
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => feature_detected!(aarch64, "neon"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Sse => feature_detected!(x86, "sse2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Avx2 => feature_detected!(x86, "avx2"),
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            SimdLevel::Avx512 => feature_detected!(x86, "avx512f"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    pub fn lanes(self) -> usize {
+        match self {
+            SimdLevel::Scalar => 1,
+            SimdLevel::Neon | SimdLevel::Sse => 4,
+            SimdLevel::Avx2 => 8,
+            SimdLevel::Avx512 => 16,
+        }
+    }
+
+    pub fn aabb_aabb_pairs(
+        self,
+        a: &[Vec<f32>; 6],
+        b: &[Vec<f32>; 6],
+        hit: impl FnMut(usize, usize),
+    ) {
+        dispatch!(self, aabb_aabb_pairs(a, b, hit))
+    }
+
+    pub fn aabb_sphere_pairs(
+        self,
+        aabbs: &[Vec<f32>; 6],
+        spheres: &[Vec<f32>; 4],
+        hit: impl FnMut(usize, usize),
+    ) {
+        dispatch!(self, aabb_sphere_pairs(aabbs, spheres, hit))
+    }
+
+    pub fn sphere_sphere_pairs(
+        self,
+        a: &[Vec<f32>; 4],
+        b: &[Vec<f32>; 4],
+        hit: impl FnMut(usize, usize),
+    ) {
+        dispatch!(self, sphere_sphere_pairs(a, b, hit))
+    }
+
+    pub fn aabb_aabb_filtered_pairs(
+        self,
+        a: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+        b: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+        hit: impl FnMut(usize, usize),
+    ) {
+        dispatch!(self, aabb_aabb_filtered_pairs(a, b, hit))
+    }
+
+    pub fn aabb_sphere_filtered_pairs(
+        self,
+        aabbs: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+        spheres: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+        hit: impl FnMut(usize, usize),
+    ) {
+        dispatch!(self, aabb_sphere_filtered_pairs(aabbs, spheres, hit))
+    }
+
+    pub fn sphere_sphere_filtered_pairs(
+        self,
+        a: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+        b: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+        hit: impl FnMut(usize, usize),
+    ) {
+        dispatch!(self, sphere_sphere_filtered_pairs(a, b, hit))
+    }
+
+    pub fn ray_aabb_pairs(
+        self,
+        rays: &[Vec<f32>; 6],
+        aabbs: &[Vec<f32>; 6],
+        hit: impl FnMut(usize, usize, f32),
+    ) {
+        dispatch!(self, ray_aabb_pairs(rays, aabbs, hit))
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    // Each level gets its own copy of the batch loops compiled with the
+    // matching target features, so the wider lanes map onto real registers.
+    macro_rules! level {
+        ($name:ident, $feature:literal, $lanes:literal) => {
+            pub mod $name {
+                use crate::batch;
+                use alloc::vec::Vec;
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn aabb_aabb_pairs(
+                    a: &[Vec<f32>; 6],
+                    b: &[Vec<f32>; 6],
+                    hit: impl FnMut(usize, usize),
+                ) {
+                    batch::aabb_aabb_pairs::<$lanes>(a, b, hit)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn aabb_sphere_pairs(
+                    aabbs: &[Vec<f32>; 6],
+                    spheres: &[Vec<f32>; 4],
+                    hit: impl FnMut(usize, usize),
+                ) {
+                    batch::aabb_sphere_pairs::<$lanes>(aabbs, spheres, hit)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn sphere_sphere_pairs(
+                    a: &[Vec<f32>; 4],
+                    b: &[Vec<f32>; 4],
+                    hit: impl FnMut(usize, usize),
+                ) {
+                    batch::sphere_sphere_pairs::<$lanes>(a, b, hit)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn aabb_aabb_filtered_pairs(
+                    a: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+                    b: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+                    hit: impl FnMut(usize, usize),
+                ) {
+                    batch::aabb_aabb_filtered_pairs::<$lanes>(a, b, hit)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn aabb_sphere_filtered_pairs(
+                    aabbs: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+                    spheres: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+                    hit: impl FnMut(usize, usize),
+                ) {
+                    batch::aabb_sphere_filtered_pairs::<$lanes>(aabbs, spheres, hit)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn sphere_sphere_filtered_pairs(
+                    a: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+                    b: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+                    hit: impl FnMut(usize, usize),
+                ) {
+                    batch::sphere_sphere_filtered_pairs::<$lanes>(a, b, hit)
+                }
+
+                #[target_feature(enable = $feature)]
+                pub unsafe fn ray_aabb_pairs(
+                    rays: &[Vec<f32>; 6],
+                    aabbs: &[Vec<f32>; 6],
+                    hit: impl FnMut(usize, usize, f32),
+                ) {
+                    batch::ray_aabb_pairs::<$lanes>(rays, aabbs, hit)
+                }
+            }
+        };
+    }
+
+    level!(sse, "sse2", 4);
+    level!(avx2, "avx2", 8);
+    level!(avx512, "avx512f", 16);
+}
+
+pub fn aabb_aabb_pairs(
+    a: &[Vec<f32>; 6],
+    b: &[Vec<f32>; 6],
+    hit: impl FnMut(usize, usize),
+) {
+    SimdLevel::detect().aabb_aabb_pairs(a, b, hit)
+}
+
+pub fn aabb_sphere_pairs(
+    aabbs: &[Vec<f32>; 6],
+    spheres: &[Vec<f32>; 4],
+    hit: impl FnMut(usize, usize),
+) {
+    SimdLevel::detect().aabb_sphere_pairs(aabbs, spheres, hit)
+}
+
+pub fn sphere_sphere_pairs(
+    a: &[Vec<f32>; 4],
+    b: &[Vec<f32>; 4],
+    hit: impl FnMut(usize, usize),
+) {
+    SimdLevel::detect().sphere_sphere_pairs(a, b, hit)
+}
+
+pub fn aabb_aabb_filtered_pairs(
+    a: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    hit: impl FnMut(usize, usize),
+) {
+    SimdLevel::detect().aabb_aabb_filtered_pairs(a, b, hit)
+}
+
+pub fn aabb_sphere_filtered_pairs(
+    aabbs: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    spheres: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    hit: impl FnMut(usize, usize),
+) {
+    SimdLevel::detect().aabb_sphere_filtered_pairs(aabbs, spheres, hit)
+}
+
+pub fn sphere_sphere_filtered_pairs(
+    a: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    hit: impl FnMut(usize, usize),
+) {
+    SimdLevel::detect().sphere_sphere_filtered_pairs(a, b, hit)
+}
+
+pub fn ray_aabb_pairs(
+    rays: &[Vec<f32>; 6],
+    aabbs: &[Vec<f32>; 6],
+    hit: impl FnMut(usize, usize, f32),
+) {
+    SimdLevel::detect().ray_aabb_pairs(rays, aabbs, hit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{generate_soa_data, generate_test_data};
+    use rand::Rng;
+    use std::ops::Range;
+
+    #[test]
+    fn levels_agree() {
+        const RANGE: Range<f32> = -4.0..4.0;
+        let mut rng = rand::thread_rng();
+        let (aabbs, _) = generate_test_data(45, |_| {
+            [0.0, 0.0, 0.0, 1.0, 1.0, 1.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let spheres = generate_soa_data(29, |_| {
+            [
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(0.1..1.0),
+            ]
+        });
+        let (rays, _) =
+            generate_test_data(7, |_| [0.0; 6].map(|_| rng.gen_range(RANGE)));
+        let aabb_groups =
+            [0; 2].map(|_| (0..45).map(|_| rng.gen_range(0..4)).collect());
+        let sphere_groups =
+            [0; 2].map(|_| (0..29).map(|_| rng.gen_range(0..4)).collect());
+
+        let run = |level: SimdLevel| {
+            let mut pairs = vec![];
+            let mut ray_hits = vec![];
+            level.aabb_aabb_pairs(&aabbs, &aabbs, |a, b| pairs.push((0, a, b)));
+            level.aabb_sphere_pairs(&aabbs, &spheres, |a, b| pairs.push((1, a, b)));
+            level.sphere_sphere_pairs(&spheres, &spheres, |a, b| {
+                pairs.push((2, a, b))
+            });
+            level.ray_aabb_pairs(&rays, &aabbs, |r, a, t| ray_hits.push((r, a, t)));
+            let (aabbs, spheres) =
+                ((&aabbs, &aabb_groups), (&spheres, &sphere_groups));
+            level.aabb_aabb_filtered_pairs(aabbs, aabbs, |a, b| {
+                pairs.push((3, a, b))
+            });
+            level.aabb_sphere_filtered_pairs(aabbs, spheres, |a, b| {
+                pairs.push((4, a, b))
+            });
+            level.sphere_sphere_filtered_pairs(spheres, spheres, |a, b| {
+                pairs.push((5, a, b))
+            });
+            (pairs, ray_hits)
+        };
+
+        let detected = SimdLevel::detect();
+        assert!(detected.is_supported());
+        let expected = run(SimdLevel::Scalar);
+        assert!(!expected.0.is_empty() && !expected.1.is_empty());
+        for level in SimdLevel::ALL.into_iter().filter(|l| l.is_supported()) {
+            assert_eq!(run(level), expected, "{:?}", level);
+        }
+    }
+}
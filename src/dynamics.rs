@@ -0,0 +1,1496 @@
+//! Rigid bodies attached to the colliders of a `World`, stepped with
+//! semi-implicit Euler integration and a sequential impulse solver fed by
+//! `contacts` manifolds.
+
+use crate::contacts::{self, ContactManifold};
+use crate::intersections::distance_squared;
+use crate::islands::UnionFind;
+use crate::isometry::Isometry;
+use crate::joints::{Joint, JointHandle, JointKind, Row};
+use crate::materials::Material;
+use crate::math::*;
+use crate::real::Real;
+use crate::shapes::{Placed, Shape};
+use crate::world::{Collider, ColliderHandle, QueryFilter, World};
+use crate::Vec3;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Fraction of the penetration corrected each step.
+pub(crate) const BAUMGARTE: f64 = 0.2;
+/// Penetration left alone so resting contacts don't jitter.
+const SLOP: f64 = 0.005;
+/// Closing speeds below this don't bounce.
+const RESTITUTION_THRESHOLD: f64 = 1.0;
+/// How far a contact point may move between steps and still reuse the
+/// impulse it had before.
+const WARM_START_DISTANCE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BodyKind {
+    /// Moved by gravity and contacts.
+    Dynamic,
+    /// Moved only by its velocities, pushing dynamic bodies aside.
+    Kinematic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MassProperties<T: Real = f32> {
+    pub mass: T,
+    /// Centre of mass in the collider's local space.
+    pub local_center: [T; 3],
+    /// Inertia tensor about the centre of mass, in local space.
+    pub inertia: [[T; 3]; 3],
+}
+
+impl<T: Real> MassProperties<T> {
+    /// Mass properties of `shape` filled uniformly at `density`.
+    pub fn from_shape(shape: &Shape<T>, density: T) -> Self {
+        let diagonal = |d: [T; 3]| {
+            let mut m = [[T::ZERO; 3]; 3];
+            for i in 0..3 {
+                m[i][i] = d[i];
+            }
+            m
+        };
+        let two_fifths = T::from_f64(0.4);
+        match shape.placed(&Isometry::identity()) {
+            Placed::Sphere(sphere) => {
+                let mass = density * sphere.volume();
+                let moment = two_fifths * mass * sphere.radius * sphere.radius;
+                Self {
+                    mass,
+                    local_center: sphere.center,
+                    inertia: diagonal([moment; 3]),
+                }
+            }
+            Placed::Obb(obb) => {
+                let mass = density * obb.volume();
+                let [x, y, z] = obb.half_extents.map(|h| h * h);
+                let third = mass / T::from_f64(3.0);
+                let principal = diagonal([y + z, x + z, x + y].map(|s| s * third));
+                Self {
+                    mass,
+                    local_center: obb.center,
+                    inertia: mat_mul(
+                        mat_mul(obb.axes, principal),
+                        transpose(obb.axes),
+                    ),
+                }
+            }
+            Placed::Capsule(capsule) => {
+                let offset = sub(capsule.b, capsule.a);
+                let height = length(offset);
+                let r = capsule.radius;
+                let cylinder = density * T::PI * r * r * height;
+                let caps = density * T::from_f64(4.0 / 3.0) * T::PI * r * r * r;
+                let along = cylinder * r * r * T::HALF + caps * two_fifths * r * r;
+                // The caps' own moments, moved out to the ends of the cylinder
+                let across = cylinder
+                    * (r * r / T::from_f64(4.0)
+                        + height * height / T::from_f64(12.0))
+                    + caps
+                        * (two_fifths * r * r
+                            + height * height / T::from_f64(4.0)
+                            + T::from_f64(3.0 / 8.0) * r * height);
+                let axis = if height > T::EPSILON {
+                    scale(offset, T::ONE / height)
+                } else {
+                    [T::ZERO, T::ONE, T::ZERO]
+                };
+                let inertia = core::array::from_fn(|j| {
+                    core::array::from_fn(|i| {
+                        let identity = if i == j { across } else { T::ZERO };
+                        identity + (along - across) * axis[i] * axis[j]
+                    })
+                });
+                Self {
+                    mass: cylinder + caps,
+                    local_center: capsule.center(),
+                    inertia,
+                }
+            }
+            // Terrain and voxel worlds can't be moved, whatever their density
+            Placed::Heightfield(..) | Placed::VoxelGrid(..) => Self {
+                mass: T::ZERO,
+                local_center: shape.aabb(&Isometry::identity()).center(),
+                inertia: [[T::ZERO; 3]; 3],
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RigidBody<T: Real = f32> {
+    pub kind: BodyKind,
+    pub linear_velocity: [T; 3],
+    /// In radians per second, about the centre of mass.
+    pub angular_velocity: [T; 3],
+    pub linear_damping: T,
+    pub angular_damping: T,
+    pub gravity_scale: T,
+    /// Whether the body may fall asleep once its island comes to rest.
+    pub can_sleep: bool,
+    /// Sweeps the body along its motion each step so it stops at the first
+    /// collider in the way instead of passing through it. Meant for small,
+    /// fast bodies, as it costs a shape cast per step.
+    pub ccd: bool,
+    mass: MassProperties<T>,
+    inverse_mass: T,
+    inverse_inertia: [[T; 3]; 3],
+    sleeping: bool,
+    /// How long the body has been slower than the sleep thresholds.
+    resting_time: T,
+}
+
+impl<T: Real> RigidBody<T> {
+    pub fn dynamic(mass: MassProperties<T>) -> Self {
+        let mut body = Self::kinematic();
+        body.kind = BodyKind::Dynamic;
+        body.set_mass_properties(mass);
+        body
+    }
+
+    pub fn kinematic() -> Self {
+        Self {
+            kind: BodyKind::Kinematic,
+            linear_velocity: [T::ZERO; 3],
+            angular_velocity: [T::ZERO; 3],
+            linear_damping: T::ZERO,
+            angular_damping: T::ZERO,
+            gravity_scale: T::ONE,
+            can_sleep: true,
+            ccd: false,
+            mass: MassProperties {
+                mass: T::ZERO,
+                local_center: [T::ZERO; 3],
+                inertia: [[T::ZERO; 3]; 3],
+            },
+            inverse_mass: T::ZERO,
+            inverse_inertia: [[T::ZERO; 3]; 3],
+            sleeping: false,
+            resting_time: T::ZERO,
+        }
+    }
+
+    pub fn with_linear_velocity(mut self, velocity: impl Into<Vec3<T>>) -> Self {
+        self.linear_velocity = velocity.into().into();
+        self
+    }
+
+    pub fn with_angular_velocity(mut self, velocity: impl Into<Vec3<T>>) -> Self {
+        self.angular_velocity = velocity.into().into();
+        self
+    }
+
+    pub fn with_damping(mut self, linear: T, angular: T) -> Self {
+        self.linear_damping = linear;
+        self.angular_damping = angular;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: T) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    pub fn with_ccd(mut self, ccd: bool) -> Self {
+        self.ccd = ccd;
+        self
+    }
+
+    pub fn mass_properties(&self) -> &MassProperties<T> {
+        &self.mass
+    }
+
+    pub fn set_mass_properties(&mut self, mass: MassProperties<T>) {
+        self.mass = mass;
+        self.inverse_mass = if mass.mass > T::ZERO {
+            T::ONE / mass.mass
+        } else {
+            T::ZERO
+        };
+        self.inverse_inertia = inverse(mass.inertia);
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.kind == BodyKind::Dynamic
+    }
+
+    pub fn is_sleeping(&self) -> bool {
+        self.sleeping
+    }
+
+    /// Stops the body until something wakes it. Only dynamic bodies sleep.
+    pub fn sleep(&mut self) {
+        if self.is_dynamic() {
+            self.sleeping = true;
+            self.linear_velocity = [T::ZERO; 3];
+            self.angular_velocity = [T::ZERO; 3];
+        }
+    }
+
+    pub fn wake_up(&mut self) {
+        self.sleeping = false;
+        self.resting_time = T::ZERO;
+    }
+
+    /// Dynamic and awake, so the solver moves it.
+    fn is_active(&self) -> bool {
+        self.is_dynamic() && !self.sleeping
+    }
+
+    /// Kinematic bodies act as if infinitely heavy.
+    fn solver_body(&self, transform: &Isometry<T>) -> SolverBody<T> {
+        let (inverse_mass, inverse_inertia) = match self.kind {
+            BodyKind::Dynamic => {
+                let rotation = quat_to_axes(transform.rotation);
+                let world = mat_mul(
+                    mat_mul(rotation, self.inverse_inertia),
+                    transpose(rotation),
+                );
+                (self.inverse_mass, world)
+            }
+            BodyKind::Kinematic => (T::ZERO, [[T::ZERO; 3]; 3]),
+        };
+        SolverBody {
+            inverse_mass,
+            inverse_inertia,
+            center: transform.transform_point(self.mass.local_center),
+            linear: self.linear_velocity,
+            angular: self.angular_velocity,
+        }
+    }
+}
+
+/// A body as the solver sees it, with world space inertia.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SolverBody<T: Real> {
+    pub inverse_mass: T,
+    pub inverse_inertia: [[T; 3]; 3],
+    pub center: [T; 3],
+    pub linear: [T; 3],
+    pub angular: [T; 3],
+}
+
+impl<T: Real> SolverBody<T> {
+    /// Stands in for every collider without a body.
+    fn fixed() -> Self {
+        Self {
+            inverse_mass: T::ZERO,
+            inverse_inertia: [[T::ZERO; 3]; 3],
+            center: [T::ZERO; 3],
+            linear: [T::ZERO; 3],
+            angular: [T::ZERO; 3],
+        }
+    }
+
+    /// Velocity of the point at `offset` from the centre of mass.
+    pub fn velocity_at(&self, offset: [T; 3]) -> [T; 3] {
+        add(self.linear, cross(self.angular, offset))
+    }
+
+    pub fn apply_impulse(&mut self, impulse: [T; 3], offset: [T; 3]) {
+        self.linear = add(self.linear, scale(impulse, self.inverse_mass));
+        let torque = mat_mul_vec(self.inverse_inertia, cross(offset, impulse));
+        self.angular = add(self.angular, torque);
+    }
+
+    /// How much `direction` resists an impulse applied at `offset`.
+    pub fn inverse_effective_mass(&self, offset: [T; 3], direction: [T; 3]) -> T {
+        let turn = mat_mul_vec(self.inverse_inertia, cross(offset, direction));
+        self.inverse_mass + dot(cross(turn, offset), direction)
+    }
+}
+
+/// Applies `impulse` to `b` and its opposite to `a`.
+pub(crate) fn apply_pair<T: Real>(
+    bodies: &mut [SolverBody<T>],
+    (a, b): (usize, usize),
+    impulse: [T; 3],
+    (offset_a, offset_b): ([T; 3], [T; 3]),
+) {
+    bodies[a].apply_impulse(scale(impulse, -T::ONE), offset_a);
+    bodies[b].apply_impulse(impulse, offset_b);
+}
+
+fn inverse_or_zero<T: Real>(k: T) -> T {
+    if k > T::ZERO {
+        T::ONE / k
+    } else {
+        T::ZERO
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ConstraintPoint<T: Real> {
+    point: [T; 3],
+    offset_a: [T; 3],
+    offset_b: [T; 3],
+    normal_mass: T,
+    tangent_mass: [T; 2],
+    bias: T,
+    normal_impulse: T,
+    tangent_impulse: [T; 2],
+}
+
+#[derive(Debug, Clone)]
+struct ContactConstraint<T: Real> {
+    pair: (ColliderHandle, ColliderHandle),
+    bodies: (usize, usize),
+    normal: [T; 3],
+    tangents: [[T; 3]; 2],
+    friction: T,
+    points: Vec<ConstraintPoint<T>>,
+}
+
+/// Impulses a pair's points ended the last step with, for warm starting.
+#[derive(Debug, Clone)]
+struct CachedManifold<T: Real> {
+    pair: (ColliderHandle, ColliderHandle),
+    points: Vec<([T; 3], T, [T; 3])>,
+}
+
+impl<T: Real> ContactConstraint<T> {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        pair: (ColliderHandle, ColliderHandle),
+        bodies: (usize, usize),
+        solver_bodies: &[SolverBody<T>],
+        manifold: ContactManifold<T>,
+        cached: Option<&CachedManifold<T>>,
+        friction: T,
+        restitution: T,
+        dt: T,
+    ) -> Self {
+        let (a, b) = (&solver_bodies[bodies.0], &solver_bodies[bodies.1]);
+        let normal = manifold.normal;
+        let (t1, t2) = tangents(normal);
+        let tangents = [t1, t2];
+        let points = manifold
+            .points
+            .iter()
+            .map(|contact| {
+                let offset_a = sub(contact.point, a.center);
+                let offset_b = sub(contact.point, b.center);
+                let mass = |direction| {
+                    inverse_or_zero(
+                        a.inverse_effective_mass(offset_a, direction)
+                            + b.inverse_effective_mass(offset_b, direction),
+                    )
+                };
+                let closing = dot(
+                    sub(b.velocity_at(offset_b), a.velocity_at(offset_a)),
+                    normal,
+                );
+                let mut bias = T::from_f64(BAUMGARTE) / dt
+                    * (contact.depth - T::from_f64(SLOP)).max(T::ZERO);
+                if closing < -T::from_f64(RESTITUTION_THRESHOLD) {
+                    bias = bias.max(-restitution * closing);
+                }
+                let reach = T::from_f64(WARM_START_DISTANCE);
+                let previous = cached.and_then(|cached| {
+                    cached.points.iter().find(|(p, ..)| {
+                        distance_squared(*p, contact.point) < reach * reach
+                    })
+                });
+                let (normal_impulse, tangent_impulse) = match previous {
+                    Some((_, normal_impulse, tangent)) => {
+                        (*normal_impulse, tangents.map(|t| dot(*tangent, t)))
+                    }
+                    None => (T::ZERO, [T::ZERO; 2]),
+                };
+                ConstraintPoint {
+                    point: contact.point,
+                    offset_a,
+                    offset_b,
+                    normal_mass: mass(normal),
+                    tangent_mass: tangents.map(mass),
+                    bias,
+                    normal_impulse,
+                    tangent_impulse,
+                }
+            })
+            .collect();
+        Self {
+            pair,
+            bodies,
+            normal,
+            tangents,
+            friction,
+            points,
+        }
+    }
+
+    fn warm_start(&self, bodies: &mut [SolverBody<T>]) {
+        for point in &self.points {
+            let impulse = add(
+                scale(self.normal, point.normal_impulse),
+                add(
+                    scale(self.tangents[0], point.tangent_impulse[0]),
+                    scale(self.tangents[1], point.tangent_impulse[1]),
+                ),
+            );
+            apply_pair(
+                bodies,
+                self.bodies,
+                impulse,
+                (point.offset_a, point.offset_b),
+            );
+        }
+    }
+
+    fn solve(&mut self, bodies: &mut [SolverBody<T>]) {
+        let (a, b) = self.bodies;
+        for point in &mut self.points {
+            let offsets = (point.offset_a, point.offset_b);
+            let relative = |bodies: &[SolverBody<T>]| {
+                sub(
+                    bodies[b].velocity_at(point.offset_b),
+                    bodies[a].velocity_at(point.offset_a),
+                )
+            };
+
+            // Friction first, bounded by last iteration's normal impulse
+            let limit = self.friction * point.normal_impulse;
+            for (k, tangent) in self.tangents.into_iter().enumerate() {
+                let speed = dot(relative(bodies), tangent);
+                let total = (point.tangent_impulse[k]
+                    - speed * point.tangent_mass[k])
+                    .max(-limit)
+                    .min(limit);
+                let change = total - point.tangent_impulse[k];
+                point.tangent_impulse[k] = total;
+                apply_pair(bodies, self.bodies, scale(tangent, change), offsets);
+            }
+
+            let speed = dot(relative(bodies), self.normal);
+            let total = (point.normal_impulse
+                + (point.bias - speed) * point.normal_mass)
+                .max(T::ZERO);
+            let change = total - point.normal_impulse;
+            point.normal_impulse = total;
+            apply_pair(bodies, self.bodies, scale(self.normal, change), offsets);
+        }
+    }
+
+    fn cached(&self) -> CachedManifold<T> {
+        let points = self
+            .points
+            .iter()
+            .map(|point| {
+                let tangent = add(
+                    scale(self.tangents[0], point.tangent_impulse[0]),
+                    scale(self.tangents[1], point.tangent_impulse[1]),
+                );
+                (point.point, point.normal_impulse, tangent)
+            })
+            .collect();
+        CachedManifold {
+            pair: self.pair,
+            points,
+        }
+    }
+}
+
+/// A collision `World` whose colliders can carry rigid bodies. Colliders
+/// without one are static.
+#[derive(Debug, Clone)]
+pub struct PhysicsWorld<T: Real = f32> {
+    pub gravity: [T; 3],
+    /// Solver passes over all constraints per step.
+    pub iterations: usize,
+    /// The material of colliders that don't have their own.
+    pub material: Material<T>,
+    /// Speeds below which a body counts as resting.
+    pub sleep_linear_threshold: T,
+    pub sleep_angular_threshold: T,
+    /// How long a whole island has to rest before it falls asleep.
+    pub time_to_sleep: T,
+    world: World<T>,
+    /// Indexed by collider slot, with the handle the body was added under.
+    bodies: Vec<Option<(ColliderHandle, RigidBody<T>)>>,
+    /// Sorted by pair.
+    cache: Vec<CachedManifold<T>>,
+    joints: Vec<JointSlot<T>>,
+    free_joints: Vec<u32>,
+    /// Touching and jointed colliders as of the last step, sorted.
+    links: Vec<(ColliderHandle, ColliderHandle)>,
+}
+
+#[derive(Debug, Clone)]
+struct JointSlot<T: Real> {
+    generation: u32,
+    joint: Option<Joint<T>>,
+}
+
+impl<T: Real> Default for PhysicsWorld<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Real> PhysicsWorld<T> {
+    pub fn new() -> Self {
+        Self {
+            gravity: [T::ZERO, T::from_f64(-9.81), T::ZERO],
+            iterations: 8,
+            material: Material::new(T::HALF, T::ZERO),
+            sleep_linear_threshold: T::from_f64(0.05),
+            sleep_angular_threshold: T::from_f64(0.05),
+            time_to_sleep: T::HALF,
+            world: World::new(),
+            bodies: Vec::new(),
+            cache: Vec::new(),
+            joints: Vec::new(),
+            free_joints: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    pub fn world(&self) -> &World<T> {
+        &self.world
+    }
+
+    /// Colliders added through here are static. Removing one that has a
+    /// body leaves the body behind until its slot is reused.
+    pub fn world_mut(&mut self) -> &mut World<T> {
+        &mut self.world
+    }
+
+    pub fn insert(
+        &mut self,
+        collider: Collider<T>,
+        body: RigidBody<T>,
+    ) -> ColliderHandle {
+        let handle = self.world.insert(collider);
+        if self.bodies.len() <= handle.index() {
+            self.bodies.resize(handle.index() + 1, None);
+        }
+        self.bodies[handle.index()] = Some((handle, body));
+        handle
+    }
+
+    /// Also removes the joints attached to the collider and wakes whatever
+    /// it was touching.
+    pub fn remove(&mut self, handle: ColliderHandle) -> Option<Collider<T>> {
+        let collider = self.world.remove(handle)?;
+        if let Some(slot) = self.bodies.get_mut(handle.index()) {
+            *slot = None;
+        }
+        let touching: Vec<ColliderHandle> = self
+            .links
+            .iter()
+            .filter_map(|&(a, b)| match (a == handle, b == handle) {
+                (true, _) => Some(b),
+                (_, true) => Some(a),
+                _ => None,
+            })
+            .collect();
+        for other in touching {
+            self.wake_up(other);
+        }
+        let attached: Vec<JointHandle> = self
+            .joints()
+            .filter(|(_, joint)| joint.a == handle || joint.b == handle)
+            .map(|(joint, _)| joint)
+            .collect();
+        for joint in attached {
+            self.remove_joint(joint);
+        }
+        Some(collider)
+    }
+
+    pub fn body(&self, handle: ColliderHandle) -> Option<&RigidBody<T>> {
+        match self.bodies.get(handle.index())? {
+            Some((h, body)) if *h == handle => Some(body),
+            _ => None,
+        }
+    }
+
+    /// Wakes the body up, as it's usually about to be changed.
+    pub fn body_mut(&mut self, handle: ColliderHandle) -> Option<&mut RigidBody<T>> {
+        match self.bodies.get_mut(handle.index())? {
+            Some((h, body)) if *h == handle => {
+                body.wake_up();
+                Some(body)
+            }
+            _ => None,
+        }
+    }
+
+    /// Its island follows on the next step.
+    pub fn wake_up(&mut self, handle: ColliderHandle) {
+        self.body_mut(handle);
+    }
+
+    /// A joint between `a` and `b` whose frame sits at `frame` in world
+    /// space, given where the colliders are now.
+    pub fn joint_at(
+        &self,
+        kind: JointKind,
+        a: ColliderHandle,
+        b: ColliderHandle,
+        frame: Isometry<T>,
+    ) -> Option<Joint<T>> {
+        let transform_a = self.world.get(a)?.transform;
+        let transform_b = self.world.get(b)?.transform;
+        let frame_a = transform_a.inverse() * frame;
+        let frame_b = transform_b.inverse() * frame;
+        Some(Joint::new(kind, a, frame_a, b, frame_b))
+    }
+
+    pub fn insert_joint(&mut self, joint: Joint<T>) -> JointHandle {
+        let index = match self.free_joints.pop() {
+            Some(index) => index as usize,
+            None => {
+                self.joints.push(JointSlot {
+                    generation: 0,
+                    joint: None,
+                });
+                self.joints.len() - 1
+            }
+        };
+        let slot = &mut self.joints[index];
+        slot.joint = Some(joint);
+        JointHandle::new(index, slot.generation)
+    }
+
+    pub fn remove_joint(&mut self, handle: JointHandle) -> Option<Joint<T>> {
+        let slot = self.joints.get_mut(handle.index())?;
+        if slot.generation != handle.generation() {
+            return None;
+        }
+        let joint = slot.joint.take()?;
+        slot.generation += 1;
+        self.free_joints.push(handle.index() as u32);
+        Some(joint)
+    }
+
+    pub fn joint(&self, handle: JointHandle) -> Option<&Joint<T>> {
+        self.joints
+            .get(handle.index())
+            .filter(|slot| slot.generation == handle.generation())?
+            .joint
+            .as_ref()
+    }
+
+    pub fn joint_mut(&mut self, handle: JointHandle) -> Option<&mut Joint<T>> {
+        self.joints
+            .get_mut(handle.index())
+            .filter(|slot| slot.generation == handle.generation())?
+            .joint
+            .as_mut()
+    }
+
+    pub fn joints(&self) -> impl Iterator<Item = (JointHandle, &Joint<T>)> {
+        self.joints.iter().enumerate().filter_map(|(index, slot)| {
+            let joint = slot.joint.as_ref()?;
+            Some((JointHandle::new(index, slot.generation), joint))
+        })
+    }
+
+    /// Changes a body's velocities as if `impulse` hit it at `point`, both in
+    /// world space.
+    pub fn apply_impulse(
+        &mut self,
+        handle: ColliderHandle,
+        impulse: impl Into<Vec3<T>>,
+        point: impl Into<Vec3<T>>,
+    ) {
+        let Some(transform) = self.world.get(handle).map(|c| c.transform) else {
+            return;
+        };
+        let Some(body) = self.body_mut(handle).filter(|b| b.is_dynamic()) else {
+            return;
+        };
+        let mut solver = body.solver_body(&transform);
+        solver.apply_impulse(
+            impulse.into().into(),
+            sub(point.into().into(), solver.center),
+        );
+        body.linear_velocity = solver.linear;
+        body.angular_velocity = solver.angular;
+    }
+
+    /// Advances the simulation by `dt` seconds. The world is updated at the
+    /// end, so queries and `world().events()` see where the bodies moved to.
+    pub fn step(&mut self, dt: T) {
+        for (_, body) in self.bodies.iter_mut().flatten() {
+            if !body.is_active() {
+                continue;
+            }
+            let gravity = scale(self.gravity, body.gravity_scale * dt);
+            body.linear_velocity = add(body.linear_velocity, gravity);
+            body.linear_velocity = scale(
+                body.linear_velocity,
+                T::ONE / (T::ONE + dt * body.linear_damping),
+            );
+            body.angular_velocity = scale(
+                body.angular_velocity,
+                T::ONE / (T::ONE + dt * body.angular_damping),
+            );
+        }
+        self.world.rebuild();
+
+        let mut connected = Vec::new();
+        self.links.clear();
+        for joint in self.joints.iter().filter_map(|slot| slot.joint.as_ref()) {
+            let pair = (joint.a.min(joint.b), joint.a.max(joint.b));
+            if !joint.collide_connected {
+                connected.push(pair);
+            }
+            self.links.push(pair);
+        }
+        connected.sort_unstable();
+        let mut pairs = Vec::new();
+        self.world.contact_pairs(|a, b| {
+            if connected.binary_search(&(a, b)).is_err() {
+                pairs.push((a, b))
+            }
+        });
+        self.links.extend_from_slice(&pairs);
+        self.links.sort_unstable();
+        let mut islands = self.wake_islands();
+
+        // Slot zero is shared by everything static or asleep
+        let mut solver_bodies = vec![SolverBody::fixed()];
+        let mut solver_index = vec![0; self.bodies.len()];
+        let mut active = vec![false];
+        for (index, (handle, body)) in self
+            .bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| Some((index, slot.as_ref()?)))
+        {
+            if body.sleeping {
+                continue;
+            }
+            if let Some(collider) = self.world.get(*handle) {
+                solver_index[index] = solver_bodies.len();
+                solver_bodies.push(body.solver_body(&collider.transform));
+                active.push(body.is_dynamic());
+            }
+        }
+        let index_of = |handle: ColliderHandle| {
+            solver_index.get(handle.index()).copied().unwrap_or(0)
+        };
+
+        let mut rows: Vec<Row<T>> = Vec::new();
+        for (index, slot) in self.joints.iter().enumerate() {
+            let Some(joint) = &slot.joint else {
+                continue;
+            };
+            let (Some(a), Some(b)) =
+                (self.world.get(joint.a), self.world.get(joint.b))
+            else {
+                continue;
+            };
+            let bodies = (index_of(joint.a), index_of(joint.b));
+            if !active[bodies.0] && !active[bodies.1] {
+                continue;
+            }
+            let transforms = (&a.transform, &b.transform);
+            joint.rows(index, bodies, transforms, &solver_bodies, dt, &mut rows);
+        }
+
+        let mut constraints = Vec::new();
+        for (a, b) in pairs {
+            let bodies = (index_of(a), index_of(b));
+            if !active[bodies.0] && !active[bodies.1] {
+                continue;
+            }
+            let (collider_a, collider_b) =
+                (self.world.get(a).unwrap(), self.world.get(b).unwrap());
+            let Some(manifold) = contacts::contact(
+                &collider_a.shape,
+                &collider_a.transform,
+                &collider_b.shape,
+                &collider_b.transform,
+            ) else {
+                continue;
+            };
+            let cached = self
+                .cache
+                .binary_search_by_key(&(a, b), |cached| cached.pair)
+                .ok()
+                .map(|i| &self.cache[i]);
+            let material_a = collider_a.material.unwrap_or(self.material);
+            let material_b = collider_b.material.unwrap_or(self.material);
+            let (friction, restitution) = material_a.combine(&material_b);
+            constraints.push(ContactConstraint::new(
+                (a, b),
+                bodies,
+                &solver_bodies,
+                manifold,
+                cached,
+                friction,
+                restitution,
+                dt,
+            ));
+        }
+
+        for row in &rows {
+            row.warm_start(&mut solver_bodies);
+        }
+        for constraint in &constraints {
+            constraint.warm_start(&mut solver_bodies);
+        }
+        for _ in 0..self.iterations {
+            for row in &mut rows {
+                row.solve(&mut solver_bodies);
+            }
+            for constraint in &mut constraints {
+                constraint.solve(&mut solver_bodies);
+            }
+        }
+        for joint in self
+            .joints
+            .iter_mut()
+            .filter_map(|slot| slot.joint.as_mut())
+        {
+            joint.clear_impulses();
+        }
+        for row in &rows {
+            if let Some(joint) = &mut self.joints[row.joint].joint {
+                joint.store_impulse(row);
+            }
+        }
+        // Sleeping pairs keep what they had, ready for when they wake
+        let sleeping = self.cache.iter().filter(|cached| {
+            let (a, b) = cached.pair;
+            index_of(a) == 0
+                && index_of(b) == 0
+                && self.links.binary_search(&(a, b)).is_ok()
+        });
+        let mut cache: Vec<_> = sleeping.cloned().collect();
+        cache.extend(constraints.iter().map(ContactConstraint::cached));
+        cache.sort_unstable_by_key(|cached| cached.pair);
+        self.cache = cache;
+
+        // Swept before anything moves, so every cast sees the same world
+        let mut fractions = vec![T::ONE; self.bodies.len()];
+        for (index, slot) in self.bodies.iter().enumerate() {
+            let Some((handle, body)) = slot else {
+                continue;
+            };
+            if !body.ccd || solver_index[index] == 0 || !body.is_dynamic() {
+                continue;
+            }
+            let motion = scale(solver_bodies[solver_index[index]].linear, dt);
+            if let Some(time) =
+                ccd_fraction(&self.world, &self.links, *handle, motion)
+            {
+                fractions[index] = time;
+            }
+        }
+        for (index, slot) in self.bodies.iter_mut().enumerate() {
+            let Some((handle, body)) = slot else {
+                continue;
+            };
+            let solved = match solver_index[index] {
+                0 => continue,
+                i => &solver_bodies[i],
+            };
+            body.linear_velocity = solved.linear;
+            body.angular_velocity = solved.angular;
+            if solved.linear == [T::ZERO; 3] && solved.angular == [T::ZERO; 3] {
+                continue;
+            }
+            let mut solved = *solved;
+            solved.linear = scale(solved.linear, fractions[index]);
+            let transform = &self.world.get(*handle).unwrap().transform;
+            let moved = integrate(transform, body.mass.local_center, &solved, dt);
+            self.world.set_transform(*handle, moved);
+        }
+        self.sleep_islands(&mut islands, dt);
+        self.world.update();
+    }
+
+    /// Joins bodies linked by contacts or joints into islands and wakes every
+    /// island with an awake body in it, or touched by a moving kinematic one.
+    fn wake_islands(&mut self) -> UnionFind {
+        let mut islands = UnionFind::new(self.bodies.len());
+        let mut kicked = Vec::new();
+        for &(a, b) in &self.links {
+            match (self.body(a), self.body(b)) {
+                (Some(body_a), Some(body_b)) => {
+                    if body_a.is_dynamic() && body_b.is_dynamic() {
+                        islands.union(a.index(), b.index());
+                    } else if body_a.is_dynamic() && is_moving(body_b) {
+                        kicked.push(a.index());
+                    } else if body_b.is_dynamic() && is_moving(body_a) {
+                        kicked.push(b.index());
+                    }
+                }
+                _ => continue,
+            }
+        }
+        let mut awake = vec![false; self.bodies.len()];
+        for (index, slot) in self.bodies.iter().enumerate() {
+            if slot.as_ref().is_some_and(|(_, body)| body.is_active()) {
+                awake[islands.find(index)] = true;
+            }
+        }
+        for index in kicked {
+            awake[islands.find(index)] = true;
+        }
+        for (index, slot) in self.bodies.iter_mut().enumerate() {
+            if let Some((_, body)) = slot {
+                if body.sleeping && awake[islands.find(index)] {
+                    body.wake_up();
+                }
+            }
+        }
+        islands
+    }
+
+    /// Puts every island to sleep whose bodies have all rested long enough.
+    fn sleep_islands(&mut self, islands: &mut UnionFind, dt: T) {
+        let mut rested = vec![T::INFINITY; self.bodies.len()];
+        for (index, slot) in self.bodies.iter_mut().enumerate() {
+            let Some((_, body)) = slot else {
+                continue;
+            };
+            if !body.is_active() {
+                continue;
+            }
+            let resting = body.can_sleep
+                && length(body.linear_velocity) < self.sleep_linear_threshold
+                && length(body.angular_velocity) < self.sleep_angular_threshold;
+            body.resting_time = if resting {
+                body.resting_time + dt
+            } else {
+                T::ZERO
+            };
+            let root = islands.find(index);
+            rested[root] = rested[root].min(body.resting_time);
+        }
+        for (index, slot) in self.bodies.iter_mut().enumerate() {
+            if let Some((_, body)) = slot {
+                if body.is_active()
+                    && rested[islands.find(index)] >= self.time_to_sleep
+                {
+                    body.sleep();
+                }
+            }
+        }
+    }
+}
+
+/// The fraction of `motion` a CCD body can make before running into a
+/// collider it would collide with and isn't already touching. It's let in
+/// by `SLOP` so the contact solver takes over on the next step.
+fn ccd_fraction<T: Real>(
+    world: &World<T>,
+    links: &[(ColliderHandle, ColliderHandle)],
+    handle: ColliderHandle,
+    motion: [T; 3],
+) -> Option<T> {
+    let collider = world.get(handle)?;
+    let untouched = |other: ColliderHandle, candidate: &Collider<T>| {
+        let accepted = if handle < other {
+            world.accepts_pair(collider, candidate)
+        } else {
+            world.accepts_pair(candidate, collider)
+        };
+        let pair = (handle.min(other), handle.max(other));
+        other != handle
+            && !candidate.sensor
+            && accepted
+            && links.binary_search(&pair).is_err()
+    };
+    let filter = QueryFilter::new(collider.groups).with_predicate(&untouched);
+    let hit = world.cast_shape(
+        &collider.shape,
+        &collider.transform,
+        motion,
+        T::ONE,
+        filter,
+    )?;
+    let time = hit.time + T::from_f64(SLOP) / length(motion);
+    (time < T::ONE).then_some(time)
+}
+
+fn is_moving<T: Real>(body: &RigidBody<T>) -> bool {
+    body.linear_velocity != [T::ZERO; 3] || body.angular_velocity != [T::ZERO; 3]
+}
+
+/// Moves `transform` along the body's velocities, rotating about its centre
+/// of mass.
+fn integrate<T: Real>(
+    transform: &Isometry<T>,
+    local_center: [T; 3],
+    body: &SolverBody<T>,
+    dt: T,
+) -> Isometry<T> {
+    let center = add(body.center, scale(body.linear, dt));
+    let [x, y, z] = scale(body.angular, dt * T::HALF);
+    let spin = quat_mul([x, y, z, T::ZERO], transform.rotation);
+    let rotation: [T; 4] = core::array::from_fn(|i| transform.rotation[i] + spin[i]);
+    let rotated = Isometry::new([T::ZERO; 3], rotation);
+    let translation = sub(center, rotated.transform_vector::<[T; 3]>(local_center));
+    rotated.with_translation(translation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heightfield::Heightfield;
+    use crate::materials::CombineRule;
+    use crate::ray::Ray;
+    use crate::shapes::{Aabb, Capsule, Obb, Sphere};
+    use crate::test_utils::physics::{close, run, unit_box, with_ground};
+    use crate::voxels::VoxelGrid;
+    use crate::world::CollisionEvent;
+
+    #[test]
+    fn mass_properties() {
+        let cube =
+            MassProperties::from_shape(&Aabb::new([0.0; 3], [1.0; 3]).into(), 2.0);
+        assert!(close(cube.mass, 2.0, 1e-6));
+        assert_eq!(cube.local_center, [0.5; 3]);
+        assert!(close(cube.inertia[0][0], 2.0 / 6.0, 1e-6));
+        assert_eq!(cube.inertia[0][1], 0.0);
+
+        let sphere =
+            MassProperties::from_shape(&Sphere::new([0.0; 3], 1.0).into(), 1.0);
+        let mass = 4.0 / 3.0 * core::f32::consts::PI;
+        assert!(close(sphere.mass, mass, 1e-5));
+        assert!(close(sphere.inertia[2][2], 0.4 * mass, 1e-5));
+
+        // A long box turned onto its side swaps its moments around
+        let turned = Obb::new(
+            [0.0; 3],
+            [1.0, 0.5, 0.5],
+            [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+        );
+        let turned = MassProperties::from_shape(&turned.into(), 1.0);
+        let upright = MassProperties::from_shape(
+            &Aabb::new([-0.5, -1.0, -0.5], [0.5, 1.0, 0.5]).into(),
+            1.0,
+        );
+        for (row, expected) in turned.inertia.iter().zip(upright.inertia.iter()) {
+            for (a, b) in row.iter().zip(expected.iter()) {
+                assert!(close(*a, *b, 1e-5));
+            }
+        }
+
+        // With no length a capsule is a sphere
+        let ball = MassProperties::from_shape(
+            &Capsule::new([0.0; 3], [0.0; 3], 1.0).into(),
+            1.0,
+        );
+        assert!(close(ball.mass, sphere.mass, 1e-5));
+        assert!(close(ball.inertia[0][0], sphere.inertia[0][0], 1e-5));
+        let capsule = Capsule::upright(1.0, 0.5);
+        let long = MassProperties::from_shape(&capsule.into(), 3.0);
+        assert!(close(long.mass, 3.0 * capsule.volume(), 1e-5));
+        assert!(long.inertia[1][1] < long.inertia[0][0]);
+        assert!(close(long.inertia[0][0], long.inertia[2][2], 1e-6));
+    }
+
+    #[test]
+    fn free_fall() {
+        let mut world = PhysicsWorld::new();
+        let ball = Sphere::new([0.0; 3], 0.5);
+        let body = RigidBody::dynamic(MassProperties::from_shape(&ball.into(), 1.0))
+            .with_angular_velocity([0.0, 1.0, 0.0]);
+        let handle = world.insert(Collider::new(ball, Isometry::identity()), body);
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+        let body = world.body(handle).unwrap();
+        assert!(close(body.linear_velocity[1], -9.81, 1e-4));
+        let transform = world.world().get(handle).unwrap().transform;
+        // Semi-implicit Euler moves by the already updated velocity
+        let fallen = 9.81 / 3600.0 * (60.0 * 61.0 / 2.0);
+        assert!(close(transform.translation[1], -fallen, 1e-3));
+        let expected = Isometry::<f32>::from_axis_angle([0.0, 1.0, 0.0], 1.0);
+        for (a, b) in transform.rotation.iter().zip(expected.rotation.iter()) {
+            assert!(close(*a, *b, 1e-3));
+        }
+    }
+
+    #[test]
+    fn queries_after_step() {
+        let mut world = with_ground();
+        let falling = unit_box(&mut world, [0.0, 0.6, 0.0]);
+        let down = Ray::new([0.0, 5.0, 0.0], [0.0, -1.0, 0.0]);
+        let mut landed = false;
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+            let y = world.world().get(falling).unwrap().transform.translation[1];
+            let all = QueryFilter::default();
+            let hit = world.world().cast_ray(down, f32::INFINITY, all).unwrap();
+            assert_eq!(hit.collider, falling);
+            assert!(close(hit.time, 4.5 - y, 1e-4), "{} {}", hit.time, y);
+            // Events describe the positions the step ended at
+            let events = world.world().events();
+            if events
+                .iter()
+                .any(|e| matches!(e, CollisionEvent::Started(_)))
+            {
+                assert!(!landed && y < 0.5, "{}", y);
+                landed = true;
+            }
+        }
+        assert!(landed);
+    }
+
+    #[test]
+    fn stacking() {
+        let mut world = with_ground();
+        let boxes: Vec<_> = (0..3)
+            .map(|i| unit_box(&mut world, [0.0, 0.5 + i as f32 * 1.01, 0.0]))
+            .collect();
+        let capsule = Capsule::new([-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.5);
+        let body =
+            RigidBody::dynamic(MassProperties::from_shape(&capsule.into(), 1.0));
+        let lying = world.insert(
+            Collider::new(capsule, Isometry::from_translation([4.0, 0.6, 0.0])),
+            body,
+        );
+        for _ in 0..180 {
+            world.step(1.0 / 60.0);
+        }
+        for (i, handle) in boxes.into_iter().enumerate() {
+            let transform = world.world().get(handle).unwrap().transform;
+            assert!(
+                close(transform.translation[1], 0.5 + i as f32, 0.05),
+                "{:?}",
+                transform
+            );
+            assert!(close(transform.translation[0], 0.0, 0.02));
+            assert!(close(transform.rotation[3].abs(), 1.0, 1e-3));
+            let body = world.body(handle).unwrap();
+            assert!(length(body.linear_velocity) < 0.05);
+        }
+        let transform = world.world().get(lying).unwrap().transform;
+        assert!(close(transform.translation[1], 0.5, 0.02));
+        assert!(length(world.body(lying).unwrap().angular_velocity) < 0.05);
+    }
+
+    #[test]
+    fn friction_and_restitution() {
+        let mut world = with_ground();
+        let sliding = unit_box(&mut world, [0.0, 0.5, 0.0]);
+        world.body_mut(sliding).unwrap().linear_velocity = [3.0, 0.0, 0.0];
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+        // Sliding to a stop takes v² / 2μg
+        let x = world.world().get(sliding).unwrap().transform.translation[0];
+        assert!(close(x, 9.0 / (2.0 * 0.5 * 9.81), 0.1), "{}", x);
+        assert!(world.body(sliding).unwrap().linear_velocity[0].abs() < 1e-3);
+
+        // Ice keeps its low friction against the ground's default material
+        let mut world = with_ground();
+        let ice = Material::new(0.05, 0.0).with_friction_combine(CombineRule::Min);
+        let shape = Shape::Aabb(Aabb::new([-0.5; 3], [0.5; 3]));
+        let body = RigidBody::dynamic(MassProperties::from_shape(&shape, 1.0))
+            .with_linear_velocity([3.0, 0.0, 0.0]);
+        let icy = world.insert(
+            Collider::new(shape, Isometry::from_translation([-5.0, 0.5, 0.0]))
+                .with_material(ice),
+            body,
+        );
+        for _ in 0..480 {
+            world.step(1.0 / 60.0);
+        }
+        let x = world.world().get(icy).unwrap().transform.translation[0];
+        assert!(close(x + 5.0, 9.0 / (2.0 * 0.05 * 9.81), 0.5), "{}", x);
+
+        let mut world = with_ground();
+        world.material.restitution = 1.0;
+        let ball = Sphere::new([0.0; 3], 0.5);
+        let body = RigidBody::dynamic(MassProperties::from_shape(&ball.into(), 1.0));
+        let bouncing = world.insert(
+            Collider::new(ball, Isometry::from_translation([0.0, 2.5, 0.0])),
+            body,
+        );
+        let mut highest_after_bounce = 0.0f32;
+        let mut bounced = false;
+        for _ in 0..240 {
+            world.step(1.0 / 120.0);
+            let velocity = world.body(bouncing).unwrap().linear_velocity[1];
+            bounced |= velocity > 0.0;
+            if bounced {
+                let y =
+                    world.world().get(bouncing).unwrap().transform.translation[1];
+                highest_after_bounce = highest_after_bounce.max(y);
+            }
+        }
+        assert!(bounced);
+        assert!(highest_after_bounce > 2.2, "{}", highest_after_bounce);
+    }
+
+    #[test]
+    fn terrain() {
+        // Flat for x < 0, then rising by 0.3 per unit
+        let heights = (0..21 * 21)
+            .map(|i| ((i % 21) as f32 - 10.0).max(0.0) * 0.3)
+            .collect();
+        let field = Heightfield::new(21, 21, heights, [1.0; 3]);
+        let mut world = PhysicsWorld::new();
+        world.world_mut().insert(Collider::new(
+            field,
+            Isometry::from_translation([-10.0, 0.0, -10.0]),
+        ));
+        let resting = unit_box(&mut world, [-5.0, 1.0, 0.5]);
+        let ball = Sphere::new([0.0; 3], 0.5);
+        let body = RigidBody::dynamic(MassProperties::from_shape(&ball.into(), 1.0));
+        let rolling = world.insert(
+            Collider::new(ball, Isometry::from_translation([5.0, 3.0, -5.0])),
+            body,
+        );
+        for _ in 0..240 {
+            world.step(1.0 / 60.0);
+        }
+        let position =
+            |handle| world.world().get(handle).unwrap().transform.translation;
+        assert!(
+            close(position(resting)[1], 0.5, 0.02),
+            "{:?}",
+            position(resting)
+        );
+        assert!(
+            close(position(resting)[0], -5.0, 0.02),
+            "{:?}",
+            position(resting)
+        );
+        let [x, y, _] = position(rolling);
+        assert!(x < 2.0, "{}", x);
+        assert!(y > x.max(0.0) * 0.3 + 0.4, "{} {}", x, y);
+    }
+
+    #[test]
+    fn voxel_floor() {
+        let floor = (-10..10).flat_map(|x| (-10..10).map(move |z| [x, -1, z]));
+        let mut world = PhysicsWorld::new();
+        world.world_mut().insert(Collider::new(
+            VoxelGrid::sparse(1.0).with_occupied(floor),
+            Isometry::identity(),
+        ));
+        let sliding = unit_box(&mut world, [0.0, 0.5, 0.0]);
+        world.body_mut(sliding).unwrap().linear_velocity = [6.0, 0.0, 0.0];
+        let dropped = unit_box(&mut world, [-4.5, 3.0, 3.2]);
+        for _ in 0..180 {
+            world.step(1.0 / 60.0);
+        }
+        // Crossing the seams between cells neither trips nor slows the box
+        let [x, y, _] = world.world().get(sliding).unwrap().transform.translation;
+        assert!(close(x, 36.0 / (2.0 * 0.5 * 9.81), 0.1), "{}", x);
+        assert!(close(y, 0.5, 0.02), "{}", y);
+        let rotation = world.world().get(sliding).unwrap().transform.rotation;
+        assert!(rotation[3].abs() > 0.999, "{:?}", rotation);
+        let [x, y, z] = world.world().get(dropped).unwrap().transform.translation;
+        assert!(close(y, 0.5, 0.02), "{}", y);
+        assert!(close(x, -4.5, 0.02) && close(z, 3.2, 0.02), "{} {}", x, z);
+    }
+
+    #[test]
+    fn buried_in_voxels() {
+        let block = (-3..3).flat_map(|x| {
+            (-4..0).flat_map(move |y| (-3..3).map(move |z| [x, y, z]))
+        });
+        let mut world = PhysicsWorld::new();
+        world.world_mut().insert(Collider::new(
+            VoxelGrid::sparse(1.0).with_occupied(block),
+            Isometry::identity(),
+        ));
+        // Dropped in so deep that every face it overlaps is inside the block
+        let buried = unit_box(&mut world, [0.3, -0.7, 0.2]);
+        run(&mut world, 6.0);
+        let [x, y, z] = world.world().get(buried).unwrap().transform.translation;
+        assert!(close(y, 0.5, 0.05), "{}", y);
+        assert!(close(x, 0.3, 0.05) && close(z, 0.2, 0.05), "{} {}", x, z);
+    }
+
+    #[test]
+    fn sleeping() {
+        let mut world = with_ground();
+        let stack: Vec<_> = (0..4)
+            .map(|i| unit_box(&mut world, [0.0, 0.5 + i as f32 * 1.01, 0.0]))
+            .collect();
+        let restless = unit_box(&mut world, [5.0, 0.5, 0.0]);
+        world.body_mut(restless).unwrap().can_sleep = false;
+        for _ in 0..240 {
+            world.step(1.0 / 60.0);
+        }
+        assert!(stack.iter().all(|&h| world.body(h).unwrap().is_sleeping()));
+        assert!(!world.body(restless).unwrap().is_sleeping());
+        let before: Vec<_> = stack
+            .iter()
+            .map(|&h| world.world().get(h).unwrap().transform)
+            .collect();
+        world.step(1.0 / 60.0);
+        for (h, transform) in stack.iter().zip(&before) {
+            assert_eq!(world.world().get(*h).unwrap().transform, *transform);
+        }
+
+        // Landing on top wakes the whole island
+        unit_box(&mut world, [0.0, 5.0, 0.0]);
+        let mut woken = false;
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+            woken |= stack.iter().all(|&h| !world.body(h).unwrap().is_sleeping());
+        }
+        assert!(woken);
+        for _ in 0..240 {
+            world.step(1.0 / 60.0);
+        }
+        assert!(stack.iter().all(|&h| world.body(h).unwrap().is_sleeping()));
+
+        // So do impulses, and losing the ground
+        world.apply_impulse(stack[3], [0.0, 0.0, 0.1], [0.0, 4.0, 0.0]);
+        assert!(!world.body(stack[3]).unwrap().is_sleeping());
+        let ground = world.world().iter().next().unwrap().0;
+        world.remove(ground);
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        for &h in &stack {
+            assert!(!world.body(h).unwrap().is_sleeping());
+            assert!(world.body(h).unwrap().linear_velocity[1] < -1.0);
+        }
+    }
+
+    #[test]
+    fn continuous() {
+        let mut world = PhysicsWorld::new();
+        world.gravity = [0.0; 3];
+        let walls = world.world_mut();
+        walls.insert(Collider::new(
+            Aabb::new([5.0, -1.0, -1.0], [5.1, 1.0, 1.0]),
+            Isometry::identity(),
+        ));
+        walls.insert(Collider::new(
+            Obb::from_rotation(
+                [5.0, 0.0, 5.0],
+                [0.05, 1.0, 1.0],
+                Isometry::from_axis_angle([0.0, 1.0, 0.0], 0.3).rotation,
+            ),
+            Isometry::identity(),
+        ));
+        let bullet = Sphere::new([0.0; 3], 0.05);
+        let mass = MassProperties::from_shape(&bullet.into(), 1.0);
+        let fire = |world: &mut PhysicsWorld, z: f32, ccd: bool| {
+            let body = RigidBody::dynamic(mass)
+                .with_linear_velocity([270.0, 0.0, 0.0])
+                .with_ccd(ccd);
+            let position = Isometry::from_translation([0.0, 0.0, z]);
+            world.insert(Collider::new(bullet, position), body)
+        };
+        let tunnelling = fire(&mut world, 0.0, false);
+        let stopped = [fire(&mut world, 0.0, true), fire(&mut world, 5.0, true)];
+        let target = unit_box(&mut world, [8.0, 0.0, 10.0]);
+        let pushing = fire(&mut world, 10.0, true);
+
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+        let position =
+            |handle| world.world().get(handle).unwrap().transform.translation;
+        let x = |handle| position(handle)[0];
+        assert!(x(tunnelling) > 20.0);
+        assert!(x(stopped[0]) < 5.0);
+        assert!(world.body(stopped[0]).unwrap().linear_velocity[0] < 1.0);
+        // Glancing off the turned wall, but still on the near side of it
+        let (sin, cos) = 0.3f32.sin_cos();
+        let offset = sub(position(stopped[1]), [5.0, 0.0, 5.0]);
+        assert!(dot(offset, [cos, 0.0, -sin]) < 0.0, "{:?}", offset);
+        // Momentum still reaches dynamic bodies that are hit
+        assert!(x(pushing) < x(target));
+        assert!(world.body(target).unwrap().linear_velocity[0] > 0.1);
+    }
+
+    #[test]
+    fn replays() {
+        let mut world = with_ground();
+        let stack: Vec<_> = (0..3)
+            .map(|i| unit_box(&mut world, [0.1 * i as f32, 0.5 + i as f32, 0.0]))
+            .collect();
+        let ball = Sphere::new([0.0; 3], 0.25);
+        let body = RigidBody::dynamic(MassProperties::from_shape(&ball.into(), 1.0))
+            .with_linear_velocity([-6.0, 2.0, 0.5]);
+        world.insert(
+            Collider::new(ball, Isometry::from_translation([4.0, 1.0, 0.0])),
+            body,
+        );
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        // Everything a step depends on lives in the world, so a copy stays in
+        // lockstep with the original down to the last bit. This is all the
+        // default build promises, `recorded_bits` covers other platforms
+        let mut copy = world.clone();
+        let bits = |world: &PhysicsWorld| -> Vec<u32> {
+            world
+                .world()
+                .iter()
+                .flat_map(|(_, collider)| {
+                    let Isometry {
+                        translation,
+                        rotation,
+                    } = collider.transform;
+                    translation.into_iter().chain(rotation).map(f32::to_bits)
+                })
+                .collect()
+        };
+        for _ in 0..90 {
+            world.step(1.0 / 60.0);
+            copy.step(1.0 / 60.0);
+            assert_eq!(bits(&world), bits(&copy));
+        }
+        let moved = world.world().get(stack[2]).unwrap().transform.translation;
+        assert_ne!(moved, [0.2, 2.5, 0.0]);
+    }
+
+    /// Bit patterns recorded from a run, which any platform has to reproduce
+    /// once trigonometry comes from `libm`.
+    #[cfg(feature = "deterministic")]
+    #[test]
+    fn recorded_bits() {
+        let mut world = with_ground();
+        let tilted = Isometry::from_axis_angle([1.0, 0.0, 1.0], 0.4)
+            .with_translation([0.0, 2.0, 0.0]);
+        let shape = Shape::Obb(Obb::from(Aabb::new([-0.5; 3], [0.5; 3])));
+        let body = RigidBody::dynamic(MassProperties::from_shape(&shape, 1.0))
+            .with_angular_velocity([0.0, 3.0, 0.0]);
+        let tumbling = world.insert(Collider::new(shape, tilted), body);
+        let resting = unit_box(&mut world, [2.0, 0.5, 0.0]);
+        for _ in 0..90 {
+            world.step(1.0 / 60.0);
+        }
+        let bits = [tumbling, resting].map(|handle| {
+            let Isometry {
+                translation,
+                rotation,
+            } = world.world().get(handle).unwrap().transform;
+            let mut bits = [0; 7];
+            for (bits, value) in
+                bits.iter_mut().zip(translation.into_iter().chain(rotation))
+            {
+                *bits = value.to_bits();
+            }
+            bits
+        });
+        assert_eq!(
+            bits,
+            [
+                [
+                    0xbec77837, 0x3efdf11d, 0xbe417008, 0x3a5bf8b2, 0x3f577eb2,
+                    0x3a0a2a3f, 0x3f0a31f3,
+                ],
+                [
+                    0x3ffffe49, 0x3efff2e8, 0x3801d522, 0x3836453e, 0x3777e948,
+                    0x370c4d83, 0x3f800000,
+                ],
+            ]
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! C API over the f32 queries. The header is generated with
+//! `cbindgen --config cbindgen.toml --output include/squad.h`.
+
+use crate::bvh::Bvh;
+use crate::dispatch;
+use crate::intersections;
+use crate::isometry::Isometry;
+use crate::ray::Ray;
+use crate::shapes::{Aabb, Obb, Sphere};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::slice;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SquadVec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl From<SquadVec3> for mint::Vector3<f32> {
+    fn from(v: SquadVec3) -> Self {
+        mint::Vector3 {
+            x: v.x,
+            y: v.y,
+            z: v.z,
+        }
+    }
+}
+
+/// A bvh over an array of aabbs, created with `squad_bvh_new` and released
+/// with `squad_bvh_free`.
+pub struct SquadBvh {
+    bvh: Bvh<f32>,
+    aabbs: Vec<Aabb<f32>>,
+}
+
+/// Called once per hit by the batch functions, with the `user_data` pointer
+/// passed alongside it.
+pub type SquadPairCallback =
+    extern "C" fn(user_data: *mut c_void, a: usize, b: usize);
+pub type SquadRayCallback =
+    extern "C" fn(user_data: *mut c_void, ray: usize, aabb: usize, time: f32);
+
+#[no_mangle]
+pub extern "C" fn squad_aabb_aabb(a: Aabb<f32>, b: Aabb<f32>) -> bool {
+    intersections::aabb_aabb(a, b)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_aabb_point(aabb: Aabb<f32>, point: SquadVec3) -> bool {
+    intersections::aabb_point(aabb, point)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_aabb_sphere(aabb: Aabb<f32>, sphere: Sphere<f32>) -> bool {
+    intersections::aabb_sphere(aabb, sphere)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_obb_obb(a: Obb<f32>, b: Obb<f32>) -> bool {
+    intersections::obb_obb(a, b)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_obb_point(obb: Obb<f32>, point: SquadVec3) -> bool {
+    intersections::obb_point(obb, point)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_obb_sphere(obb: Obb<f32>, sphere: Sphere<f32>) -> bool {
+    intersections::obb_sphere(obb, sphere)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_sphere_sphere(a: Sphere<f32>, b: Sphere<f32>) -> bool {
+    intersections::sphere_sphere(a, b)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_sphere_point(sphere: Sphere<f32>, point: SquadVec3) -> bool {
+    intersections::sphere_point(sphere, point)
+}
+
+/// Time of impact along the ray, or a negative value on a miss.
+#[no_mangle]
+pub extern "C" fn squad_ray_aabb(ray: Ray<f32>, aabb: Aabb<f32>) -> f32 {
+    intersections::ray_aabb_time(ray, aabb)
+}
+
+/// Time of impact along the ray, or a negative value on a miss.
+#[no_mangle]
+pub extern "C" fn squad_ray_obb(ray: Ray<f32>, obb: Obb<f32>) -> f32 {
+    intersections::ray_obb_time(ray, obb)
+}
+
+#[no_mangle]
+pub extern "C" fn squad_obb_transformed(
+    obb: Obb<f32>,
+    iso: Isometry<f32>,
+) -> Obb<f32> {
+    obb.transformed(&iso)
+}
+
+unsafe fn as_slice<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+fn aabb_soa(aabbs: &[Aabb<f32>]) -> [Vec<f32>; 6] {
+    core::array::from_fn(|k| {
+        aabbs
+            .iter()
+            .map(|a| if k < 3 { a.min[k] } else { a.max[k - 3] })
+            .collect()
+    })
+}
+
+fn sphere_soa(spheres: &[Sphere<f32>]) -> [Vec<f32>; 4] {
+    core::array::from_fn(|k| {
+        spheres
+            .iter()
+            .map(|s| if k < 3 { s.center[k] } else { s.radius })
+            .collect()
+    })
+}
+
+fn ray_soa(rays: &[Ray<f32>]) -> [Vec<f32>; 6] {
+    core::array::from_fn(|k| {
+        rays.iter()
+            .map(|r| {
+                if k < 3 {
+                    r.origin[k]
+                } else {
+                    r.direction[k - 3]
+                }
+            })
+            .collect()
+    })
+}
+
+/// Calls `callback` for every intersecting pair of `a` and `b`. When `a` and
+/// `b` are the same array each pair is reported once, with `a < b`.
+///
+/// # Safety
+///
+/// `a` and `b` must point to `a_len` and `b_len` valid aabbs.
+#[no_mangle]
+pub unsafe extern "C" fn squad_aabb_aabb_pairs(
+    a: *const Aabb<f32>,
+    a_len: usize,
+    b: *const Aabb<f32>,
+    b_len: usize,
+    callback: SquadPairCallback,
+    user_data: *mut c_void,
+) {
+    let soa_a = aabb_soa(as_slice(a, a_len));
+    let hit = |i, j| callback(user_data, i, j);
+    if a == b && a_len == b_len {
+        dispatch::aabb_aabb_pairs(&soa_a, &soa_a, hit);
+    } else {
+        dispatch::aabb_aabb_pairs(&soa_a, &aabb_soa(as_slice(b, b_len)), hit);
+    }
+}
+
+/// Calls `callback` for every intersecting aabb and sphere.
+///
+/// # Safety
+///
+/// `aabbs` and `spheres` must point to `aabbs_len` and `spheres_len` valid
+/// shapes.
+#[no_mangle]
+pub unsafe extern "C" fn squad_aabb_sphere_pairs(
+    aabbs: *const Aabb<f32>,
+    aabbs_len: usize,
+    spheres: *const Sphere<f32>,
+    spheres_len: usize,
+    callback: SquadPairCallback,
+    user_data: *mut c_void,
+) {
+    dispatch::aabb_sphere_pairs(
+        &aabb_soa(as_slice(aabbs, aabbs_len)),
+        &sphere_soa(as_slice(spheres, spheres_len)),
+        |i, j| callback(user_data, i, j),
+    );
+}
+
+/// Calls `callback` for every intersecting pair of `a` and `b`. When `a` and
+/// `b` are the same array each pair is reported once, with `a < b`.
+///
+/// # Safety
+///
+/// `a` and `b` must point to `a_len` and `b_len` valid spheres.
+#[no_mangle]
+pub unsafe extern "C" fn squad_sphere_sphere_pairs(
+    a: *const Sphere<f32>,
+    a_len: usize,
+    b: *const Sphere<f32>,
+    b_len: usize,
+    callback: SquadPairCallback,
+    user_data: *mut c_void,
+) {
+    let soa_a = sphere_soa(as_slice(a, a_len));
+    let hit = |i, j| callback(user_data, i, j);
+    if a == b && a_len == b_len {
+        dispatch::sphere_sphere_pairs(&soa_a, &soa_a, hit);
+    } else {
+        dispatch::sphere_sphere_pairs(&soa_a, &sphere_soa(as_slice(b, b_len)), hit);
+    }
+}
+
+/// Casts every ray against every aabb, calling `callback` with the time of
+/// impact of each hit.
+///
+/// # Safety
+///
+/// `rays` and `aabbs` must point to `rays_len` and `aabbs_len` valid values.
+#[no_mangle]
+pub unsafe extern "C" fn squad_ray_aabb_pairs(
+    rays: *const Ray<f32>,
+    rays_len: usize,
+    aabbs: *const Aabb<f32>,
+    aabbs_len: usize,
+    callback: SquadRayCallback,
+    user_data: *mut c_void,
+) {
+    dispatch::ray_aabb_pairs(
+        &ray_soa(as_slice(rays, rays_len)),
+        &aabb_soa(as_slice(aabbs, aabbs_len)),
+        |r, a, t| callback(user_data, r, a, t),
+    );
+}
+
+/// Builds a bvh over a copy of `aabbs`, identifying each by its index.
+///
+/// # Safety
+///
+/// `aabbs` must point to `len` valid aabbs.
+#[no_mangle]
+pub unsafe extern "C" fn squad_bvh_new(
+    aabbs: *const Aabb<f32>,
+    len: usize,
+) -> *mut SquadBvh {
+    let aabbs = as_slice(aabbs, len).to_vec();
+    let bvh = Bvh::new(aabbs.iter().copied().zip(0..));
+    Box::into_raw(Box::new(SquadBvh { bvh, aabbs }))
+}
+
+/// Time of impact of the closest aabb hit by `ray` within `max_time`, with its
+/// index stored in `index`, or a negative value on a miss.
+///
+/// # Safety
+///
+/// `bvh` must come from `squad_bvh_new` and `index` must be valid to write.
+#[no_mangle]
+pub unsafe extern "C" fn squad_bvh_cast_ray(
+    bvh: *const SquadBvh,
+    ray: Ray<f32>,
+    max_time: f32,
+    index: *mut usize,
+) -> f32 {
+    let SquadBvh { bvh, aabbs } = &*bvh;
+    let hit = bvh.cast_ray(ray, max_time, |i| {
+        Some(intersections::ray_aabb_time(ray, aabbs[i])).filter(|t| *t >= 0.0)
+    });
+    match hit {
+        Some((i, time)) => {
+            *index = i;
+            time
+        }
+        None => -1.0,
+    }
+}
+
+/// # Safety
+///
+/// `bvh` must come from `squad_bvh_new` and not be used afterwards. Null is
+/// ignored.
+#[no_mangle]
+pub unsafe extern "C" fn squad_bvh_free(bvh: *mut SquadBvh) {
+    if !bvh.is_null() {
+        drop(Box::from_raw(bvh));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn push_pair(user_data: *mut c_void, a: usize, b: usize) {
+        let pairs = unsafe { &mut *(user_data as *mut Vec<(usize, usize)>) };
+        pairs.push((a, b));
+    }
+
+    extern "C" fn push_ray(user_data: *mut c_void, r: usize, a: usize, t: f32) {
+        let hits = unsafe { &mut *(user_data as *mut Vec<(usize, usize, f32)>) };
+        hits.push((r, a, t));
+    }
+
+    #[test]
+    fn c_api() {
+        let aabbs = [
+            Aabb::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+            Aabb::new([0.5, 0.5, 0.5], [2.0, 2.0, 2.0]),
+            Aabb::new([5.0, 0.0, 0.0], [6.0, 1.0, 1.0]),
+        ];
+        let spheres = [Sphere::new([5.5, 0.5, 2.0], 1.2)];
+        let point = SquadVec3 {
+            x: 0.25,
+            y: 0.5,
+            z: 0.75,
+        };
+        assert!(squad_aabb_aabb(aabbs[0], aabbs[1]));
+        assert!(squad_aabb_point(aabbs[0], point));
+        assert!(!squad_sphere_point(spheres[0], point));
+        assert!(squad_aabb_sphere(aabbs[2], spheres[0]));
+        let ray = Ray::new([-1.0, 0.75, 0.75], [1.0, 0.0, 0.0]);
+        assert_eq!(squad_ray_aabb(ray, aabbs[0]), 1.0);
+
+        let mut pairs: Vec<(usize, usize)> = vec![];
+        let user_data = &mut pairs as *mut _ as *mut c_void;
+        unsafe {
+            squad_aabb_aabb_pairs(
+                aabbs.as_ptr(),
+                3,
+                aabbs.as_ptr(),
+                3,
+                push_pair,
+                user_data,
+            );
+            squad_aabb_sphere_pairs(
+                aabbs.as_ptr(),
+                3,
+                spheres.as_ptr(),
+                1,
+                push_pair,
+                user_data,
+            );
+        }
+        assert_eq!(pairs, [(0, 1), (2, 0)]);
+
+        let mut hits: Vec<(usize, usize, f32)> = vec![];
+        unsafe {
+            squad_ray_aabb_pairs(
+                &ray,
+                1,
+                aabbs.as_ptr(),
+                3,
+                push_ray,
+                &mut hits as *mut _ as *mut c_void,
+            );
+        }
+        assert_eq!(hits, [(0, 0, 1.0), (0, 1, 1.5), (0, 2, 6.0)]);
+
+        let mut index = usize::MAX;
+        unsafe {
+            let bvh = squad_bvh_new(aabbs.as_ptr(), 3);
+            assert_eq!(squad_bvh_cast_ray(bvh, ray, f32::INFINITY, &mut index), 1.0);
+            assert_eq!(index, 0);
+            let beside = Ray::new([5.5, 0.5, -3.0], [0.0, 0.0, 1.0]);
+            assert_eq!(squad_bvh_cast_ray(bvh, beside, 10.0, &mut index), 3.0);
+            assert_eq!(index, 2);
+            assert!(squad_bvh_cast_ray(bvh, beside, 2.0, &mut index) < 0.0);
+            squad_bvh_free(bvh);
+        }
+    }
+}
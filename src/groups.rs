@@ -0,0 +1,53 @@
+/// Which layers an object belongs to and which layers it collides with. Two
+/// objects interact when each one's memberships overlap the other's filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct CollisionGroups {
+    pub memberships: u32,
+    pub filter: u32,
+}
+
+impl CollisionGroups {
+    pub const ALL: Self = Self::new(u32::MAX, u32::MAX);
+    pub const NONE: Self = Self::new(0, 0);
+
+    pub const fn new(memberships: u32, filter: u32) -> Self {
+        Self {
+            memberships,
+            filter,
+        }
+    }
+
+    pub fn interacts(self, other: Self) -> bool {
+        self.memberships & other.filter != 0 && other.memberships & self.filter != 0
+    }
+}
+
+impl Default for CollisionGroups {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers() {
+        const PLAYER: u32 = 1;
+        const ENEMY: u32 = 2;
+        const PROJECTILE: u32 = 4;
+        let player = CollisionGroups::new(PLAYER, ENEMY | PROJECTILE);
+        let enemy = CollisionGroups::new(ENEMY, PLAYER | ENEMY);
+        let bullet = CollisionGroups::new(PROJECTILE, PLAYER);
+        assert!(player.interacts(enemy));
+        assert!(enemy.interacts(enemy));
+        assert!(player.interacts(bullet));
+        // Enemies don't listen for projectiles, so neither side sees the other
+        assert!(!enemy.interacts(bullet));
+        assert!(!bullet.interacts(enemy));
+        assert!(!CollisionGroups::NONE.interacts(CollisionGroups::ALL));
+    }
+}
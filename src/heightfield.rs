@@ -0,0 +1,394 @@
+//! Terrain as a grid of heights, split into triangles on demand.
+
+use crate::intersections::ray_triangle_time;
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::shapes::{Aabb, Triangle};
+use crate::Vec3;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// A grid of `rows` by `columns` heights in row major order. The sample at
+/// `(row, column)` sits at `[column * x, height * y, row * z]` for a `scale`
+/// of `[x, y, z]`, and each cell between four samples is split into two
+/// triangles facing up. Everything below the surface counts as solid, down
+/// to the lowest height.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Heightfield<T: Real = f32> {
+    rows: usize,
+    columns: usize,
+    heights: Vec<T>,
+    scale: [T; 3],
+    /// Lowest and highest unscaled height.
+    range: [T; 2],
+}
+
+impl<T: Real> Heightfield<T> {
+    pub fn new(
+        rows: usize,
+        columns: usize,
+        heights: Vec<T>,
+        scale: impl Into<Vec3<T>>,
+    ) -> Self {
+        assert!(
+            rows >= 2 && columns >= 2,
+            "a heightfield needs at least two rows and columns"
+        );
+        assert_eq!(heights.len(), rows * columns, "one height per sample");
+        let scale: [T; 3] = scale.into().into();
+        assert!(
+            scale.iter().all(|s| *s > T::ZERO),
+            "a heightfield's scale must be positive"
+        );
+        let range = heights
+            .iter()
+            .fold([T::INFINITY, -T::INFINITY], |[low, high], h| {
+                [low.min(*h), high.max(*h)]
+            });
+        Self {
+            rows,
+            columns,
+            heights,
+            scale,
+            range,
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// The unscaled heights, in row major order.
+    pub fn heights(&self) -> &[T] {
+        &self.heights
+    }
+
+    pub fn scale<V: From<Vec3<T>>>(&self) -> V {
+        Vec3::from(self.scale).into()
+    }
+
+    /// The sample at `(row, column)` in local space.
+    pub fn point<V: From<Vec3<T>>>(&self, row: usize, column: usize) -> V {
+        let height = self.heights[row * self.columns + column];
+        let [x, y, z] = self.scale;
+        let point = [
+            T::from_f64(column as f64) * x,
+            height * y,
+            T::from_f64(row as f64) * z,
+        ];
+        Vec3::from(point).into()
+    }
+
+    pub fn local_aabb(&self) -> Aabb<T> {
+        let [x, y, z] = self.scale;
+        Aabb {
+            min: [T::ZERO, self.range[0] * y, T::ZERO],
+            max: [
+                T::from_f64((self.columns - 1) as f64) * x,
+                self.range[1] * y,
+                T::from_f64((self.rows - 1) as f64) * z,
+            ],
+        }
+    }
+
+    /// The two triangles of the cell whose lowest corner is the sample at
+    /// `(row, column)`.
+    pub fn cell_triangles(&self, row: usize, column: usize) -> [Triangle<T>; 2] {
+        let p00: [T; 3] = self.point(row, column);
+        let p01: [T; 3] = self.point(row, column + 1);
+        let p10: [T; 3] = self.point(row + 1, column);
+        let p11: [T; 3] = self.point(row + 1, column + 1);
+        [Triangle::new(p00, p10, p11), Triangle::new(p00, p11, p01)]
+    }
+
+    /// The triangles of every cell under `aabb`, in local space, skipping
+    /// cells lying wholly below it.
+    pub fn triangles(
+        &self,
+        aabb: &Aabb<T>,
+    ) -> impl Iterator<Item = Triangle<T>> + '_ {
+        let [x, _, z] = self.scale;
+        let rows = Self::span(aabb.min[2], aabb.max[2], z, self.rows - 1);
+        let columns = Self::span(aabb.min[0], aabb.max[0], x, self.columns - 1);
+        let floor = aabb.min[1];
+        rows.flat_map(move |row| columns.clone().map(move |column| (row, column)))
+            .filter(move |&(row, column)| {
+                let top = [(0, 0), (0, 1), (1, 0), (1, 1)]
+                    .map(|(r, c)| self.point::<[T; 3]>(row + r, column + c)[1])
+                    .into_iter()
+                    .fold(-T::INFINITY, T::max);
+                top >= floor
+            })
+            .flat_map(move |(row, column)| self.cell_triangles(row, column))
+    }
+
+    /// The cells along one axis overlapping `low..=high`.
+    fn span(low: T, high: T, spacing: T, cells: usize) -> Range<usize> {
+        let size = T::from_f64(cells as f64) * spacing;
+        if high < T::ZERO || low > size || low > high {
+            return 0..0;
+        }
+        Self::cell(low, spacing, cells)..Self::cell(high, spacing, cells) + 1
+    }
+
+    /// The cell along one axis containing `value`, clamped to the grid.
+    fn cell(value: T, spacing: T, cells: usize) -> usize {
+        let last = T::from_f64((cells - 1) as f64);
+        let cell = (value / spacing).floor().max(T::ZERO).min(last);
+        cell.to_usize().unwrap_or(0)
+    }
+
+    /// The local height of the surface above `(x, z)`, or `None` outside the
+    /// grid.
+    pub fn height_at(&self, x: T, z: T) -> Option<T> {
+        let [sx, sy, sz] = self.scale;
+        let (u, v) = (x / sx, z / sz);
+        let width = T::from_f64((self.columns - 1) as f64);
+        let depth = T::from_f64((self.rows - 1) as f64);
+        if !(u >= T::ZERO && u <= width && v >= T::ZERO && v <= depth) {
+            return None;
+        }
+        let column = Self::cell(x, sx, self.columns - 1);
+        let row = Self::cell(z, sz, self.rows - 1);
+        let fx = u - T::from_f64(column as f64);
+        let fz = v - T::from_f64(row as f64);
+        let h =
+            |r: usize, c: usize| self.heights[(row + r) * self.columns + column + c];
+        let (h00, h01, h10, h11) = (h(0, 0), h(0, 1), h(1, 0), h(1, 1));
+        let height = if fz >= fx {
+            h00 + fx * (h11 - h10) + fz * (h10 - h00)
+        } else {
+            h00 + fx * (h01 - h00) + fz * (h11 - h01)
+        };
+        Some(height * sy)
+    }
+
+    /// Whether `point` in local space lies below the surface and above the
+    /// lowest height.
+    pub fn contains_point(&self, point: impl Into<Vec3<T>>) -> bool {
+        let [x, y, z]: [T; 3] = point.into().into();
+        let floor = self.range[0] * self.scale[1];
+        self.height_at(x, z).is_some_and(|h| y <= h && y >= floor)
+    }
+
+    /// Time of impact and up facing normal of the first triangle hit by
+    /// `ray` in local space, from either side. Walks the cells under the ray
+    /// in order, so only the triangles along its path are tested.
+    pub fn cast_ray(&self, ray: Ray<T>) -> Option<(T, [T; 3])> {
+        let bounds = self.local_aabb();
+        let (mut enter, mut exit) = (T::ZERO, T::INFINITY);
+        for i in 0..3 {
+            let (origin, direction) = (ray.origin[i], ray.direction[i]);
+            if direction == T::ZERO {
+                if origin < bounds.min[i] || origin > bounds.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (bounds.min[i] - origin) / direction;
+            let t1 = (bounds.max[i] - origin) / direction;
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+        }
+        if enter > exit {
+            return None;
+        }
+
+        let start: [T; 3] = ray.get_point(enter);
+        let [sx, _, sz] = self.scale;
+        let (columns, rows) = (self.columns - 1, self.rows - 1);
+        let mut column = Self::cell(start[0], sx, columns);
+        let mut row = Self::cell(start[2], sz, rows);
+        // Step direction, time of the next crossing and time between
+        // crossings along x and z
+        let axis = |origin: T, direction: T, spacing: T, cell: usize| {
+            let cell = T::from_f64(cell as f64);
+            if direction > T::ZERO {
+                let next = ((cell + T::ONE) * spacing - origin) / direction;
+                (1, next, spacing / direction)
+            } else if direction < T::ZERO {
+                (
+                    -1,
+                    (cell * spacing - origin) / direction,
+                    -spacing / direction,
+                )
+            } else {
+                (0, T::INFINITY, T::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) =
+            axis(ray.origin[0], ray.direction[0], sx, column);
+        let (step_z, mut next_z, delta_z) =
+            axis(ray.origin[2], ray.direction[2], sz, row);
+        loop {
+            let hit = self
+                .cell_triangles(row, column)
+                .into_iter()
+                .map(|triangle| (ray_triangle_time(ray, triangle), triangle))
+                .filter(|(time, _)| *time >= T::ZERO)
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((time, triangle)) = hit {
+                return Some((time, triangle.normal()));
+            }
+            let (cell, step, cells) = if next_x < next_z {
+                if next_x >= exit {
+                    return None;
+                }
+                next_x += delta_x;
+                (&mut column, step_x, columns)
+            } else {
+                if next_z >= exit {
+                    return None;
+                }
+                next_z += delta_z;
+                (&mut row, step_z, rows)
+            };
+            match cell.checked_add_signed(step) {
+                Some(next) if next < cells => *cell = next,
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contacts::contact;
+    use crate::isometry::Isometry;
+    use crate::math::*;
+    use crate::shapes::{Capsule, Obb, Shape, Sphere};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Gentle hills over a 16 by 12 grid with cells two units wide.
+    fn hills() -> Heightfield {
+        let (rows, columns) = (12, 16);
+        let heights = (0..rows * columns)
+            .map(|i| {
+                let (row, column) = ((i / columns) as f32, (i % columns) as f32);
+                (row * 0.7).sin() + (column * 0.5).cos() * 0.5
+            })
+            .collect();
+        Heightfield::new(rows, columns, heights, [2.0, 1.5, 2.0])
+    }
+
+    #[test]
+    fn sampling() {
+        let field =
+            Heightfield::new(2, 3, vec![0.0, 1.0, 2.0, 1.0, 2.0, 3.0], [1.0; 3]);
+        assert_eq!(field.local_aabb(), Aabb::new([0.0; 3], [2.0, 3.0, 1.0]));
+        assert_eq!(field.point::<[f32; 3]>(1, 2), [2.0, 3.0, 1.0]);
+        assert_eq!(field.height_at(0.0, 0.0), Some(0.0));
+        assert_eq!(field.height_at(2.0, 1.0), Some(3.0));
+        assert_eq!(field.height_at(0.5, 0.5), Some(1.0));
+        assert_eq!(field.height_at(1.5, 0.25), Some(1.75));
+        assert_eq!(field.height_at(2.5, 0.5), None);
+        assert!(field.contains_point([1.0, 1.5, 0.5]));
+        assert!(!field.contains_point([1.0, 2.5, 0.5]));
+        assert!(!field.contains_point([1.0, -0.5, 0.5]));
+
+        let cell = field.cell_triangles(0, 1);
+        for triangle in cell {
+            assert!(triangle.normal::<[f32; 3]>()[1] > 0.0);
+        }
+        let everything = Aabb::new([-1.0; 3], [5.0; 3]);
+        assert_eq!(field.triangles(&everything).count(), 4);
+        let above = Aabb::new([0.0, 2.5, 0.0], [2.0, 4.0, 1.0]);
+        assert_eq!(field.triangles(&above).count(), 2);
+        let outside = Aabb::new([3.0, 0.0, 0.0], [4.0, 1.0, 1.0]);
+        assert_eq!(field.triangles(&outside).count(), 0);
+    }
+
+    #[test]
+    fn ray_casts() {
+        let field = hills();
+        let bounds = field.local_aabb().expanded(4.0);
+        let everything = Aabb::new([-1e3; 3], [1e3; 3]);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin: [f32; 3] = core::array::from_fn(|i| {
+                rng.gen_range(bounds.min[i]..bounds.max[i])
+            });
+            let direction = [
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..0.2),
+                rng.gen_range(-1.0..1.0),
+            ];
+            let ray = Ray::new(origin, direction);
+            let expected = field
+                .triangles(&everything)
+                .map(|triangle| ray_triangle_time(ray, triangle))
+                .filter(|time| *time >= 0.0)
+                .min_by(|a, b| a.total_cmp(b));
+            let hit = field.cast_ray(ray);
+            assert_eq!(hit.is_some(), expected.is_some(), "{:?}", ray);
+            if let (Some((time, normal)), Some(expected)) = (hit, expected) {
+                assert!((time - expected).abs() < 1e-4, "{} != {}", time, expected);
+                assert!(normal[1] > 0.0);
+                hits += 1;
+            }
+        }
+        assert!(hits > 100, "{}", hits);
+
+        // Straight down lands on the surface from anywhere over the grid
+        let ray = Ray::new([7.0, 10.0, 9.0], [0.0, -1.0, 0.0]);
+        let (time, _) = field.cast_ray(ray).unwrap();
+        let height = field.height_at(7.0, 9.0).unwrap();
+        assert!((10.0 - time - height).abs() < 1e-5);
+        let past = Ray::new([40.0, 10.0, 9.0], [0.0, -1.0, 0.0]);
+        assert!(field.cast_ray(past).is_none());
+    }
+
+    #[test]
+    fn convex_contacts() {
+        let field = hills();
+        let terrain = Shape::Heightfield(field.clone());
+        let placement = Isometry::from_axis_angle([0.0, 1.0, 0.0], 0.3)
+            .with_translation([-10.0, 2.0, 5.0]);
+        let (x, z) = (13.0, 9.0);
+        let surface: [f32; 3] =
+            placement.transform_point([x, field.height_at(x, z).unwrap(), z]);
+        let up: [f32; 3] = placement.transform_vector([0.0, 1.0, 0.0]);
+        let at = |height: f32| {
+            Isometry::from_translation(add(surface, scale(up, height)))
+        };
+
+        let ball = Shape::Sphere(Sphere::new([0.0; 3], 0.5));
+        let manifold = contact(&terrain, &placement, &ball, &at(0.4)).unwrap();
+        assert!(manifold.normal[1] > 0.8);
+        assert!(manifold.max_depth() > 0.0 && manifold.max_depth() < 0.2);
+        assert!(contact(&terrain, &placement, &ball, &at(0.8)).is_none());
+        let flipped = contact(&ball, &at(0.4), &terrain, &placement).unwrap();
+        assert!(flipped.normal[1] < -0.8);
+        // Below the surface is solid, so a buried ball is pushed back up
+        let buried = contact(&terrain, &placement, &ball, &at(-1.0)).unwrap();
+        assert!(buried.normal[1] > 0.8 && buried.max_depth() > 1.0);
+
+        let cube = Shape::Obb(Obb::new([0.0; 3], [0.5; 3], identity()));
+        let manifold = contact(&terrain, &placement, &cube, &at(0.45)).unwrap();
+        assert!(manifold.normal[1] > 0.8);
+        assert!(manifold.points.len() >= 2);
+        assert!(contact(&terrain, &placement, &cube, &at(1.5)).is_none());
+
+        let lying =
+            Shape::Capsule(Capsule::new([-1.0, 0.0, 0.0], [1.0, 0.0, 0.0], 0.3));
+        let manifold = contact(&terrain, &placement, &lying, &at(0.2)).unwrap();
+        assert!(manifold.normal[1] > 0.8);
+        assert!(contact(&terrain, &placement, &lying, &at(3.0)).is_none());
+
+        for (shape, touching) in [(&ball, 0.4), (&cube, 0.45), (&lying, 0.2)] {
+            assert!(terrain.intersects(&placement, shape, &at(touching)));
+            assert!(shape.intersects(&at(-1.0), &terrain, &placement));
+            assert!(!terrain.intersects(&placement, shape, &at(3.0)));
+        }
+        assert!(terrain.contains_point(&placement, add(surface, scale(up, -0.1))));
+        assert!(!terrain.contains_point(&placement, add(surface, scale(up, 0.1))));
+    }
+}
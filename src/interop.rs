@@ -0,0 +1,148 @@
+//! Conversions to transform types that mint has no equivalent for. Vectors,
+//! quaternions and matrices go through the mint conversions on the shapes
+//! and `Isometry` instead.
+
+#[cfg(feature = "glam")]
+mod glam_impls {
+    use crate::isometry::Isometry;
+
+    impl From<Isometry<f32>> for glam::Affine3A {
+        fn from(iso: Isometry<f32>) -> Self {
+            let (translation, rotation): (glam::Vec3, glam::Quat) = iso.into();
+            glam::Affine3A::from_rotation_translation(rotation, translation)
+        }
+    }
+
+    impl From<Isometry<f64>> for glam::DAffine3 {
+        fn from(iso: Isometry<f64>) -> Self {
+            let (translation, rotation): (glam::DVec3, glam::DQuat) = iso.into();
+            glam::DAffine3::from_rotation_translation(rotation, translation)
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impls {
+    use crate::isometry::Isometry;
+    use crate::real::Real;
+    use nalgebra::{Isometry3, Quaternion, RealField, Translation3, UnitQuaternion};
+
+    impl<T: Real + RealField> From<Isometry<T>> for Isometry3<T> {
+        fn from(iso: Isometry<T>) -> Self {
+            let [x, y, z] = iso.translation;
+            let [i, j, k, w] = iso.rotation;
+            Isometry3::from_parts(
+                Translation3::new(x, y, z),
+                UnitQuaternion::new_unchecked(Quaternion::new(w, i, j, k)),
+            )
+        }
+    }
+
+    impl<T: Real + RealField> From<Isometry3<T>> for Isometry<T> {
+        fn from(iso: Isometry3<T>) -> Self {
+            let translation = iso.translation.vector;
+            let rotation = iso.rotation.into_inner().coords;
+            Isometry {
+                translation: [translation.x, translation.y, translation.z],
+                rotation: [rotation.x, rotation.y, rotation.z, rotation.w],
+            }
+        }
+    }
+}
+
+#[cfg(feature = "cgmath")]
+mod cgmath_impls {
+    use crate::isometry::Isometry;
+    use crate::real::Real;
+    use cgmath::{BaseFloat, Decomposed, Quaternion, Vector3};
+
+    impl<T: Real + BaseFloat> From<Isometry<T>>
+        for Decomposed<Vector3<T>, Quaternion<T>>
+    {
+        fn from(iso: Isometry<T>) -> Self {
+            let (disp, rot) = iso.into();
+            Decomposed {
+                scale: T::ONE,
+                rot,
+                disp,
+            }
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "glam", feature = "nalgebra", feature = "cgmath")))]
+mod tests {
+    use crate::isometry::Isometry;
+
+    fn iso() -> Isometry {
+        Isometry::from_axis_angle([0.3, -1.0, 0.5], 1.2)
+            .with_translation([1.0, 2.0, -3.0])
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn glam() {
+        use crate::shapes::{Aabb, Obb};
+        let affine = glam::Affine3A::from(iso());
+        let p = glam::Vec3::new(0.5, 1.5, -2.0);
+        assert_close(affine.transform_point3(p).into(), iso().transform_point(p));
+
+        let aabb = Aabb::from((glam::Vec3::ZERO, glam::Vec3::ONE));
+        let center = glam::Vec3::from(aabb.center());
+        assert_eq!(center, glam::Vec3::splat(0.5));
+        let (min, max): (glam::Vec3, glam::Vec3) = aabb.into();
+        assert_eq!((min, max), (glam::Vec3::ZERO, glam::Vec3::ONE));
+
+        let rotation = glam::Quat::from_rotation_y(0.7);
+        let obb = Obb::from((glam::Vec3::X, glam::Vec3::ONE, rotation));
+        let (_, _, back): (glam::Vec3, glam::Vec3, glam::Quat) = obb.into();
+        assert!(back.angle_between(rotation) < 1e-5);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra() {
+        use crate::ray::Ray;
+        let na_iso = nalgebra::Isometry3::from(iso());
+        let p = nalgebra::Point3::new(0.5, 1.5, -2.0);
+        let moved = na_iso.transform_point(&p);
+        assert_close(moved.coords.into(), iso().transform_point(p.coords));
+        assert_eq!(Isometry::from(na_iso), iso());
+
+        let ray = Ray::from((nalgebra::Vector3::zeros(), nalgebra::Vector3::x()));
+        let point: nalgebra::Vector3<f32> = ray.get_point(2.0);
+        assert_eq!(point, nalgebra::Vector3::new(2.0, 0.0, 0.0));
+        let matrix: nalgebra::Matrix4<f32> = iso().to_matrix();
+        assert_close(
+            matrix.transform_point(&p).coords.into(),
+            iso().transform_point(p.coords),
+        );
+    }
+
+    #[cfg(feature = "cgmath")]
+    #[test]
+    fn cgmath() {
+        use crate::shapes::Sphere;
+        use cgmath::Transform;
+        let decomposed = cgmath::Decomposed::<
+            cgmath::Vector3<f32>,
+            cgmath::Quaternion<f32>,
+        >::from(iso());
+        let p = cgmath::Point3::new(0.5, 1.5, -2.0);
+        let moved: [f32; 3] = decomposed.transform_point(p).into();
+        assert_close(moved, iso().transform_point([0.5, 1.5, -2.0]));
+
+        let sphere = Sphere::from((cgmath::Vector3::new(1.0, 2.0, 3.0), 0.5));
+        let (center, radius): (cgmath::Vector3<f32>, f32) = sphere.into();
+        assert_eq!((center, radius), (cgmath::Vector3::new(1.0, 2.0, 3.0), 0.5));
+    }
+}
@@ -1,89 +1,669 @@
+use crate::isometry::Isometry;
+use crate::math::*;
 use crate::ray::Ray;
+use crate::real::Real;
 use crate::shapes::*;
 use crate::Vec3;
-use float_ord::FloatOrd;
 
-pub fn aabb_aabb(a: Aabb, b: Aabb) -> bool {
-    a.min.zip(b.max).iter().all(|(a, b)| a <= b)
-        && b.min.zip(a.max).iter().all(|(a, b)| a <= b)
+pub fn aabb_aabb<T: Real>(a: Aabb<T>, b: Aabb<T>) -> bool {
+    a.min.iter().zip(b.max).all(|(a, b)| *a <= b)
+        && b.min.iter().zip(a.max).all(|(a, b)| *a <= b)
 }
 
-pub fn aabb_point(aabb: Aabb, point: impl Into<Vec3>) -> bool {
-    let point: [f32; 3] = point.into().into();
-    aabb.min.zip(point).iter().all(|(a, b)| a <= b)
-        && aabb.max.zip(point).iter().all(|(a, b)| a >= b)
+pub fn aabb_point<T: Real>(aabb: Aabb<T>, point: impl Into<Vec3<T>>) -> bool {
+    let point: [T; 3] = point.into().into();
+    aabb.min.iter().zip(point).all(|(a, b)| *a <= b)
+        && aabb.max.iter().zip(point).all(|(a, b)| *a >= b)
 }
 
-pub fn aabb_sphere(aabb: Aabb, sphere: Sphere) -> bool {
-    let sphere_center = sphere.center.zip(aabb.min).map(|(s_c, a_min)| s_c - a_min);
-    let sphere_radius = sphere.radius;
-    sphere_center
-        .zip(aabb.max.zip(aabb.min))
+pub fn aabb_sphere<T: Real>(aabb: Aabb<T>, sphere: Sphere<T>) -> bool {
+    let closest = max(sphere.center, aabb.min);
+    let closest = min(closest, aabb.max);
+    distance_squared(closest, sphere.center) <= sphere.radius * sphere.radius
+}
+
+pub fn obb_point<T: Real>(obb: Obb<T>, point: impl Into<Vec3<T>>) -> bool {
+    let offset = sub(point.into().into(), obb.center);
+    obb.axes
+        .iter()
+        .zip(obb.half_extents)
+        .all(|(axis, h)| dot(offset, *axis).abs() <= h)
+}
+
+pub fn obb_sphere<T: Real>(obb: Obb<T>, sphere: Sphere<T>) -> bool {
+    let offset = sub(sphere.center, obb.center);
+    let distance_squared: T = obb
+        .axes
         .iter()
-        .all(|(s_c, (a_max, a_min))| s_c.abs() <= a_max - a_min + sphere_radius)
+        .zip(obb.half_extents)
+        .map(|(axis, h)| {
+            let d = dot(offset, *axis);
+            let outside = d - d.clamp(-h, h);
+            outside * outside
+        })
+        .sum();
+    distance_squared <= sphere.radius * sphere.radius
+}
+
+pub fn obb_obb<T: Real>(a: Obb<T>, b: Obb<T>) -> bool {
+    // Separating axis test, axes of `b` expressed in `a`'s frame
+    let epsilon = T::from_f64(1e-6);
+    let mut r = [[T::ZERO; 3]; 3];
+    let mut abs_r = [[T::ZERO; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            r[i][j] = dot(a.axes[i], b.axes[j]);
+            abs_r[i][j] = r[i][j].abs() + epsilon;
+        }
+    }
+    let offset = sub(b.center, a.center);
+    let t = [
+        dot(offset, a.axes[0]),
+        dot(offset, a.axes[1]),
+        dot(offset, a.axes[2]),
+    ];
+    let (ea, eb) = (a.half_extents, b.half_extents);
+
+    for i in 0..3 {
+        let ra = ea[i];
+        let rb = eb[0] * abs_r[i][0] + eb[1] * abs_r[i][1] + eb[2] * abs_r[i][2];
+        if t[i].abs() > ra + rb {
+            return false;
+        }
+    }
+    for j in 0..3 {
+        let ra = ea[0] * abs_r[0][j] + ea[1] * abs_r[1][j] + ea[2] * abs_r[2][j];
+        let rb = eb[j];
+        let t_j = t[0] * r[0][j] + t[1] * r[1][j] + t[2] * r[2][j];
+        if t_j.abs() > ra + rb {
+            return false;
+        }
+    }
+    for i in 0..3 {
+        let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+        for j in 0..3 {
+            let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+            let ra = ea[i1] * abs_r[i2][j] + ea[i2] * abs_r[i1][j];
+            let rb = eb[j1] * abs_r[i][j2] + eb[j2] * abs_r[i][j1];
+            let t_ij = t[i2] * r[i1][j] - t[i1] * r[i2][j];
+            if t_ij.abs() > ra + rb {
+                return false;
+            }
+        }
+    }
+    true
 }
 
-pub fn sphere_sphere(a: Sphere, b: Sphere) -> bool {
+pub fn sphere_sphere<T: Real>(a: Sphere<T>, b: Sphere<T>) -> bool {
     let distance_squared = distance_squared(a.center, b.center);
     let total_radius = a.radius + b.radius;
     distance_squared <= total_radius * total_radius
 }
 
-pub fn sphere_point(sphere: Sphere, point: impl Into<Vec3>) -> bool {
-    let point: [f32; 3] = point.into().into();
+pub fn sphere_point<T: Real>(sphere: Sphere<T>, point: impl Into<Vec3<T>>) -> bool {
+    let point: [T; 3] = point.into().into();
     let distance_squared = distance_squared(sphere.center, point);
     distance_squared <= sphere.radius * sphere.radius
 }
 
-pub fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
-    a.zip(b).iter().map(|(a, b)| (a - b).powi(2)).sum()
+pub fn distance_squared<T: Real>(a: [T; 3], b: [T; 3]) -> T {
+    let d = sub(a, b);
+    dot(d, d)
 }
 
-pub fn ray_aabb_time(ray: Ray, aabb: Aabb) -> f32 {
-    let t1 = aabb.min.zip(ray.origin.zip(ray.direction)).map(
-        |(aabb_min, (ray_origin, ray_direction))| {
-            (aabb_min - ray_origin) / ray_direction
-        },
-    );
-    let t2 = aabb.max.zip(ray.origin.zip(ray.direction)).map(
-        |(aabb_max, (ray_origin, ray_direction))| {
-            (aabb_max - ray_origin) / ray_direction
-        },
-    );
+pub fn ray_aabb_time<T: Real>(ray: Ray<T>, aabb: Aabb<T>) -> T {
+    let t1: [T; 3] =
+        core::array::from_fn(|i| (aabb.min[i] - ray.origin[i]) / ray.direction[i]);
+    let t2: [T; 3] =
+        core::array::from_fn(|i| (aabb.max[i] - ray.origin[i]) / ray.direction[i]);
     let t_min = t1
-        .zip(t2)
         .iter()
-        .map(|(t1, t2)| FloatOrd(t1.min(*t2)))
-        .max()
-        .map(|f| f.0)
-        .unwrap_or(0.0);
-    let t_max = t1
         .zip(t2)
+        .map(|(t1, t2)| t1.min(t2))
+        .max_by(|a, b| a.total_cmp(b))
+        .unwrap_or(T::ZERO);
+    let t_max = t1
         .iter()
-        .map(|(t1, t2)| FloatOrd(t1.max(*t2)))
-        .min()
-        .map(|f| f.0)
-        .unwrap_or(0.0);
+        .zip(t2)
+        .map(|(t1, t2)| t1.max(t2))
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(T::ZERO);
     if t_min <= t_max {
-        if t_min > 0.0 {
+        if t_min > T::ZERO {
             t_min
-        } else if t_max > 0.0 {
+        } else if t_max > T::ZERO {
             t_max
         } else {
-            -1.0
+            -T::ONE
         }
     } else {
-        -1.0
+        -T::ONE
+    }
+}
+
+pub fn ray_aabb<T: Real>(ray: Ray<T>, aabb: Aabb<T>) -> bool {
+    ray_aabb_time(ray, aabb) >= T::ZERO
+}
+
+pub fn ray_obb_time<T: Real>(ray: Ray<T>, obb: Obb<T>) -> T {
+    let offset = sub(ray.origin, obb.center);
+    let local_ray = Ray {
+        origin: obb.axes.map(|axis| dot(offset, axis)),
+        direction: obb.axes.map(|axis| dot(ray.direction, axis)),
+    };
+    let local_aabb = Aabb {
+        min: obb.half_extents.map(|h| -h),
+        max: obb.half_extents,
+    };
+    ray_aabb_time(local_ray, local_aabb)
+}
+
+pub fn ray_obb<T: Real>(ray: Ray<T>, obb: Obb<T>) -> bool {
+    ray_obb_time(ray, obb) >= T::ZERO
+}
+
+pub fn ray_sphere_time<T: Real>(ray: Ray<T>, sphere: Sphere<T>) -> T {
+    match ray_sphere_interval(ray, sphere) {
+        Some((t_min, t_max)) => first_time(t_min, t_max),
+        None => -T::ONE,
+    }
+}
+
+/// Where the line through `ray` enters and leaves `sphere`.
+fn ray_sphere_interval<T: Real>(ray: Ray<T>, sphere: Sphere<T>) -> Option<(T, T)> {
+    let offset = sub(ray.origin, sphere.center);
+    let a = dot(ray.direction, ray.direction);
+    let b = dot(offset, ray.direction);
+    let c = dot(offset, offset) - sphere.radius * sphere.radius;
+    let discriminant = b * b - a * c;
+    if a <= T::ZERO || discriminant < T::ZERO {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    Some(((-b - root) / a, (-b + root) / a))
+}
+
+/// The entry time if it's ahead of the origin, otherwise the exit time.
+fn first_time<T: Real>(t_min: T, t_max: T) -> T {
+    if t_min > T::ZERO {
+        t_min
+    } else if t_max > T::ZERO {
+        t_max
+    } else {
+        -T::ONE
+    }
+}
+
+pub fn ray_sphere<T: Real>(ray: Ray<T>, sphere: Sphere<T>) -> bool {
+    ray_sphere_time(ray, sphere) >= T::ZERO
+}
+
+pub fn ray_capsule_time<T: Real>(ray: Ray<T>, capsule: Capsule<T>) -> T {
+    // The capsule is the union of its end spheres and the cylinder between
+    // them, which overlap, so the line is inside it over the union of their
+    // intervals
+    let axis = sub(capsule.b, capsule.a);
+    let length_squared = dot(axis, axis);
+    let offset = sub(ray.origin, capsule.a);
+    let (d_axis, o_axis) = (dot(ray.direction, axis), dot(offset, axis));
+    let a = length_squared * dot(ray.direction, ray.direction) - d_axis * d_axis;
+    let b = length_squared * dot(offset, ray.direction) - o_axis * d_axis;
+    let c = length_squared * (dot(offset, offset) - capsule.radius * capsule.radius)
+        - o_axis * o_axis;
+    let cylinder = if length_squared <= T::ZERO || a <= T::ZERO {
+        None
+    } else {
+        let discriminant = b * b - a * c;
+        (discriminant >= T::ZERO).then(|| {
+            let root = discriminant.sqrt();
+            let (t0, t1) = ((-b - root) / a, (-b + root) / a);
+            // Clip to the slab between the end caps
+            let (s0, s1) = if d_axis == T::ZERO {
+                if o_axis < T::ZERO || o_axis > length_squared {
+                    (T::INFINITY, -T::INFINITY)
+                } else {
+                    (-T::INFINITY, T::INFINITY)
+                }
+            } else {
+                let s0 = -o_axis / d_axis;
+                let s1 = (length_squared - o_axis) / d_axis;
+                (s0.min(s1), s0.max(s1))
+            };
+            (t0.max(s0), t1.min(s1))
+        })
+    };
+    let interval = [
+        ray_sphere_interval(ray, Sphere::new(capsule.a, capsule.radius)),
+        ray_sphere_interval(ray, Sphere::new(capsule.b, capsule.radius)),
+        cylinder.filter(|(t0, t1)| t0 <= t1),
+    ]
+    .into_iter()
+    .flatten()
+    .reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)));
+    match interval {
+        Some((t_min, t_max)) => first_time(t_min, t_max),
+        None => -T::ONE,
+    }
+}
+
+pub fn ray_capsule<T: Real>(ray: Ray<T>, capsule: Capsule<T>) -> bool {
+    ray_capsule_time(ray, capsule) >= T::ZERO
+}
+
+pub fn capsule_point<T: Real>(
+    capsule: Capsule<T>,
+    point: impl Into<Vec3<T>>,
+) -> bool {
+    let point: [T; 3] = point.into().into();
+    let closest = closest_point_on_segment(capsule.a, capsule.b, point);
+    distance_squared(closest, point) <= capsule.radius * capsule.radius
+}
+
+pub fn capsule_sphere<T: Real>(capsule: Capsule<T>, sphere: Sphere<T>) -> bool {
+    let closest = closest_point_on_segment(capsule.a, capsule.b, sphere.center);
+    sphere_sphere(Sphere::new(closest, capsule.radius), sphere)
+}
+
+pub fn capsule_capsule<T: Real>(a: Capsule<T>, b: Capsule<T>) -> bool {
+    let (p, q) = closest_points_segments((a.a, a.b), (b.a, b.b));
+    let total_radius = a.radius + b.radius;
+    distance_squared(p, q) <= total_radius * total_radius
+}
+
+pub fn capsule_obb<T: Real>(capsule: Capsule<T>, obb: Obb<T>) -> bool {
+    let (p, q) = closest_points_segment_obb((capsule.a, capsule.b), obb);
+    distance_squared(p, q) <= capsule.radius * capsule.radius
+}
+
+pub fn triangle_sphere<T: Real>(triangle: Triangle<T>, sphere: Sphere<T>) -> bool {
+    let closest = closest_point_on_triangle(triangle, sphere.center);
+    distance_squared(closest, sphere.center) <= sphere.radius * sphere.radius
+}
+
+pub fn triangle_capsule<T: Real>(
+    triangle: Triangle<T>,
+    capsule: Capsule<T>,
+) -> bool {
+    let (p, q) = closest_points_segment_triangle((capsule.a, capsule.b), triangle);
+    distance_squared(p, q) <= capsule.radius * capsule.radius
+}
+
+pub fn triangle_obb<T: Real>(triangle: Triangle<T>, obb: Obb<T>) -> bool {
+    // Separating axis test over the box's faces, the triangle's face and the
+    // crossings of their edges
+    let vertices = triangle.vertices();
+    let edges = [0, 1, 2].map(|i| sub(vertices[(i + 1) % 3], vertices[i]));
+    let normal = cross(edges[0], edges[1]);
+    let crossings = obb
+        .axes
+        .into_iter()
+        .flat_map(|axis| edges.map(|edge| cross(axis, edge)));
+    obb.axes
+        .into_iter()
+        .chain([normal])
+        .chain(crossings)
+        .all(|axis| {
+            let (low, high) = vertices
+                .iter()
+                .map(|v| dot(sub(*v, obb.center), axis))
+                .fold((T::INFINITY, -T::INFINITY), |(low, high), d| {
+                    (low.min(d), high.max(d))
+                });
+            let radius = (0..3)
+                .map(|k| obb.half_extents[k] * dot(obb.axes[k], axis).abs())
+                .sum::<T>();
+            low <= radius && high >= -radius
+        })
+}
+
+pub fn triangle_triangle<T: Real>(a: Triangle<T>, b: Triangle<T>) -> bool {
+    // Separating axis test over both faces, the crossings of their edges and
+    // the in-plane edge normals, which separate coplanar triangles
+    let (a, b) = (a.vertices(), b.vertices());
+    let edges = |v: [[T; 3]; 3]| [0, 1, 2].map(|i| sub(v[(i + 1) % 3], v[i]));
+    let (a_edges, b_edges) = (edges(a), edges(b));
+    let a_normal = cross(a_edges[0], a_edges[1]);
+    let b_normal = cross(b_edges[0], b_edges[1]);
+    let crossings = a_edges
+        .into_iter()
+        .flat_map(|edge| b_edges.map(|other| cross(edge, other)));
+    let in_plane = a_edges
+        .map(|edge| cross(a_normal, edge))
+        .into_iter()
+        .chain(b_edges.map(|edge| cross(b_normal, edge)));
+    let range = |vertices: &[[T; 3]; 3], axis: [T; 3]| {
+        vertices
+            .iter()
+            .map(|v| dot(*v, axis))
+            .fold((T::INFINITY, -T::INFINITY), |(low, high), d| {
+                (low.min(d), high.max(d))
+            })
+    };
+    [a_normal, b_normal]
+        .into_iter()
+        .chain(crossings)
+        .chain(in_plane)
+        .all(|axis| {
+            let (a_low, a_high) = range(&a, axis);
+            let (b_low, b_high) = range(&b, axis);
+            a_low <= b_high && b_low <= a_high
+        })
+}
+
+/// The time at which `ray` crosses `triangle` from either side, using the
+/// Möller-Trumbore test, or -1 if it doesn't.
+pub fn ray_triangle_time<T: Real>(ray: Ray<T>, triangle: Triangle<T>) -> T {
+    let (ab, ac) = (sub(triangle.b, triangle.a), sub(triangle.c, triangle.a));
+    let p = cross(ray.direction, ac);
+    let determinant = dot(ab, p);
+    if determinant == T::ZERO {
+        return -T::ONE;
+    }
+    let inverse = T::ONE / determinant;
+    let offset = sub(ray.origin, triangle.a);
+    let u = dot(offset, p) * inverse;
+    if u < T::ZERO || u > T::ONE {
+        return -T::ONE;
+    }
+    let q = cross(offset, ab);
+    let v = dot(ray.direction, q) * inverse;
+    if v < T::ZERO || u + v > T::ONE {
+        return -T::ONE;
+    }
+    let t = dot(ac, q) * inverse;
+    if t < T::ZERO {
+        -T::ONE
+    } else {
+        t
+    }
+}
+
+pub fn ray_triangle<T: Real>(ray: Ray<T>, triangle: Triangle<T>) -> bool {
+    ray_triangle_time(ray, triangle) >= T::ZERO
+}
+
+pub fn closest_point_on_segment<T: Real>(
+    a: [T; 3],
+    b: [T; 3],
+    point: [T; 3],
+) -> [T; 3] {
+    let ab = sub(b, a);
+    let length_squared = dot(ab, ab);
+    if length_squared <= T::ZERO {
+        return a;
     }
+    let t = (dot(sub(point, a), ab) / length_squared).clamp(T::ZERO, T::ONE);
+    add(a, scale(ab, t))
 }
 
-pub fn ray_aabb(ray: Ray, aabb: Aabb) -> bool {
-    ray_aabb_time(ray, aabb) >= 0.0
+/// Closest points between segments `a` and `b`, from Real-Time Collision
+/// Detection 5.1.9.
+pub fn closest_points_segments<T: Real>(
+    (p1, q1): ([T; 3], [T; 3]),
+    (p2, q2): ([T; 3], [T; 3]),
+) -> ([T; 3], [T; 3]) {
+    let (d1, d2, r) = (sub(q1, p1), sub(q2, p2), sub(p1, p2));
+    let (a, e, f) = (dot(d1, d1), dot(d2, d2), dot(d2, r));
+    let epsilon = T::EPSILON;
+    let unit = |v: T| v.clamp(T::ZERO, T::ONE);
+    let (s, t) = if a <= epsilon && e <= epsilon {
+        (T::ZERO, T::ZERO)
+    } else if a <= epsilon {
+        (T::ZERO, unit(f / e))
+    } else {
+        let c = dot(d1, r);
+        if e <= epsilon {
+            (unit(-c / a), T::ZERO)
+        } else {
+            let b = dot(d1, d2);
+            let denominator = a * e - b * b;
+            let s = if denominator > T::ZERO {
+                unit((b * f - c * e) / denominator)
+            } else {
+                T::ZERO
+            };
+            let t = (b * s + f) / e;
+            if t < T::ZERO {
+                (unit(-c / a), T::ZERO)
+            } else if t > T::ONE {
+                (unit((b - c) / a), T::ONE)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    (add(p1, scale(d1, s)), add(p2, scale(d2, t)))
+}
+
+/// Closest point on `triangle` to `point`, from Real-Time Collision
+/// Detection 5.1.5.
+pub fn closest_point_on_triangle<T: Real>(
+    triangle: Triangle<T>,
+    point: [T; 3],
+) -> [T; 3] {
+    let Triangle { a, b, c } = triangle;
+    let (ab, ac) = (sub(b, a), sub(c, a));
+    let ap = sub(point, a);
+    let (d1, d2) = (dot(ab, ap), dot(ac, ap));
+    if d1 <= T::ZERO && d2 <= T::ZERO {
+        return a;
+    }
+    let bp = sub(point, b);
+    let (d3, d4) = (dot(ab, bp), dot(ac, bp));
+    if d3 >= T::ZERO && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= T::ZERO && d1 >= T::ZERO && d3 <= T::ZERO {
+        return add(a, scale(ab, d1 / (d1 - d3)));
+    }
+    let cp = sub(point, c);
+    let (d5, d6) = (dot(ab, cp), dot(ac, cp));
+    if d6 >= T::ZERO && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= T::ZERO && d2 >= T::ZERO && d6 <= T::ZERO {
+        return add(a, scale(ac, d2 / (d2 - d6)));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= T::ZERO && d4 - d3 >= T::ZERO && d5 - d6 >= T::ZERO {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return add(b, scale(sub(c, b), w));
+    }
+    let denominator = T::ONE / (va + vb + vc);
+    add(
+        a,
+        add(scale(ab, vb * denominator), scale(ac, vc * denominator)),
+    )
+}
+
+/// Closest points between a segment and a triangle: where the segment
+/// passes through it, or else the nearest of its ends and of the pairs of
+/// closest points with the triangle's edges.
+pub fn closest_points_segment_triangle<T: Real>(
+    (s, e): ([T; 3], [T; 3]),
+    triangle: Triangle<T>,
+) -> ([T; 3], [T; 3]) {
+    let vertices = triangle.vertices();
+    let normal = cross(sub(triangle.b, triangle.a), sub(triangle.c, triangle.a));
+    let (ds, de) = (
+        dot(sub(s, triangle.a), normal),
+        dot(sub(e, triangle.a), normal),
+    );
+    if (ds <= T::ZERO) != (de <= T::ZERO) {
+        let p = add(s, scale(sub(e, s), ds / (ds - de)));
+        let inside = (0..3).all(|i| {
+            let (v0, v1) = (vertices[i], vertices[(i + 1) % 3]);
+            dot(cross(sub(v1, v0), sub(p, v0)), normal) >= T::ZERO
+        });
+        if inside {
+            return (p, p);
+        }
+    }
+    let ends = [s, e].map(|p| (p, closest_point_on_triangle(triangle, p)));
+    let edges = [0, 1, 2].map(|i| {
+        closest_points_segments((s, e), (vertices[i], vertices[(i + 1) % 3]))
+    });
+    ends.into_iter()
+        .chain(edges)
+        .min_by(|(p1, q1), (p2, q2)| {
+            distance_squared(*p1, *q1).total_cmp(&distance_squared(*p2, *q2))
+        })
+        .unwrap()
+}
+
+pub fn closest_point_on_obb<T: Real>(obb: Obb<T>, point: [T; 3]) -> [T; 3] {
+    let offset = sub(point, obb.center);
+    obb.axes
+        .iter()
+        .zip(obb.half_extents)
+        .fold(obb.center, |closest, (axis, h)| {
+            add(closest, scale(*axis, dot(offset, *axis).clamp(-h, h)))
+        })
+}
+
+/// Closest points between a segment and an obb. Along the segment the
+/// squared distance is a quadratic between the points where it crosses the
+/// box's slabs, so each piece is minimised exactly. When the segment passes
+/// through the box this picks the first point inside.
+pub fn closest_points_segment_obb<T: Real>(
+    (a, b): ([T; 3], [T; 3]),
+    obb: Obb<T>,
+) -> ([T; 3], [T; 3]) {
+    let ab = sub(b, a);
+    let offset = sub(a, obb.center);
+    let start = obb.axes.map(|axis| dot(offset, axis));
+    let direction = obb.axes.map(|axis| dot(ab, axis));
+    // At most two crossings per axis, plus the ends
+    let mut breaks = [T::ZERO; 8];
+    let mut count = 1;
+    for i in 0..3 {
+        if direction[i] == T::ZERO {
+            continue;
+        }
+        for side in [-T::ONE, T::ONE] {
+            let t = (obb.half_extents[i] * side - start[i]) / direction[i];
+            if t > T::ZERO && t < T::ONE {
+                breaks[count] = t;
+                count += 1;
+            }
+        }
+    }
+    breaks[count] = T::ONE;
+    let breaks = &mut breaks[..=count];
+    breaks.sort_unstable_by(|a, b| a.total_cmp(b));
+
+    let at = |t: T| add(a, scale(ab, t));
+    let distance = |t: T| {
+        let p = at(t);
+        distance_squared(p, closest_point_on_obb(obb, p))
+    };
+    let mut best = (distance(T::ZERO), T::ZERO);
+    for piece in breaks.windows(2) {
+        let (low, high) = (piece[0], piece[1]);
+        let middle = (low + high) * T::HALF;
+        // Coefficients of the squared distance as `t^2 * qa + t * qb + ...`
+        let (mut qa, mut qb) = (T::ZERO, T::ZERO);
+        for i in 0..3 {
+            let u = start[i] + direction[i] * middle;
+            let h = obb.half_extents[i];
+            let gap = if u > h {
+                start[i] - h
+            } else if u < -h {
+                start[i] + h
+            } else {
+                continue;
+            };
+            qa += direction[i] * direction[i];
+            qb += T::TWO * gap * direction[i];
+        }
+        let t = if qa > T::ZERO {
+            (-qb / (T::TWO * qa)).clamp(low, high)
+        } else {
+            low
+        };
+        let candidate = distance(t);
+        if candidate < best.0 {
+            best = (candidate, t);
+        }
+    }
+    let p = at(best.1);
+    (p, closest_point_on_obb(obb, p))
+}
+
+pub fn aabb_aabb_iso<T: Real>(
+    (a, a_iso): (Aabb<T>, &Isometry<T>),
+    (b, b_iso): (Aabb<T>, &Isometry<T>),
+) -> bool {
+    obb_obb(
+        Obb::from(a).transformed(a_iso),
+        Obb::from(b).transformed(b_iso),
+    )
+}
+
+pub fn aabb_point_iso<T: Real>(
+    (aabb, iso): (Aabb<T>, &Isometry<T>),
+    point: impl Into<Vec3<T>>,
+) -> bool {
+    aabb_point(aabb, iso.inverse_transform_point::<[T; 3]>(point))
+}
+
+pub fn aabb_sphere_iso<T: Real>(
+    (aabb, aabb_iso): (Aabb<T>, &Isometry<T>),
+    (sphere, sphere_iso): (Sphere<T>, &Isometry<T>),
+) -> bool {
+    let center: [T; 3] = sphere_iso.transform_point(sphere.center);
+    let sphere = Sphere {
+        center: aabb_iso.inverse_transform_point(center),
+        radius: sphere.radius,
+    };
+    aabb_sphere(aabb, sphere)
+}
+
+pub fn obb_obb_iso<T: Real>(
+    (a, a_iso): (Obb<T>, &Isometry<T>),
+    (b, b_iso): (Obb<T>, &Isometry<T>),
+) -> bool {
+    obb_obb(a.transformed(a_iso), b.transformed(b_iso))
+}
+
+pub fn obb_sphere_iso<T: Real>(
+    (obb, obb_iso): (Obb<T>, &Isometry<T>),
+    (sphere, sphere_iso): (Sphere<T>, &Isometry<T>),
+) -> bool {
+    obb_sphere(obb.transformed(obb_iso), sphere.transformed(sphere_iso))
+}
+
+pub fn sphere_sphere_iso<T: Real>(
+    (a, a_iso): (Sphere<T>, &Isometry<T>),
+    (b, b_iso): (Sphere<T>, &Isometry<T>),
+) -> bool {
+    sphere_sphere(a.transformed(a_iso), b.transformed(b_iso))
+}
+
+pub fn sphere_point_iso<T: Real>(
+    (sphere, iso): (Sphere<T>, &Isometry<T>),
+    point: impl Into<Vec3<T>>,
+) -> bool {
+    sphere_point(sphere.transformed(iso), point)
+}
+
+pub fn ray_aabb_time_iso<T: Real>(
+    ray: Ray<T>,
+    (aabb, iso): (Aabb<T>, &Isometry<T>),
+) -> T {
+    ray_aabb_time(ray.inverse_transformed(iso), aabb)
+}
+
+pub fn ray_aabb_iso<T: Real>(ray: Ray<T>, aabb: (Aabb<T>, &Isometry<T>)) -> bool {
+    ray_aabb_time_iso(ray, aabb) >= T::ZERO
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::Rng;
 
     #[test]
     fn aabb_aabb_intersections() {
@@ -108,6 +688,81 @@ mod tests {
         assert!(aabb_sphere(aabb1, sphere1));
         let sphere2 = Sphere::new([1.6, 1.5, 1.5], 0.5);
         assert!(!aabb_sphere(aabb1, sphere2));
+        let sphere3 = Sphere::new([1.3, 1.3, 1.3], 0.5);
+        assert!(!aabb_sphere(aabb1, sphere3));
+        let sphere4 = Sphere::new([-0.3, 0.5, 1.3], 0.5);
+        assert!(aabb_sphere(aabb1, sphere4));
+    }
+
+    #[test]
+    fn aabb_sphere_corners_and_edges() {
+        use parry3d::math::{Isometry, Vector};
+        use parry3d::query::intersection_test;
+        use parry3d::shape::{Ball, Cuboid};
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut has_intersection = false;
+        let mut has_non_intersection = false;
+        for _ in 0..10000 {
+            let min = [0.0f32; 3].map(|_| rng.gen_range(-2.0..2.0));
+            let size = [0.0f32; 3].map(|_| rng.gen_range(0.1..2.0));
+            let aabb = Aabb::new(min, add(min, size));
+            let radius = rng.gen_range(0.05..1.0);
+
+            // Pick a corner (3 axes outside), edge (2 axes) or face (1 axis)
+            // region and place the sphere around it
+            let outside_axes = rng.gen_range(1..=3);
+            let axis_offset = rng.gen_range(0..3);
+            let center: [f32; 3] = std::array::from_fn(|i| {
+                if (i + axis_offset) % 3 < outside_axes {
+                    let side = if rng.gen() { aabb.max[i] } else { aabb.min[i] };
+                    let direction = if side == aabb.max[i] { 1.0 } else { -1.0 };
+                    side + direction * rng.gen_range(0.0..radius * 1.5)
+                } else {
+                    rng.gen_range(aabb.min[i]..aabb.max[i])
+                }
+            });
+            let sphere = Sphere::new(center, radius);
+
+            let closest = [0, 1, 2].map(|i| {
+                (center[i] as f64).clamp(aabb.min[i] as f64, aabb.max[i] as f64)
+            });
+            let distance = closest
+                .iter()
+                .zip(center)
+                .map(|(a, b)| (a - b as f64).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            if (distance - radius as f64).abs() < 1e-4 {
+                continue;
+            }
+
+            let [hx, hy, hz] = aabb.half_extents();
+            let [cx, cy, cz] = aabb.center();
+            let [sx, sy, sz] = sphere.center;
+            let expected = intersection_test(
+                &Isometry::translation(cx, cy, cz),
+                &Cuboid::new(Vector::new(hx, hy, hz)),
+                &Isometry::translation(sx, sy, sz),
+                &Ball::new(radius),
+            )
+            .unwrap();
+            assert_eq!(
+                aabb_sphere(aabb, sphere),
+                expected,
+                "{:?} {:?}",
+                aabb,
+                sphere
+            );
+            if expected {
+                has_intersection = true;
+            } else {
+                has_non_intersection = true;
+            }
+        }
+        assert!(has_intersection, "No intersections found");
+        assert!(has_non_intersection, "Only intersections found");
     }
 
     #[test]
@@ -135,4 +790,195 @@ mod tests {
         let aabb2 = Aabb::new([1.0, 1.0, 1.0], [2.0, 2.0, 2.0]);
         assert!(!ray_aabb(ray2, aabb2));
     }
+
+    #[test]
+    fn ray_sphere_intersections() {
+        let sphere = Sphere::new([0.0, 0.0, 0.0], 1.0);
+        let ray = Ray::new([0.0, -5.0, 0.0], [0.0, 2.0, 0.0]);
+        assert_eq!(ray_sphere_time(ray, sphere), 2.0);
+        let inside = Ray::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(ray_sphere_time(inside, sphere), 1.0);
+        let away = Ray::new([0.0, -5.0, 0.0], [0.0, -1.0, 0.0]);
+        assert!(!ray_sphere(away, sphere));
+        let beside = Ray::new([0.0, -5.0, 1.5], [0.0, 1.0, 0.0]);
+        assert!(!ray_sphere(beside, sphere));
+    }
+
+    #[test]
+    fn capsule_intersections() {
+        let capsule = Capsule::new([0.0, -1.0, 0.0], [0.0, 1.0, 0.0], 0.5);
+        assert!(capsule_point(capsule, [0.0, 1.4, 0.0]));
+        assert!(capsule_point(capsule, [0.45, -1.0, 0.0]));
+        assert!(!capsule_point(capsule, [0.45, -1.45, 0.0]));
+        assert!(capsule_sphere(capsule, Sphere::new([1.0, 0.0, 0.0], 0.6)));
+        assert!(!capsule_sphere(capsule, Sphere::new([1.0, 1.6, 0.0], 0.6)));
+        let lying = Capsule::new([-2.0, 1.9, 0.0], [2.0, 1.9, 0.0], 0.5);
+        assert!(capsule_capsule(capsule, lying));
+        assert!(!capsule_capsule(
+            capsule,
+            Capsule {
+                radius: 0.3,
+                ..lying
+            }
+        ));
+        let tilted = Obb::from_rotation(
+            [1.1, 0.0, 0.0],
+            [0.5, 0.5, 0.5],
+            Isometry::<f32>::from_axis_angle([0.0, 0.0, 1.0], 0.78).rotation,
+        );
+        assert!(capsule_obb(capsule, tilted));
+        assert!(!capsule_obb(
+            Capsule {
+                radius: 0.2,
+                ..capsule
+            },
+            tilted
+        ));
+
+        let ray = Ray::new([3.0, 0.5, 0.0], [-1.0, 0.0, 0.0]);
+        assert!((ray_capsule_time(ray, capsule) - 2.5).abs() < 1e-6);
+        let ray = Ray::new([0.0, 5.0, 0.0], [0.0, -2.0, 0.0]);
+        assert!((ray_capsule_time(ray, capsule) - 1.75).abs() < 1e-6);
+        let ray = Ray::new([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]);
+        assert!((ray_capsule_time(ray, capsule) - 0.5).abs() < 1e-6);
+
+        let mut rng = rand::thread_rng();
+        let mut has_intersection = false;
+        let mut has_non_intersection = false;
+        for _ in 0..1000 {
+            let mut point = || [0.0; 3].map(|_| rng.gen_range(-3.0..3.0));
+            let capsule = Capsule::new(point(), point(), 0.8);
+            let ray = Ray::new(point(), point());
+            let t = ray_capsule_time(ray, capsule);
+            if t < 0.0 {
+                has_non_intersection = true;
+                continue;
+            }
+            has_intersection = true;
+            let hit: [f32; 3] = ray.get_point(t);
+            let core = closest_point_on_segment(capsule.a, capsule.b, hit);
+            assert!((distance_squared(hit, core).sqrt() - 0.8).abs() < 1e-3);
+        }
+        assert!(has_intersection && has_non_intersection);
+    }
+
+    #[test]
+    fn triangle_triangle_intersections() {
+        let floor =
+            Triangle::new([-1.0, 0.0, -1.0], [2.0, 0.0, -1.0], [-1.0, 0.0, 2.0]);
+        let upright =
+            Triangle::new([0.0, -1.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]);
+        assert!(triangle_triangle(floor, upright));
+        let lifted =
+            Triangle::new([0.0, 0.1, 0.0], [0.0, 1.0, 0.0], [0.0, 0.1, 1.0]);
+        assert!(!triangle_triangle(floor, lifted));
+        // Coplanar, overlapping and apart
+        let inside =
+            Triangle::new([0.0, 0.0, 0.0], [0.1, 0.0, 0.0], [0.0, 0.0, 0.1]);
+        assert!(triangle_triangle(floor, inside));
+        let beyond =
+            Triangle::new([1.0, 0.0, 1.0], [2.0, 0.0, 1.0], [1.0, 0.0, 2.0]);
+        assert!(!triangle_triangle(floor, beyond));
+    }
+
+    #[test]
+    fn segment_obb_closest_points() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let mut point = || [0.0; 3].map(|_| rng.gen_range(-3.0..3.0));
+            let (a, b) = (point(), point());
+            let axis = point();
+            let center = point();
+            let half_extents = [0.0; 3].map(|_| rng.gen_range(0.1..1.5));
+            let obb = Obb::from_rotation(
+                center,
+                half_extents,
+                Isometry::<f32>::from_axis_angle(axis, rng.gen_range(0.0..3.0))
+                    .rotation,
+            );
+            let (p, q) = closest_points_segment_obb((a, b), obb);
+            assert!(distance_squared(p, closest_point_on_segment(a, b, p)) < 1e-8);
+            assert!(distance_squared(q, closest_point_on_obb(obb, q)) < 1e-8);
+            let brute_force = (0..=2000)
+                .map(|i| {
+                    let p = add(a, scale(sub(b, a), i as f32 / 2000.0));
+                    distance_squared(p, closest_point_on_obb(obb, p)).sqrt()
+                })
+                .fold(f32::INFINITY, f32::min);
+            let distance = distance_squared(p, q).sqrt();
+            assert!(
+                distance <= brute_force + 1e-4,
+                "{} > {}",
+                distance,
+                brute_force
+            );
+            assert!(
+                distance >= brute_force - 1e-2,
+                "{} < {}",
+                distance,
+                brute_force
+            );
+        }
+    }
+
+    #[test]
+    fn obb_obb_intersections() {
+        let obb1 = Obb::from(Aabb::new([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]));
+        let rotated =
+            Isometry::from_axis_angle([0.0, 0.0, 1.0], std::f32::consts::FRAC_PI_4);
+        let obb2 = obb1.transformed(&rotated.with_translation([2.3, 0.0, 0.0]));
+        assert!(obb_obb(obb1, obb2));
+        let obb3 = obb1.transformed(&rotated.with_translation([2.5, 0.0, 0.0]));
+        assert!(!obb_obb(obb1, obb3));
+        let obb4 = obb1.transformed(&rotated.with_translation([2.0, 2.0, 0.0]));
+        assert!(!obb_obb(obb1, obb4));
+    }
+
+    #[test]
+    fn isometry_intersections() {
+        let aabb = Aabb::new([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]);
+        let rotated =
+            Isometry::from_axis_angle([0.0, 0.0, 1.0], std::f32::consts::FRAC_PI_4)
+                .with_translation([5.0, 0.0, 0.0]);
+        assert!(aabb_point_iso((aabb, &rotated), [6.3, 0.0, 0.0]));
+        assert!(!aabb_point_iso((aabb, &rotated), [5.9, 0.9, 0.0]));
+        assert!(aabb_aabb_iso(
+            (aabb, &rotated),
+            (aabb, &Isometry::from_translation([2.7, 0.0, 0.0]))
+        ));
+        assert!(!aabb_aabb_iso(
+            (aabb, &rotated),
+            (aabb, &Isometry::from_translation([2.5, 2.0, 0.0]))
+        ));
+
+        let ray = Ray::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let t = ray_aabb_time_iso(ray, (aabb, &rotated));
+        assert!((t - (5.0 - 2.0f32.sqrt())).abs() < 1e-5);
+        let ray = Ray::new([0.0, 1.6, 0.0], [1.0, 0.0, 0.0]);
+        assert!(!ray_aabb_iso(ray, (aabb, &rotated)));
+
+        let sphere = Sphere::new([0.0, 0.0, 0.0], 0.5);
+        assert!(sphere_sphere_iso(
+            (sphere, &Isometry::from_translation([4.5, 0.0, 0.0])),
+            (sphere, &rotated)
+        ));
+        assert!(sphere_point_iso((sphere, &rotated), [5.0, 0.4, 0.0]));
+    }
+
+    #[test]
+    fn f64_queries() {
+        // A gap far below f32 precision at this distance from the origin
+        let offset = 1.0e9;
+        let a = Aabb::<f64>::new([offset; 3], [offset + 1.0; 3]);
+        let b = a.with_offset([1.0 + 1.0e-3, 0.0, 0.0]);
+        assert!(!aabb_aabb(a, b));
+        assert!(aabb_aabb(a, a.with_offset([1.0 - 1.0e-3, 0.0, 0.0])));
+
+        let ray =
+            Ray::new([offset - 2.0, offset + 0.5, offset + 0.5], [1.0, 0.0, 0.0]);
+        assert!((ray_aabb_time(ray, b) - (3.0 + 1.0e-3)).abs() < 1.0e-6);
+        let sphere = Sphere::new([offset - 0.25, offset, offset], 0.25 + 1.0e-6);
+        assert!(aabb_sphere(a, sphere));
+        assert!(!aabb_sphere(b, sphere));
+    }
 }
@@ -0,0 +1,76 @@
+//! Union-find over body slots, used to split the contact graph into islands
+//! that fall asleep and wake up together.
+
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, Default)]
+pub struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    /// `len` singleton sets.
+    pub fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: alloc::vec![1; len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    /// The representative of `i`'s set, halving the path on the way up.
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    /// Merges the sets of `a` and `b`, returning the new representative.
+    pub fn union(&mut self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return a;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            core::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+        a
+    }
+
+    /// The size of `i`'s set.
+    pub fn size(&mut self, i: usize) -> usize {
+        let root = self.find(i);
+        self.sizes[root]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets() {
+        let mut sets = UnionFind::new(6);
+        sets.union(0, 1);
+        sets.union(2, 3);
+        sets.union(1, 3);
+        assert_eq!(sets.find(0), sets.find(2));
+        assert_ne!(sets.find(0), sets.find(4));
+        assert_eq!(sets.size(3), 4);
+        assert_eq!(sets.size(5), 1);
+        assert_eq!(sets.union(4, 4), sets.find(4));
+        assert_eq!(sets.len(), 6);
+    }
+}
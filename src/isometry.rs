@@ -0,0 +1,236 @@
+use crate::math::*;
+use crate::real::Real;
+use crate::Vec3;
+use core::ops::Mul;
+
+pub type Quat<T = f32> = mint::Quaternion<T>;
+
+/// A rigid transform: rotate by `rotation` (a unit quaternion stored as
+/// `[x, y, z, w]`), then translate by `translation`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Isometry<T: Real = f32> {
+    pub translation: [T; 3],
+    pub rotation: [T; 4],
+}
+
+impl<T: Real> Default for Isometry<T> {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl<T: Real> Isometry<T> {
+    pub fn new(
+        translation: impl Into<Vec3<T>>,
+        rotation: impl Into<Quat<T>>,
+    ) -> Self {
+        let rotation: [T; 4] = rotation.into().into();
+        let length = rotation.iter().map(|r| *r * *r).sum::<T>().sqrt();
+        assert!(length > T::ZERO, "rotation quaternion must be non-zero");
+        Self {
+            translation: translation.into().into(),
+            rotation: rotation.map(|r| r / length),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self {
+            translation: [T::ZERO; 3],
+            rotation: [T::ZERO, T::ZERO, T::ZERO, T::ONE],
+        }
+    }
+
+    pub fn from_translation(translation: impl Into<Vec3<T>>) -> Self {
+        Self {
+            translation: translation.into().into(),
+            ..Self::identity()
+        }
+    }
+
+    pub fn from_axis_angle(axis: impl Into<Vec3<T>>, angle: T) -> Self {
+        let axis: [T; 3] = axis.into().into();
+        assert!(length(axis) > T::ZERO, "rotation axis must be non-zero");
+        let axis = normalize(axis);
+        let (sin, cos) = sin_cos(angle * T::HALF);
+        Self {
+            translation: [T::ZERO; 3],
+            rotation: [axis[0] * sin, axis[1] * sin, axis[2] * sin, cos],
+        }
+    }
+
+    pub fn with_translation(&self, translation: impl Into<Vec3<T>>) -> Self {
+        Self {
+            translation: translation.into().into(),
+            rotation: self.rotation,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        let rotation = quat_conjugate(self.rotation);
+        Self {
+            translation: scale(quat_rotate(rotation, self.translation), -T::ONE),
+            rotation,
+        }
+    }
+
+    pub fn transform_point<P: From<Vec3<T>>>(&self, point: impl Into<Vec3<T>>) -> P {
+        let point = add(
+            quat_rotate(self.rotation, point.into().into()),
+            self.translation,
+        );
+        Vec3::from(point).into()
+    }
+
+    pub fn transform_vector<P: From<Vec3<T>>>(
+        &self,
+        vector: impl Into<Vec3<T>>,
+    ) -> P {
+        Vec3::from(quat_rotate(self.rotation, vector.into().into())).into()
+    }
+
+    pub fn inverse_transform_point<P: From<Vec3<T>>>(
+        &self,
+        point: impl Into<Vec3<T>>,
+    ) -> P {
+        let local = sub(point.into().into(), self.translation);
+        Vec3::from(quat_rotate(quat_conjugate(self.rotation), local)).into()
+    }
+
+    pub fn inverse_transform_vector<P: From<Vec3<T>>>(
+        &self,
+        vector: impl Into<Vec3<T>>,
+    ) -> P {
+        let vector = vector.into().into();
+        Vec3::from(quat_rotate(quat_conjugate(self.rotation), vector)).into()
+    }
+
+    pub fn axes(&self) -> [[T; 3]; 3] {
+        quat_to_axes(self.rotation)
+    }
+
+    /// The transform as a 4x4 matrix, for any type mint can convert into.
+    pub fn to_matrix<M: From<mint::ColumnMatrix4<T>>>(&self) -> M {
+        mint::ColumnMatrix4::from(*self).into()
+    }
+}
+
+impl<T: Real, V: Into<Vec3<T>>, Q: Into<Quat<T>>> From<(V, Q)> for Isometry<T> {
+    fn from((translation, rotation): (V, Q)) -> Self {
+        Self::new(translation, rotation)
+    }
+}
+
+impl<T: Real, V: From<Vec3<T>>, Q: From<Quat<T>>> From<Isometry<T>> for (V, Q) {
+    fn from(iso: Isometry<T>) -> Self {
+        (
+            Vec3::from(iso.translation).into(),
+            Quat::from(iso.rotation).into(),
+        )
+    }
+}
+
+impl<T: Real> From<Isometry<T>> for mint::ColumnMatrix4<T> {
+    fn from(iso: Isometry<T>) -> Self {
+        let [x, y, z] = iso.axes();
+        let [tx, ty, tz] = iso.translation;
+        mint::ColumnMatrix4::from([
+            [x[0], x[1], x[2], T::ZERO],
+            [y[0], y[1], y[2], T::ZERO],
+            [z[0], z[1], z[2], T::ZERO],
+            [tx, ty, tz, T::ONE],
+        ])
+    }
+}
+
+impl<T: Real> Mul for Isometry<T> {
+    type Output = Isometry<T>;
+
+    /// `a * b` applies `b` first, then `a`.
+    fn mul(self, rhs: Isometry<T>) -> Isometry<T> {
+        Isometry {
+            translation: self.transform_point(rhs.translation),
+            rotation: quat_mul(self.rotation, rhs.rotation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    fn dot4(a: [f32; 4], b: [f32; 4]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    #[test]
+    fn transform_points() {
+        let iso = Isometry::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2)
+            .with_translation([1.0, 2.0, 3.0]);
+        let p: [f32; 3] = iso.transform_point([1.0, 0.0, 0.0]);
+        assert_close(p, [1.0, 3.0, 3.0]);
+        let back: [f32; 3] = iso.inverse_transform_point(p);
+        assert_close(back, [1.0, 0.0, 0.0]);
+        let inverse: [f32; 3] = iso.inverse().transform_point(p);
+        assert_close(inverse, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn compose() {
+        let a = Isometry::from_axis_angle([0.0, 1.0, 0.0], FRAC_PI_2)
+            .with_translation([0.0, 1.0, 0.0]);
+        let b = Isometry::from_axis_angle([1.0, 0.0, 0.0], 0.3)
+            .with_translation([2.0, 0.0, -1.0]);
+        let p = [0.5, -0.25, 2.0];
+        let step: [f32; 3] = b.transform_point(p);
+        let step: [f32; 3] = a.transform_point(step);
+        let composed: [f32; 3] = (a * b).transform_point(p);
+        assert_close(step, composed);
+    }
+
+    #[test]
+    #[should_panic(expected = "rotation quaternion must be non-zero")]
+    fn zero_quaternion() {
+        Isometry::new([1.0, 2.0, 3.0], [0.0f32; 4]);
+    }
+
+    #[test]
+    #[should_panic(expected = "rotation axis must be non-zero")]
+    fn zero_axis() {
+        Isometry::from_axis_angle([0.0f32; 3], 0.5);
+    }
+
+    #[test]
+    fn mint_conversions() {
+        let iso = Isometry::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2)
+            .with_translation([1.0, 2.0, 3.0]);
+        let (translation, rotation): (mint::Vector3<f32>, Quat) = iso.into();
+        let back = Isometry::from((translation, rotation));
+        assert_eq!(back.translation, iso.translation);
+        assert!(dot4(back.rotation, iso.rotation) > 1.0 - 1e-6);
+
+        let matrix: mint::ColumnMatrix4<f32> = iso.to_matrix();
+        let p = [1.0, 0.0, 0.0];
+        let by_matrix: [f32; 3] = std::array::from_fn(|i| {
+            let row: [f32; 4] = [matrix.x, matrix.y, matrix.z, matrix.w]
+                .map(|column| [column.x, column.y, column.z, column.w][i]);
+            row[0] * p[0] + row[1] * p[1] + row[2] * p[2] + row[3]
+        });
+        assert_close(by_matrix, iso.transform_point(p));
+        assert_eq!(
+            axes_to_quat(iso.axes()).map(|v| (v * 1e5).round()),
+            iso.rotation.map(|v| (v * 1e5).round())
+        );
+    }
+}
@@ -0,0 +1,541 @@
+//! Joints between the colliders of a `PhysicsWorld`, solved as rows of
+//! impulses in the same loop as the contacts.
+
+use crate::dynamics::{SolverBody, BAUMGARTE};
+use crate::isometry::Isometry;
+use crate::math::*;
+use crate::real::Real;
+use crate::world::ColliderHandle;
+use alloc::vec::Vec;
+
+/// Three point rows, three angular rows, two limits and a motor.
+const ROWS: usize = 9;
+const LOWER: usize = 6;
+const UPPER: usize = 7;
+const MOTOR: usize = 8;
+
+/// Identifies a joint in a `PhysicsWorld`. Handles of removed joints are never
+/// handed out again, even when their slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JointHandle {
+    index: u32,
+    generation: u32,
+}
+
+impl JointHandle {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        Self {
+            index: index as u32,
+            generation,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self.index as usize
+    }
+
+    pub(crate) fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+/// What a joint leaves free. Limits and motors act on the hinge angle, the
+/// prismatic translation or the distance; ball and fixed joints have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JointKind {
+    /// Pins the anchors together and lets the bodies turn freely.
+    Ball,
+    /// Pins the anchors together and lets the bodies turn about the frame's
+    /// x axis.
+    Hinge,
+    /// Lets the bodies slide along the frame's x axis without turning.
+    Prismatic,
+    /// Keeps the frames together.
+    Fixed,
+    /// Only acts through its limits and motor, on the distance between the
+    /// anchors.
+    Distance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Motor<T: Real = f32> {
+    pub target_velocity: T,
+    /// Also pulls towards this position, adding `stiffness` times the error
+    /// to the target velocity.
+    pub target_position: Option<T>,
+    pub stiffness: T,
+    /// Force, or torque for hinges, the motor can apply.
+    pub max_force: T,
+}
+
+impl<T: Real> Motor<T> {
+    pub fn velocity(target_velocity: T, max_force: T) -> Self {
+        Self {
+            target_velocity,
+            target_position: None,
+            stiffness: T::ZERO,
+            max_force,
+        }
+    }
+
+    pub fn position(target_position: T, stiffness: T, max_force: T) -> Self {
+        Self {
+            target_velocity: T::ZERO,
+            target_position: Some(target_position),
+            stiffness,
+            max_force,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Joint<T: Real = f32> {
+    pub kind: JointKind,
+    pub a: ColliderHandle,
+    pub b: ColliderHandle,
+    /// The joint's anchor and axes in each collider's local space.
+    pub frame_a: Isometry<T>,
+    pub frame_b: Isometry<T>,
+    /// Smallest and largest angle, translation or distance.
+    pub limits: Option<[T; 2]>,
+    pub motor: Option<Motor<T>>,
+    /// Whether the two colliders still get contacts with each other.
+    pub collide_connected: bool,
+    impulses: [T; ROWS],
+}
+
+impl<T: Real> Joint<T> {
+    pub fn new(
+        kind: JointKind,
+        a: ColliderHandle,
+        frame_a: Isometry<T>,
+        b: ColliderHandle,
+        frame_b: Isometry<T>,
+    ) -> Self {
+        Self {
+            kind,
+            a,
+            b,
+            frame_a,
+            frame_b,
+            limits: None,
+            motor: None,
+            collide_connected: false,
+            impulses: [T::ZERO; ROWS],
+        }
+    }
+
+    pub fn with_limits(mut self, min: T, max: T) -> Self {
+        self.limits = Some([min, max]);
+        self
+    }
+
+    pub fn with_motor(mut self, motor: Motor<T>) -> Self {
+        self.motor = Some(motor);
+        self
+    }
+
+    pub fn with_collide_connected(mut self, collide_connected: bool) -> Self {
+        self.collide_connected = collide_connected;
+        self
+    }
+
+    /// The hinge angle, prismatic translation or distance with the
+    /// colliders at `transform_a` and `transform_b`. Zero for ball and
+    /// fixed joints.
+    pub fn position(
+        &self,
+        transform_a: &Isometry<T>,
+        transform_b: &Isometry<T>,
+    ) -> T {
+        let (frame_a, frame_b) =
+            (*transform_a * self.frame_a, *transform_b * self.frame_b);
+        let (axes_a, axes_b) = (frame_a.axes(), frame_b.axes());
+        let offset = sub(frame_b.translation, frame_a.translation);
+        match self.kind {
+            JointKind::Hinge => {
+                let turn = dot(cross(axes_a[1], axes_b[1]), axes_a[0]);
+                atan2(turn, dot(axes_a[1], axes_b[1]))
+            }
+            JointKind::Prismatic => dot(offset, axes_a[0]),
+            JointKind::Distance => length(offset),
+            JointKind::Ball | JointKind::Fixed => T::ZERO,
+        }
+    }
+
+    /// Appends the rows holding this joint together this step, starting from
+    /// last step's impulses.
+    pub(crate) fn rows(
+        &self,
+        joint: usize,
+        bodies: (usize, usize),
+        (transform_a, transform_b): (&Isometry<T>, &Isometry<T>),
+        solver_bodies: &[SolverBody<T>],
+        dt: T,
+        rows: &mut Vec<Row<T>>,
+    ) {
+        let (frame_a, frame_b) =
+            (*transform_a * self.frame_a, *transform_b * self.frame_b);
+        let (axes_a, axes_b) = (frame_a.axes(), frame_b.axes());
+        let (anchor_a, anchor_b) = (frame_a.translation, frame_b.translation);
+        let offset = sub(anchor_b, anchor_a);
+        let (center_a, center_b) = (
+            solver_bodies[bodies.0].center,
+            solver_bodies[bodies.1].center,
+        );
+        let anchors = (sub(anchor_a, center_a), sub(anchor_b, center_b));
+        let correction = T::from_f64(BAUMGARTE) / dt;
+        let mut push =
+            |slot: usize, jacobian: Jacobian<T>, target: T, bounds: (T, T)| {
+                let (a, b) = (&solver_bodies[bodies.0], &solver_bodies[bodies.1]);
+                let (turn_a, turn_b) = jacobian.turns();
+                let k = (a.inverse_mass + b.inverse_mass)
+                    * dot(jacobian.linear, jacobian.linear)
+                    + dot(turn_a, mat_mul_vec(a.inverse_inertia, turn_a))
+                    + dot(turn_b, mat_mul_vec(b.inverse_inertia, turn_b));
+                if k <= T::ZERO {
+                    return;
+                }
+                rows.push(Row {
+                    joint,
+                    slot,
+                    bodies,
+                    jacobian,
+                    target,
+                    bounds,
+                    mass: T::ONE / k,
+                    impulse: self.impulses[slot],
+                });
+            };
+        let free = (-T::INFINITY, T::INFINITY);
+        let world_axes = identity::<T>();
+
+        if matches!(
+            self.kind,
+            JointKind::Ball | JointKind::Hinge | JointKind::Fixed
+        ) {
+            for (slot, axis) in world_axes.into_iter().enumerate() {
+                let jacobian = Jacobian::linear(axis, anchors);
+                push(slot, jacobian, -correction * dot(offset, axis), free);
+            }
+        }
+        if self.kind == JointKind::Prismatic {
+            // Measured at b's anchor, so the offset along the axis is free
+            let slide = (sub(anchor_b, center_a), anchors.1);
+            for (slot, axis) in axes_a.into_iter().enumerate().skip(1) {
+                let jacobian = Jacobian::linear(axis, slide);
+                push(slot, jacobian, -correction * dot(offset, axis), free);
+            }
+        }
+        if matches!(self.kind, JointKind::Prismatic | JointKind::Fixed) {
+            let [x, y, z, w] =
+                quat_mul(frame_b.rotation, quat_conjugate(frame_a.rotation));
+            let error = scale([x, y, z], T::TWO * w.signum());
+            for (slot, axis) in world_axes.into_iter().enumerate() {
+                let jacobian = Jacobian::angular(axis);
+                push(3 + slot, jacobian, -correction * error[slot], free);
+            }
+        }
+        if self.kind == JointKind::Hinge {
+            let error = cross(axes_a[0], axes_b[0]);
+            for (slot, axis) in axes_a.into_iter().enumerate().skip(1) {
+                let jacobian = Jacobian::angular(axis);
+                push(3 + slot, jacobian, -correction * dot(error, axis), free);
+            }
+        }
+
+        let jacobian = match self.kind {
+            JointKind::Hinge => Jacobian::angular(axes_a[0]),
+            JointKind::Prismatic => {
+                Jacobian::linear(axes_a[0], (sub(anchor_b, center_a), anchors.1))
+            }
+            JointKind::Distance => {
+                let distance = length(offset);
+                let direction = if distance > T::EPSILON {
+                    scale(offset, T::ONE / distance)
+                } else {
+                    axes_a[0]
+                };
+                Jacobian::linear(direction, anchors)
+            }
+            JointKind::Ball | JointKind::Fixed => return,
+        };
+        let position = self.position(transform_a, transform_b);
+        if let Some([min, max]) = self.limits {
+            // Speculative, so an approaching limit stops the bodies right at it
+            let target = |gap: T| {
+                if gap > T::ZERO {
+                    -gap / dt
+                } else {
+                    -correction * gap
+                }
+            };
+            let push_only = (T::ZERO, T::INFINITY);
+            push(LOWER, jacobian, target(position - min), push_only);
+            push(
+                UPPER,
+                jacobian.reversed(),
+                target(max - position),
+                push_only,
+            );
+        }
+        if let Some(motor) = self.motor {
+            let pull = motor
+                .target_position
+                .map_or(T::ZERO, |target| motor.stiffness * (target - position));
+            let limit = motor.max_force * dt;
+            push(
+                MOTOR,
+                jacobian,
+                motor.target_velocity + pull,
+                (-limit, limit),
+            );
+        }
+    }
+
+    /// Starts collecting this step's impulses.
+    pub(crate) fn clear_impulses(&mut self) {
+        self.impulses = [T::ZERO; ROWS];
+    }
+
+    pub(crate) fn store_impulse(&mut self, row: &Row<T>) {
+        self.impulses[row.slot] = row.impulse;
+    }
+}
+
+/// A row's direction: a linear part applied at an offset from each centre
+/// of mass, plus a pure angular part.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Jacobian<T: Real> {
+    linear: [T; 3],
+    offsets: ([T; 3], [T; 3]),
+    angular: [T; 3],
+}
+
+impl<T: Real> Jacobian<T> {
+    fn linear(direction: [T; 3], offsets: ([T; 3], [T; 3])) -> Self {
+        Self {
+            linear: direction,
+            offsets,
+            angular: [T::ZERO; 3],
+        }
+    }
+
+    fn angular(axis: [T; 3]) -> Self {
+        Self {
+            linear: [T::ZERO; 3],
+            offsets: ([T::ZERO; 3], [T::ZERO; 3]),
+            angular: axis,
+        }
+    }
+
+    fn reversed(self) -> Self {
+        Self {
+            linear: scale(self.linear, -T::ONE),
+            angular: scale(self.angular, -T::ONE),
+            ..self
+        }
+    }
+
+    /// The angular parts seen by each body.
+    fn turns(&self) -> ([T; 3], [T; 3]) {
+        (
+            add(cross(self.offsets.0, self.linear), self.angular),
+            add(cross(self.offsets.1, self.linear), self.angular),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Row<T: Real> {
+    pub joint: usize,
+    slot: usize,
+    bodies: (usize, usize),
+    jacobian: Jacobian<T>,
+    /// Relative velocity along the row that the solver aims for.
+    target: T,
+    bounds: (T, T),
+    mass: T,
+    impulse: T,
+}
+
+impl<T: Real> Row<T> {
+    fn apply(&self, bodies: &mut [SolverBody<T>], impulse: T) {
+        let (turn_a, turn_b) = self.jacobian.turns();
+        let linear = scale(self.jacobian.linear, impulse);
+        let (a, b) = self.bodies;
+        for (body, sign, turn) in [(a, -T::ONE, turn_a), (b, T::ONE, turn_b)] {
+            let body = &mut bodies[body];
+            body.linear = add(body.linear, scale(linear, sign * body.inverse_mass));
+            let spin =
+                mat_mul_vec(body.inverse_inertia, scale(turn, sign * impulse));
+            body.angular = add(body.angular, spin);
+        }
+    }
+
+    pub(crate) fn warm_start(&self, bodies: &mut [SolverBody<T>]) {
+        self.apply(bodies, self.impulse);
+    }
+
+    pub(crate) fn solve(&mut self, bodies: &mut [SolverBody<T>]) {
+        let (a, b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+        let (turn_a, turn_b) = self.jacobian.turns();
+        let speed = dot(sub(b.linear, a.linear), self.jacobian.linear)
+            + dot(b.angular, turn_b)
+            - dot(a.angular, turn_a);
+        let total = (self.impulse + (self.target - speed) * self.mass)
+            .max(self.bounds.0)
+            .min(self.bounds.1);
+        let change = total - self.impulse;
+        self.impulse = total;
+        self.apply(bodies, change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamics::PhysicsWorld;
+    use crate::shapes::{Aabb, Sphere};
+    use crate::test_utils::physics::{close, cuboid, run};
+    use crate::world::Collider;
+    use core::f32::consts::FRAC_PI_2;
+
+    fn pin(world: &mut PhysicsWorld, position: [f32; 3]) -> ColliderHandle {
+        let pin = Sphere::new([0.0; 3], 0.05);
+        world
+            .world_mut()
+            .insert(Collider::new(pin, Isometry::from_translation(position)))
+    }
+
+    fn frames(world: &PhysicsWorld, joint: JointHandle) -> (Isometry, Isometry) {
+        let joint = world.joint(joint).unwrap();
+        let transform = |h| world.world().get(h).unwrap().transform;
+        (
+            transform(joint.a) * joint.frame_a,
+            transform(joint.b) * joint.frame_b,
+        )
+    }
+
+    fn position(world: &PhysicsWorld, joint: JointHandle) -> f32 {
+        let joint = world.joint(joint).unwrap();
+        let transform = |h| world.world().get(h).unwrap().transform;
+        joint.position(&transform(joint.a), &transform(joint.b))
+    }
+
+    #[test]
+    fn pendulum_and_door() {
+        let mut world = PhysicsWorld::new();
+        let anchor = pin(&mut world, [0.0, 5.0, 0.0]);
+        let bob = cuboid(&mut world, [0.25; 3], [2.0, 5.0, 0.0]);
+        let frame = Isometry::from_translation([0.0, 5.0, 0.0]);
+        let joint = world.joint_at(JointKind::Ball, anchor, bob, frame).unwrap();
+        let pendulum = world.insert_joint(joint);
+
+        // A door hung on a vertical hinge, driven open until it hits its stop
+        let post = pin(&mut world, [5.0, 1.0, 0.0]);
+        let door = cuboid(&mut world, [0.5, 1.0, 0.05], [5.6, 1.0, 0.0]);
+        let upright = Isometry::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2)
+            .with_translation([5.0, 1.0, 0.0]);
+        let joint = world
+            .joint_at(JointKind::Hinge, post, door, upright)
+            .unwrap()
+            .with_limits(0.0, FRAC_PI_2)
+            .with_motor(Motor::velocity(2.0, 50.0));
+        let hinge = world.insert_joint(joint);
+
+        run(&mut world, 0.75);
+        let center = world.world().get(bob).unwrap().transform.translation;
+        assert!(center[1] < 3.5, "{:?}", center);
+        run(&mut world, 2.25);
+        let (a, b) = frames(&world, pendulum);
+        assert!(length(sub(a.translation, b.translation)) < 0.02);
+        let center = world.world().get(bob).unwrap().transform.translation;
+        // A swinging period later it's back near its start, but never higher
+        assert!(center[1] < 5.01);
+        assert!(close(length(sub(center, [0.0, 5.0, 0.0])), 2.0, 0.02));
+
+        let (a, b) = frames(&world, hinge);
+        assert!(length(sub(a.translation, b.translation)) < 0.02);
+        assert!(dot(a.axes()[0], b.axes()[0]) > 0.999);
+        let angle = position(&world, hinge);
+        assert!(close(angle, FRAC_PI_2, 0.05), "{}", angle);
+        let center = world.world().get(door).unwrap().transform.translation;
+        assert!(close(center[2], -0.6, 0.05), "{:?}", center);
+
+        assert!(world.remove(door).is_some());
+        assert!(world.joint(hinge).is_none());
+        assert_eq!(world.joints().count(), 1);
+    }
+
+    #[test]
+    fn prismatic_fixed_and_distance() {
+        let mut world = PhysicsWorld::new();
+        let slide = Isometry::from_axis_angle([0.0, 0.0, 1.0], FRAC_PI_2);
+
+        // Two sprung wheels, one of which can't travel far enough
+        let mut wheels = [0.0, 3.0].map(|x| {
+            let anchor = pin(&mut world, [x, 3.0, 0.0]);
+            let wheel = cuboid(&mut world, [0.25; 3], [x, 2.0, 0.0]);
+            let frame = slide.with_translation([x, 3.0, 0.0]);
+            let joint = world
+                .joint_at(JointKind::Prismatic, anchor, wheel, frame)
+                .unwrap()
+                .with_motor(Motor::position(-1.5, 5.0, 100.0));
+            world.insert_joint(joint)
+        });
+        world.joint_mut(wheels[1]).unwrap().limits = Some([-1.2, 0.0]);
+
+        let ground = Aabb::new([-20.0, -1.0, -20.0], [20.0, 0.0, 20.0]);
+        world
+            .world_mut()
+            .insert(Collider::new(ground, Isometry::identity()));
+        let left = cuboid(&mut world, [0.5; 3], [8.0, 2.0, 0.0]);
+        let right = cuboid(&mut world, [0.5; 3], [9.0, 2.0, 0.0]);
+        let weld = world
+            .joint_at(
+                JointKind::Fixed,
+                left,
+                right,
+                Isometry::from_translation([8.5, 2.0, 0.0]),
+            )
+            .unwrap();
+        let weld = world.insert_joint(weld);
+        world.body_mut(left).unwrap().angular_velocity = [1.0, 2.0, 0.0];
+
+        let anchor = pin(&mut world, [-5.0, 5.0, 0.0]);
+        let weight = cuboid(&mut world, [0.25; 3], [-5.0, 4.0, 0.0]);
+        let rope = Joint::new(
+            JointKind::Distance,
+            anchor,
+            Isometry::identity(),
+            weight,
+            Isometry::identity(),
+        )
+        .with_limits(0.0, 2.0);
+        let rope = world.insert_joint(rope);
+
+        run(&mut world, 4.0);
+        for (wheel, expected) in wheels.iter_mut().zip([-1.5, -1.2]) {
+            let (a, b) = frames(&world, *wheel);
+            let offset = sub(b.translation, a.translation);
+            assert!(close(offset[0], 0.0, 0.01) && close(offset[2], 0.0, 0.01));
+            assert!(dot(a.axes()[1], b.axes()[1]) > 0.999);
+            let travel = position(&world, *wheel);
+            assert!(close(travel, expected, 0.03), "{}", travel);
+        }
+
+        let (a, b) = frames(&world, weld);
+        assert!(length(sub(a.translation, b.translation)) < 0.02);
+        for (x, y) in a.axes().iter().zip(b.axes().iter()) {
+            assert!(dot(*x, *y) > 0.999);
+        }
+        let weight_center = world.world().get(weight).unwrap().transform.translation;
+        assert!(close(weight_center[1], 3.0, 0.03), "{:?}", weight_center);
+        assert!(close(position(&world, rope), 2.0, 0.03));
+    }
+}
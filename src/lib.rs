@@ -1,11 +1,37 @@
-#![feature(portable_simd)]
-#![feature(array_zip)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly-simd", feature(portable_simd))]
 
+extern crate alloc;
+
+pub mod batch;
+pub mod bvh;
+pub mod character;
+pub mod contacts;
+pub mod dispatch;
+pub mod dynamics;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod groups;
+pub mod heightfield;
+mod interop;
 pub mod intersections;
+pub mod islands;
+pub mod isometry;
+pub mod joints;
+pub mod materials;
+pub mod math;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod ray;
+pub mod real;
+pub mod sdf;
 pub mod shapes;
+pub mod simd;
+pub mod sweep;
 pub mod test_utils;
 pub mod utils;
+pub mod voxels;
 pub mod wide_intersections;
+pub mod world;
 
-pub type Vec3 = mint::Vector3<f32>;
+pub type Vec3<T = f32> = mint::Vector3<T>;
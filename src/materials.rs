@@ -0,0 +1,91 @@
+//! Surface properties of colliders and how two of them mix at a contact.
+
+use crate::real::Real;
+
+/// How the coefficients of two touching materials are combined. When the
+/// two sides ask for different rules, the one listed last wins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CombineRule {
+    #[default]
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+impl CombineRule {
+    pub fn combine<T: Real>(self, a: T, b: T) -> T {
+        match self {
+            CombineRule::Average => (a + b) * T::HALF,
+            CombineRule::Min => a.min(b),
+            CombineRule::Multiply => a * b,
+            CombineRule::Max => a.max(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Material<T: Real = f32> {
+    pub friction: T,
+    /// How much of the closing speed is given back, from 0 for a dead stop
+    /// to 1 for a perfect bounce.
+    pub restitution: T,
+    pub friction_combine: CombineRule,
+    pub restitution_combine: CombineRule,
+}
+
+impl<T: Real> Material<T> {
+    pub fn new(friction: T, restitution: T) -> Self {
+        Self {
+            friction,
+            restitution,
+            friction_combine: CombineRule::Average,
+            restitution_combine: CombineRule::Average,
+        }
+    }
+
+    pub fn with_friction_combine(mut self, rule: CombineRule) -> Self {
+        self.friction_combine = rule;
+        self
+    }
+
+    pub fn with_restitution_combine(mut self, rule: CombineRule) -> Self {
+        self.restitution_combine = rule;
+        self
+    }
+
+    /// The friction and restitution of a contact between `self` and `other`.
+    pub fn combine(&self, other: &Self) -> (T, T) {
+        let friction = self.friction_combine.max(other.friction_combine);
+        let restitution = self.restitution_combine.max(other.restitution_combine);
+        (
+            friction.combine(self.friction, other.friction),
+            restitution.combine(self.restitution, other.restitution),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combining() {
+        let wood = Material::new(0.5, 0.25);
+        let ice = Material::new(0.02, 0.125).with_friction_combine(CombineRule::Min);
+        let rubber =
+            Material::new(0.75, 0.75).with_restitution_combine(CombineRule::Max);
+        assert_eq!(wood.combine(&wood), (0.5, 0.25));
+        assert_eq!(wood.combine(&ice), (0.02, 0.1875));
+        assert_eq!(ice.combine(&rubber), (0.02, 0.75));
+        assert_eq!(rubber.combine(&wood), (0.625, 0.75));
+        // Multiply outranks Min, but not Max
+        let sticky = wood.with_friction_combine(CombineRule::Multiply);
+        assert_eq!(sticky.combine(&ice).0, 0.01);
+        assert_eq!(sticky.combine(&rubber).0, 0.375);
+        let grippy = rubber.with_friction_combine(CombineRule::Max);
+        assert_eq!(sticky.combine(&grippy).0, 0.75);
+    }
+}
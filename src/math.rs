@@ -0,0 +1,233 @@
+use crate::real::Real;
+
+pub fn add<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale<T: Real>(a: [T; 3], s: T) -> [T; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn min<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])]
+}
+
+pub fn max<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])]
+}
+
+/// The trigonometry used inside the crate. With the `deterministic` feature
+/// it goes through `libm`, which gives the same bits on every platform,
+/// rather than the platform's own maths library.
+pub fn sin_cos<T: Real>(angle: T) -> (T, T) {
+    #[cfg(feature = "deterministic")]
+    {
+        let angle = angle.to_f64().unwrap();
+        (T::from_f64(libm::sin(angle)), T::from_f64(libm::cos(angle)))
+    }
+    #[cfg(not(feature = "deterministic"))]
+    angle.sin_cos()
+}
+
+pub fn cos<T: Real>(angle: T) -> T {
+    sin_cos(angle).1
+}
+
+pub fn atan2<T: Real>(y: T, x: T) -> T {
+    #[cfg(feature = "deterministic")]
+    {
+        T::from_f64(libm::atan2(y.to_f64().unwrap(), x.to_f64().unwrap()))
+    }
+    #[cfg(not(feature = "deterministic"))]
+    y.atan2(x)
+}
+
+pub fn dot<T: Real>(a: [T; 3], b: [T; 3]) -> T {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross<T: Real>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn length<T: Real>(a: [T; 3]) -> T {
+    dot(a, a).sqrt()
+}
+
+pub fn normalize<T: Real>(a: [T; 3]) -> [T; 3] {
+    let length = length(a);
+    if length > T::ZERO {
+        scale(a, T::ONE / length)
+    } else {
+        a
+    }
+}
+
+pub fn quat_mul<T: Real>(a: [T; 4], b: [T; 4]) -> [T; 4] {
+    let [ax, ay, az, aw] = a;
+    let [bx, by, bz, bw] = b;
+    [
+        aw * bx + ax * bw + ay * bz - az * by,
+        aw * by - ax * bz + ay * bw + az * bx,
+        aw * bz + ax * by - ay * bx + az * bw,
+        aw * bw - ax * bx - ay * by - az * bz,
+    ]
+}
+
+pub fn quat_conjugate<T: Real>([x, y, z, w]: [T; 4]) -> [T; 4] {
+    [-x, -y, -z, w]
+}
+
+pub fn quat_rotate<T: Real>(q: [T; 4], v: [T; 3]) -> [T; 3] {
+    let q_v = [q[0], q[1], q[2]];
+    let t = scale(cross(q_v, v), T::TWO);
+    add(add(v, scale(t, q[3])), cross(q_v, t))
+}
+
+pub fn identity<T: Real>() -> [[T; 3]; 3] {
+    [
+        [T::ONE, T::ZERO, T::ZERO],
+        [T::ZERO, T::ONE, T::ZERO],
+        [T::ZERO, T::ZERO, T::ONE],
+    ]
+}
+
+/// Matrices are stored as columns, matching `quat_to_axes`.
+pub fn mat_mul_vec<T: Real>(m: [[T; 3]; 3], v: [T; 3]) -> [T; 3] {
+    add(add(scale(m[0], v[0]), scale(m[1], v[1])), scale(m[2], v[2]))
+}
+
+pub fn mat_mul<T: Real>(a: [[T; 3]; 3], b: [[T; 3]; 3]) -> [[T; 3]; 3] {
+    b.map(|column| mat_mul_vec(a, column))
+}
+
+pub fn transpose<T: Real>(m: [[T; 3]; 3]) -> [[T; 3]; 3] {
+    core::array::from_fn(|c| core::array::from_fn(|r| m[r][c]))
+}
+
+/// Inverse of `m`, or all zeros if it's singular.
+pub fn inverse<T: Real>(m: [[T; 3]; 3]) -> [[T; 3]; 3] {
+    let rows = transpose(m);
+    let adjugate = [
+        cross(rows[1], rows[2]),
+        cross(rows[2], rows[0]),
+        cross(rows[0], rows[1]),
+    ];
+    let determinant = dot(rows[0], adjugate[0]);
+    if determinant == T::ZERO {
+        return [[T::ZERO; 3]; 3];
+    }
+    transpose(adjugate.map(|row| scale(row, T::ONE / determinant)))
+}
+
+/// Two unit vectors perpendicular to `n` and each other.
+pub fn tangents<T: Real>(n: [T; 3]) -> ([T; 3], [T; 3]) {
+    let other = if n[0].abs() < T::from_f64(0.57) {
+        [T::ONE, T::ZERO, T::ZERO]
+    } else {
+        [T::ZERO, T::ONE, T::ZERO]
+    };
+    let t1 = normalize(cross(n, other));
+    (t1, cross(n, t1))
+}
+
+/// Columns of the rotation matrix, i.e. the rotated x, y and z axes.
+pub fn quat_to_axes<T: Real>(q: [T; 4]) -> [[T; 3]; 3] {
+    [
+        quat_rotate(q, [T::ONE, T::ZERO, T::ZERO]),
+        quat_rotate(q, [T::ZERO, T::ONE, T::ZERO]),
+        quat_rotate(q, [T::ZERO, T::ZERO, T::ONE]),
+    ]
+}
+
+/// Inverse of `quat_to_axes` for an orthonormal, right handed set of axes.
+pub fn axes_to_quat<T: Real>(axes: [[T; 3]; 3]) -> [T; 4] {
+    let m = |row: usize, column: usize| axes[column][row];
+    let quarter = T::HALF * T::HALF;
+    let trace = m(0, 0) + m(1, 1) + m(2, 2);
+    let q = if trace > T::ZERO {
+        let s = (trace + T::ONE).sqrt() * T::TWO;
+        [
+            (m(2, 1) - m(1, 2)) / s,
+            (m(0, 2) - m(2, 0)) / s,
+            (m(1, 0) - m(0, 1)) / s,
+            quarter * s,
+        ]
+    } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+        let s = (T::ONE + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * T::TWO;
+        [
+            quarter * s,
+            (m(0, 1) + m(1, 0)) / s,
+            (m(0, 2) + m(2, 0)) / s,
+            (m(2, 1) - m(1, 2)) / s,
+        ]
+    } else if m(1, 1) > m(2, 2) {
+        let s = (T::ONE + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * T::TWO;
+        [
+            (m(0, 1) + m(1, 0)) / s,
+            quarter * s,
+            (m(1, 2) + m(2, 1)) / s,
+            (m(0, 2) - m(2, 0)) / s,
+        ]
+    } else {
+        let s = (T::ONE + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * T::TWO;
+        [
+            (m(0, 2) + m(2, 0)) / s,
+            (m(1, 2) + m(2, 1)) / s,
+            quarter * s,
+            (m(1, 0) - m(0, 1)) / s,
+        ]
+    };
+    let length = q.iter().map(|v| *v * *v).sum::<T>().sqrt();
+    q.map(|v| v / length)
+}
+
+/// Eigenvectors of a symmetric 3x3 matrix using cyclic Jacobi rotations,
+/// returned as a right handed set of unit axes.
+pub fn symmetric_eigenvectors<T: Real>(m: [[T; 3]; 3]) -> [[T; 3]; 3] {
+    let mut a = m;
+    let mut v = identity();
+    for _ in 0..32 {
+        let (p, q) = [(0, 1), (0, 2), (1, 2)]
+            .into_iter()
+            .max_by(|(p1, q1), (p2, q2)| {
+                a[*p1][*q1].abs().total_cmp(&a[*p2][*q2].abs())
+            })
+            .unwrap();
+        if a[p][q].abs() <= T::EPSILON * (a[p][p].abs() + a[q][q].abs()) {
+            break;
+        }
+        let theta = (a[q][q] - a[p][p]) / (T::TWO * a[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + T::ONE).sqrt());
+        let c = T::ONE / (t * t + T::ONE).sqrt();
+        let s = t * c;
+
+        let a_pq = a[p][q];
+        a[p][p] -= t * a_pq;
+        a[q][q] += t * a_pq;
+        a[p][q] = T::ZERO;
+        a[q][p] = T::ZERO;
+        let r = 3 - p - q;
+        let (a_rp, a_rq) = (a[r][p], a[r][q]);
+        a[r][p] = c * a_rp - s * a_rq;
+        a[p][r] = a[r][p];
+        a[r][q] = s * a_rp + c * a_rq;
+        a[q][r] = a[r][q];
+        for row in v.iter_mut() {
+            let (v_p, v_q) = (row[p], row[q]);
+            row[p] = c * v_p - s * v_q;
+            row[q] = s * v_p + c * v_q;
+        }
+    }
+    let column = |i: usize| normalize([v[0][i], v[1][i], v[2][i]]);
+    let (x, y) = (column(0), column(1));
+    [x, y, cross(x, y)]
+}
@@ -0,0 +1,278 @@
+use crate::batch;
+use crate::shapes::Aabb;
+use crate::simd::{Mask, Simd};
+use crate::wide_intersections;
+use rayon::prelude::*;
+use std::ops::Range;
+use treeculler::Frustum;
+
+/// Lane groups handed to each task, so every chunk but the last starts on a
+/// lane boundary.
+const CHUNK_LANES: usize = 64;
+
+/// Splits `0..count` into lane-aligned ranges and runs `f` on each across
+/// the thread pool, concatenating the results in chunk order.
+fn chunked<const LANES: usize, H: Send>(
+    count: usize,
+    f: impl Fn(Range<usize>, &mut Vec<H>) + Sync,
+) -> Vec<H> {
+    let chunk = CHUNK_LANES * LANES;
+    (0..count.div_ceil(chunk))
+        .into_par_iter()
+        .flat_map_iter(|c| {
+            let mut hits = vec![];
+            f(c * chunk..(c * chunk + chunk).min(count), &mut hits);
+            hits
+        })
+        .collect()
+}
+
+/// Parallel `batch::pairs`. Pairs come back grouped by chunk of `input_b`,
+/// which is deterministic but not the order of the serial version.
+pub fn pairs<const LANES: usize, const WIDTH: usize, const WIDTH2: usize>(
+    input_a: &[Vec<f32>; WIDTH],
+    input_b: &[Vec<f32>; WIDTH2],
+    test: impl Fn([Simd<f32, LANES>; WIDTH], [Simd<f32, LANES>; WIDTH2]) -> Mask<i32, LANES>
+        + Sync,
+) -> Vec<(usize, usize)> {
+    chunked::<LANES, _>(input_b[0].len(), |columns, hits| {
+        batch::pairs_in(input_a, input_b, columns, &test, |a, b| hits.push((a, b)))
+    })
+}
+
+pub fn aabb_aabb_pairs<const LANES: usize>(
+    a: &[Vec<f32>; 6],
+    b: &[Vec<f32>; 6],
+) -> Vec<(usize, usize)> {
+    pairs::<LANES, 6, 6>(a, b, wide_intersections::aabb_aabb)
+}
+
+pub fn aabb_sphere_pairs<const LANES: usize>(
+    aabbs: &[Vec<f32>; 6],
+    spheres: &[Vec<f32>; 4],
+) -> Vec<(usize, usize)> {
+    pairs::<LANES, 6, 4>(aabbs, spheres, wide_intersections::aabb_sphere)
+}
+
+pub fn sphere_sphere_pairs<const LANES: usize>(
+    a: &[Vec<f32>; 4],
+    b: &[Vec<f32>; 4],
+) -> Vec<(usize, usize)> {
+    pairs::<LANES, 4, 4>(a, b, wide_intersections::sphere_sphere)
+}
+
+/// Parallel `batch::filtered_pairs`, in the same order as `pairs`.
+pub fn filtered_pairs<
+    const LANES: usize,
+    const WIDTH: usize,
+    const WIDTH2: usize,
+>(
+    a: (&[Vec<f32>; WIDTH], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; WIDTH2], &[Vec<u32>; 2]),
+    test: impl Fn([Simd<f32, LANES>; WIDTH], [Simd<f32, LANES>; WIDTH2]) -> Mask<i32, LANES>
+        + Sync,
+) -> Vec<(usize, usize)> {
+    chunked::<LANES, _>(b.0[0].len(), |columns, hits| {
+        batch::filtered_pairs_in(a, b, columns, &test, |a, b| hits.push((a, b)))
+    })
+}
+
+pub fn aabb_aabb_filtered_pairs<const LANES: usize>(
+    a: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+) -> Vec<(usize, usize)> {
+    filtered_pairs::<LANES, 6, 6>(a, b, wide_intersections::aabb_aabb)
+}
+
+pub fn aabb_sphere_filtered_pairs<const LANES: usize>(
+    aabbs: (&[Vec<f32>; 6], &[Vec<u32>; 2]),
+    spheres: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+) -> Vec<(usize, usize)> {
+    filtered_pairs::<LANES, 6, 4>(aabbs, spheres, wide_intersections::aabb_sphere)
+}
+
+pub fn sphere_sphere_filtered_pairs<const LANES: usize>(
+    a: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+    b: (&[Vec<f32>; 4], &[Vec<u32>; 2]),
+) -> Vec<(usize, usize)> {
+    filtered_pairs::<LANES, 4, 4>(a, b, wide_intersections::sphere_sphere)
+}
+
+/// Parallel `batch::ray_aabb_pairs`, returning `(ray, aabb, time)` for each
+/// hit. The aabbs are split across threads, so a handful of rays against a
+/// large scene still uses every core.
+pub fn ray_aabb_pairs<const LANES: usize>(
+    rays: &[Vec<f32>; 6],
+    aabbs: &[Vec<f32>; 6],
+) -> Vec<(usize, usize, f32)> {
+    chunked::<LANES, _>(aabbs[0].len(), |columns, hits| {
+        batch::ray_aabb_pairs_in::<LANES>(rays, aabbs, columns, |r, a, t| {
+            hits.push((r, a, t))
+        })
+    })
+}
+
+/// Indices of the aabbs at least partly inside `frustum`, in ascending order.
+pub fn cull_frustum<const LANES: usize>(
+    aabbs: &[Vec<f32>; 6],
+    frustum: &Frustum<f32>,
+) -> Vec<usize> {
+    chunked::<LANES, _>(aabbs[0].len(), |columns, visible| {
+        visible.extend(columns.filter(|&i| {
+            let aabb = Aabb {
+                min: [aabbs[0][i], aabbs[1][i], aabbs[2][i]],
+                max: [aabbs[3][i], aabbs[4][i], aabbs[5][i]],
+            };
+            aabb.within_frustum(frustum)
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groups::CollisionGroups;
+    use crate::test_utils::{generate_soa_data, generate_test_data};
+    use rand::Rng;
+
+    #[test]
+    fn matches_batch() {
+        const LANES: usize = 8;
+        const RANGE: std::ops::Range<f32> = -40.0..40.0;
+        let mut rng = rand::thread_rng();
+        let (aabbs, _) = generate_test_data(1500, |_| {
+            [0.0, 0.0, 0.0, 2.0, 2.0, 2.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let spheres = generate_soa_data(1203, |_| {
+            [
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(0.1..2.0),
+            ]
+        });
+        let (rays, _) =
+            generate_test_data(5, |_| [0.0; 6].map(|_| rng.gen_range(RANGE)));
+
+        let sorted = |mut hits: Vec<(usize, usize)>| {
+            hits.sort_unstable();
+            hits
+        };
+        let mut expected = vec![];
+        batch::aabb_aabb_pairs::<LANES>(&aabbs, &aabbs, |a, b| {
+            expected.push((a, b))
+        });
+        assert!(!expected.is_empty());
+        assert_eq!(sorted(aabb_aabb_pairs::<LANES>(&aabbs, &aabbs)), expected);
+
+        let mut expected = vec![];
+        batch::aabb_sphere_pairs::<LANES>(&aabbs, &spheres, |a, b| {
+            expected.push((a, b))
+        });
+        assert!(!expected.is_empty());
+        assert_eq!(
+            sorted(aabb_sphere_pairs::<LANES>(&aabbs, &spheres)),
+            expected
+        );
+
+        let mut expected = vec![];
+        batch::ray_aabb_pairs::<LANES>(&rays, &aabbs, |r, a, t| {
+            expected.push((r, a, t))
+        });
+        let mut hits = ray_aabb_pairs::<LANES>(&rays, &aabbs);
+        hits.sort_unstable_by_key(|&(r, a, _)| (r, a));
+        assert!(!expected.is_empty());
+        assert_eq!(hits, expected);
+    }
+
+    #[test]
+    fn filtered_matches_batch() {
+        const LANES: usize = 8;
+        const RANGE: std::ops::Range<f32> = -30.0..30.0;
+        let mut rng = rand::thread_rng();
+        let (aabbs, _) = generate_test_data(1500, |_| {
+            [0.0, 0.0, 0.0, 2.0, 2.0, 2.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let spheres = generate_soa_data(1203, |_| {
+            [
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(0.1..2.0),
+            ]
+        });
+        let mut groups = |count: usize| -> [Vec<u32>; 2] {
+            let groups: Vec<CollisionGroups> = (0..count)
+                .map(|_| {
+                    CollisionGroups::new(
+                        1 << rng.gen_range(0..3),
+                        rng.gen_range(0..8),
+                    )
+                })
+                .collect();
+            [
+                groups.iter().map(|g| g.memberships).collect(),
+                groups.iter().map(|g| g.filter).collect(),
+            ]
+        };
+        let aabbs = (&aabbs, &groups(1500));
+        let spheres = (&spheres, &groups(1203));
+
+        let sorted = |mut hits: Vec<(usize, usize)>| {
+            hits.sort_unstable();
+            hits
+        };
+        let mut expected = vec![];
+        batch::aabb_aabb_filtered_pairs::<LANES>(aabbs, aabbs, |a, b| {
+            expected.push((a, b))
+        });
+        assert!(!expected.is_empty());
+        assert_eq!(
+            sorted(aabb_aabb_filtered_pairs::<LANES>(aabbs, aabbs)),
+            expected
+        );
+
+        let mut expected = vec![];
+        batch::aabb_sphere_filtered_pairs::<LANES>(aabbs, spheres, |a, b| {
+            expected.push((a, b))
+        });
+        assert!(!expected.is_empty());
+        assert_eq!(
+            sorted(aabb_sphere_filtered_pairs::<LANES>(aabbs, spheres)),
+            expected
+        );
+
+        let mut expected = vec![];
+        batch::sphere_sphere_filtered_pairs::<LANES>(spheres, spheres, |a, b| {
+            expected.push((a, b))
+        });
+        assert!(!expected.is_empty());
+        assert_eq!(
+            sorted(sphere_sphere_filtered_pairs::<LANES>(spheres, spheres)),
+            expected
+        );
+    }
+
+    #[test]
+    fn culls_frustum() {
+        let mut rng = rand::thread_rng();
+        let (aabbs, aos_aabbs) = generate_test_data(3000, |_| {
+            [0.0, 0.0, 0.0, 1.0, 1.0, 1.0].map(|v| v + rng.gen_range(-20.0..20.0))
+        });
+        // Orthographic view of the unit cube around the origin, scaled by 10
+        let scale = 0.1;
+        let frustum = Frustum::from_modelview_projection([
+            [scale, 0.0, 0.0, 0.0],
+            [0.0, scale, 0.0, 0.0],
+            [0.0, 0.0, scale, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let expected: Vec<usize> = (0..aos_aabbs.len())
+            .filter(|&i| {
+                Aabb::new(aos_aabbs[i][0], aos_aabbs[i][1]).within_frustum(&frustum)
+            })
+            .collect();
+        assert!(!expected.is_empty() && expected.len() < aos_aabbs.len());
+        assert_eq!(cull_frustum::<8>(&aabbs, &frustum), expected);
+    }
+}
@@ -1,34 +1,49 @@
+use crate::isometry::Isometry;
+use crate::math::{add, scale};
+use crate::real::Real;
 use crate::Vec3;
-use std::ops::Range;
+use core::ops::Range;
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct Ray {
-    pub origin: [f32; 3],
-    pub direction: [f32; 3],
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Ray<T: Real = f32> {
+    pub origin: [T; 3],
+    pub direction: [T; 3],
 }
 
-impl Ray {
-    pub fn new(origin: impl Into<Vec3>, direction: impl Into<Vec3>) -> Self {
+impl<T: Real> Ray<T> {
+    pub fn new(origin: impl Into<Vec3<T>>, direction: impl Into<Vec3<T>>) -> Self {
         let origin = origin.into().into();
         let direction = direction.into().into();
         Self { origin, direction }
     }
 
-    pub fn get_point<T: std::convert::From<[f32; 3]>>(&self, t: f32) -> T {
-        (self
-            .origin
-            .zip(self.direction.map(|d| d * t))
-            .map(|(a, b)| a + b))
-        .into()
+    pub fn transformed(&self, iso: &Isometry<T>) -> Self {
+        Self {
+            origin: iso.transform_point(self.origin),
+            direction: iso.transform_vector(self.direction),
+        }
     }
 
-    pub fn get_range<T: std::convert::From<[f32; 3]>>(
+    pub fn inverse_transformed(&self, iso: &Isometry<T>) -> Self {
+        Self {
+            origin: iso.inverse_transform_point(self.origin),
+            direction: iso.inverse_transform_vector(self.direction),
+        }
+    }
+
+    pub fn get_point<P: From<Vec3<T>>>(&self, t: T) -> P {
+        Vec3::from(add(self.origin, scale(self.direction, t))).into()
+    }
+
+    pub fn get_range<P: From<Vec3<T>>>(
         &self,
-        r: Range<f32>,
-        step: f32,
-    ) -> impl Iterator<Item = T> + '_ {
+        r: Range<T>,
+        step: T,
+    ) -> impl Iterator<Item = P> + '_ {
         let mut t = r.start;
-        std::iter::from_fn(move || {
+        core::iter::from_fn(move || {
             let point = self.get_point(t);
             t += step;
             if t > r.end {
@@ -39,3 +54,18 @@ impl Ray {
         })
     }
 }
+
+impl<T: Real, V: Into<Vec3<T>>> From<(V, V)> for Ray<T> {
+    fn from((origin, direction): (V, V)) -> Self {
+        Self::new(origin, direction)
+    }
+}
+
+impl<T: Real, V: From<Vec3<T>>> From<Ray<T>> for (V, V) {
+    fn from(ray: Ray<T>) -> Self {
+        (
+            Vec3::from(ray.origin).into(),
+            Vec3::from(ray.direction).into(),
+        )
+    }
+}
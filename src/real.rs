@@ -0,0 +1,64 @@
+use crate::simd::SimdElement;
+use core::cmp::Ordering;
+use core::fmt::Debug;
+use core::iter::{Product, Sum};
+use core::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
+use num_traits::Float;
+
+/// The scalar type shapes and queries are generic over, `f32` or `f64`.
+pub trait Real:
+    Float
+    + Debug
+    + Default
+    + Sum
+    + Product
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + SimdElement
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const TWO: Self;
+    const HALF: Self;
+    const PI: Self;
+    const EPSILON: Self;
+    const MAX: Self;
+    const MIN: Self;
+    const INFINITY: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+macro_rules! impl_real {
+    ($($t:ident),*) => {$(
+        impl Real for $t {
+            const ZERO: Self = 0.0;
+            const ONE: Self = 1.0;
+            const TWO: Self = 2.0;
+            const HALF: Self = 0.5;
+            const PI: Self = core::$t::consts::PI;
+            const EPSILON: Self = $t::EPSILON;
+            const MAX: Self = $t::MAX;
+            const MIN: Self = $t::MIN;
+            const INFINITY: Self = $t::INFINITY;
+
+            #[inline]
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            #[inline]
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                $t::total_cmp(self, other)
+            }
+        }
+    )*};
+}
+
+impl_real!(f32, f64);
@@ -0,0 +1,631 @@
+//! Signed distance fields, and shapes built by combining them. They stand
+//! apart from `Shape` and `World`, meeting other shapes only through the
+//! sphere and capsule contacts here.
+
+use crate::contacts::{add_cap_points, ContactManifold};
+use crate::intersections::closest_point_on_segment;
+use crate::math::*;
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::shapes::{Aabb, Capsule, Obb, Sphere};
+use crate::Vec3;
+use alloc::boxed::Box;
+
+/// Distance at which sphere tracing counts as touching the surface.
+const TOLERANCE: f64 = 1e-4;
+const MAX_STEPS: usize = 256;
+
+/// A solid given by the signed distance to its surface, negative inside.
+/// Combinators only promise a lower bound on the distance away from the
+/// surface, which is all sphere tracing needs.
+pub trait Sdf<T: Real = f32> {
+    fn distance(&self, point: [T; 3]) -> T;
+
+    /// The outward unit normal of the surface nearest `point`, estimated
+    /// from the distances around it unless a shape knows better.
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        estimated_normal(self, point)
+    }
+
+    /// Time of impact and outward surface normal of the first hit along
+    /// `ray` within `max_time`, found by sphere tracing. A ray starting
+    /// inside hits on its way out.
+    fn cast_ray(&self, ray: Ray<T>, max_time: T) -> Option<(T, [T; 3])> {
+        let speed = length(ray.direction);
+        if speed <= T::EPSILON {
+            return None;
+        }
+        let inside = self.distance(ray.origin) < T::ZERO;
+        let mut time = T::ZERO;
+        for _ in 0..MAX_STEPS {
+            let point: [T; 3] = ray.get_point(time);
+            let distance = self.distance(point);
+            // Only a blend overstating the depth inside can step across
+            if distance.abs() <= T::from_f64(TOLERANCE)
+                || (distance < T::ZERO) != inside
+            {
+                return Some((time, self.normal(point)));
+            }
+            time += distance.abs() / speed;
+            if time > max_time {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// How deep `point` is inside and the normal to push it out along, or
+    /// `None` outside.
+    fn penetration(&self, point: [T; 3]) -> Option<(T, [T; 3])> {
+        let distance = self.distance(point);
+        (distance < T::ZERO).then(|| (-distance, self.normal(point)))
+    }
+
+    fn union<B: Sdf<T>>(self, other: B) -> Union<Self, B>
+    where
+        Self: Sized,
+    {
+        Union { a: self, b: other }
+    }
+
+    fn intersection<B: Sdf<T>>(self, other: B) -> Intersection<Self, B>
+    where
+        Self: Sized,
+    {
+        Intersection { a: self, b: other }
+    }
+
+    /// `self` with `other` cut out of it.
+    fn subtraction<B: Sdf<T>>(self, other: B) -> Subtraction<Self, B>
+    where
+        Self: Sized,
+    {
+        Subtraction { a: self, b: other }
+    }
+
+    /// Like `union`, with the seam filleted over a width of `radius`.
+    fn smooth_union<B: Sdf<T>>(self, other: B, radius: T) -> SmoothUnion<Self, B, T>
+    where
+        Self: Sized,
+    {
+        assert!(radius > T::ZERO, "smoothing radius must be positive");
+        SmoothUnion {
+            a: self,
+            b: other,
+            radius,
+        }
+    }
+}
+
+/// Central differences over the corners of a tetrahedron, which takes four
+/// samples rather than six.
+fn estimated_normal<T: Real, S: Sdf<T> + ?Sized>(sdf: &S, point: [T; 3]) -> [T; 3] {
+    let largest = point.iter().fold(T::ONE, |m, v| m.max(v.abs()));
+    let step = T::EPSILON.sqrt() * largest;
+    let corners = [
+        [T::ONE, -T::ONE, -T::ONE],
+        [-T::ONE, -T::ONE, T::ONE],
+        [-T::ONE, T::ONE, -T::ONE],
+        [T::ONE, T::ONE, T::ONE],
+    ];
+    let gradient = corners.iter().fold([T::ZERO; 3], |sum, corner| {
+        let distance = sdf.distance(add(point, scale(*corner, step)));
+        add(sum, scale(*corner, distance))
+    });
+    unit_or_up(gradient)
+}
+
+fn unit_or_up<T: Real>(v: [T; 3]) -> [T; 3] {
+    let length = length(v);
+    if length > T::EPSILON {
+        scale(v, T::ONE / length)
+    } else {
+        [T::ZERO, T::ONE, T::ZERO]
+    }
+}
+
+/// The half-space of points behind the plane through `normal * offset`
+/// facing along `normal`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Plane<T: Real = f32> {
+    pub normal: [T; 3],
+    pub offset: T,
+}
+
+impl<T: Real> Plane<T> {
+    pub fn new(normal: impl Into<Vec3<T>>, offset: T) -> Self {
+        let normal: [T; 3] = normal.into().into();
+        assert!(length(normal) > T::ZERO, "plane normal must be non-zero");
+        Self {
+            normal: normalize(normal),
+            offset,
+        }
+    }
+
+    pub fn from_point_normal(
+        point: impl Into<Vec3<T>>,
+        normal: impl Into<Vec3<T>>,
+    ) -> Self {
+        let plane = Self::new(normal, T::ZERO);
+        Self {
+            offset: dot(point.into().into(), plane.normal),
+            ..plane
+        }
+    }
+}
+
+impl<T: Real> Sdf<T> for Plane<T> {
+    fn distance(&self, point: [T; 3]) -> T {
+        dot(point, self.normal) - self.offset
+    }
+
+    fn normal(&self, _: [T; 3]) -> [T; 3] {
+        self.normal
+    }
+}
+
+impl<T: Real> Sdf<T> for Sphere<T> {
+    fn distance(&self, point: [T; 3]) -> T {
+        length(sub(point, self.center)) - self.radius
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        unit_or_up(sub(point, self.center))
+    }
+}
+
+impl<T: Real> Sdf<T> for Capsule<T> {
+    fn distance(&self, point: [T; 3]) -> T {
+        let core = closest_point_on_segment(self.a, self.b, point);
+        length(sub(point, core)) - self.radius
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        unit_or_up(sub(point, closest_point_on_segment(self.a, self.b, point)))
+    }
+}
+
+/// How far `local`, relative to a box's centre, sits outside its faces
+/// along each axis.
+fn box_gaps<T: Real>(local: [T; 3], half_extents: [T; 3]) -> [T; 3] {
+    core::array::from_fn(|i| local[i].abs() - half_extents[i])
+}
+
+fn box_distance<T: Real>(local: [T; 3], half_extents: [T; 3]) -> T {
+    let gaps = box_gaps(local, half_extents);
+    let outside = length(gaps.map(|g| g.max(T::ZERO)));
+    let inside = gaps[0].max(gaps[1]).max(gaps[2]).min(T::ZERO);
+    outside + inside
+}
+
+/// The box normal in its own axes: towards the point outside, and through
+/// the nearest face inside.
+fn box_normal<T: Real>(local: [T; 3], half_extents: [T; 3]) -> [T; 3] {
+    let gaps = box_gaps(local, half_extents);
+    if gaps.iter().any(|g| *g > T::ZERO) {
+        return normalize(core::array::from_fn(|i| {
+            gaps[i].max(T::ZERO) * local[i].signum()
+        }));
+    }
+    let face = (0..3).max_by(|&i, &j| gaps[i].total_cmp(&gaps[j])).unwrap();
+    let mut normal = [T::ZERO; 3];
+    normal[face] = local[face].signum();
+    normal
+}
+
+impl<T: Real> Sdf<T> for Aabb<T> {
+    fn distance(&self, point: [T; 3]) -> T {
+        let local = sub(point, self.center());
+        box_distance(local, self.half_extents())
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        let local = sub(point, self.center());
+        box_normal(local, self.half_extents())
+    }
+}
+
+impl<T: Real> Sdf<T> for Obb<T> {
+    fn distance(&self, point: [T; 3]) -> T {
+        let offset = sub(point, self.center);
+        box_distance(self.axes.map(|axis| dot(offset, axis)), self.half_extents)
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        let offset = sub(point, self.center);
+        let local =
+            box_normal(self.axes.map(|axis| dot(offset, axis)), self.half_extents);
+        (0..3).fold([T::ZERO; 3], |normal, i| {
+            add(normal, scale(self.axes[i], local[i]))
+        })
+    }
+}
+
+impl<T: Real, S: Sdf<T> + ?Sized> Sdf<T> for &S {
+    fn distance(&self, point: [T; 3]) -> T {
+        (**self).distance(point)
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        (**self).normal(point)
+    }
+}
+
+impl<T: Real, S: Sdf<T> + ?Sized> Sdf<T> for Box<S> {
+    fn distance(&self, point: [T; 3]) -> T {
+        (**self).distance(point)
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        (**self).normal(point)
+    }
+}
+
+/// Points inside either of `a` and `b`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Union<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<T: Real, A: Sdf<T>, B: Sdf<T>> Sdf<T> for Union<A, B> {
+    fn distance(&self, point: [T; 3]) -> T {
+        self.a.distance(point).min(self.b.distance(point))
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        if self.a.distance(point) <= self.b.distance(point) {
+            self.a.normal(point)
+        } else {
+            self.b.normal(point)
+        }
+    }
+}
+
+/// Points inside both `a` and `b`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Intersection<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<T: Real, A: Sdf<T>, B: Sdf<T>> Sdf<T> for Intersection<A, B> {
+    fn distance(&self, point: [T; 3]) -> T {
+        self.a.distance(point).max(self.b.distance(point))
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        if self.a.distance(point) >= self.b.distance(point) {
+            self.a.normal(point)
+        } else {
+            self.b.normal(point)
+        }
+    }
+}
+
+/// Points inside `a` but not `b`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subtraction<A, B> {
+    pub a: A,
+    pub b: B,
+}
+
+impl<T: Real, A: Sdf<T>, B: Sdf<T>> Sdf<T> for Subtraction<A, B> {
+    fn distance(&self, point: [T; 3]) -> T {
+        self.a.distance(point).max(-self.b.distance(point))
+    }
+
+    fn normal(&self, point: [T; 3]) -> [T; 3] {
+        if self.a.distance(point) >= -self.b.distance(point) {
+            self.a.normal(point)
+        } else {
+            scale(self.b.normal(point), -T::ONE)
+        }
+    }
+}
+
+/// A union blending `a` and `b` into each other where their surfaces come
+/// within `radius`, using the polynomial smooth minimum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SmoothUnion<A, B, T: Real = f32> {
+    pub a: A,
+    pub b: B,
+    pub radius: T,
+}
+
+impl<T: Real, A: Sdf<T>, B: Sdf<T>> Sdf<T> for SmoothUnion<A, B, T> {
+    fn distance(&self, point: [T; 3]) -> T {
+        let (a, b) = (self.a.distance(point), self.b.distance(point));
+        let blend = (T::HALF + T::HALF * (b - a) / self.radius)
+            .max(T::ZERO)
+            .min(T::ONE);
+        b + (a - b) * blend - self.radius * blend * (T::ONE - blend)
+    }
+}
+
+/// The contact between the solid `sdf` and `sphere`, with the normal
+/// pointing out of the solid.
+pub fn sdf_sphere<T: Real, S: Sdf<T> + ?Sized>(
+    sdf: &S,
+    sphere: Sphere<T>,
+) -> Option<ContactManifold<T>> {
+    let depth = sphere.radius - sdf.distance(sphere.center);
+    if depth < T::ZERO {
+        return None;
+    }
+    let normal = sdf.normal(sphere.center);
+    let point = sub(
+        sphere.center,
+        scale(normal, sphere.radius - depth * T::HALF),
+    );
+    Some(ContactManifold::single(normal, point, depth))
+}
+
+/// The contact between the solid `sdf` and `capsule`, sampled at the ends and
+/// middle of its core. The deepest sample decides the normal and the others
+/// pushing the same way add their points, so a capsule lying on a surface
+/// rests along its length, while a bump under its core between the samples can
+/// be missed.
+pub fn sdf_capsule<T: Real, S: Sdf<T> + ?Sized>(
+    sdf: &S,
+    capsule: Capsule<T>,
+) -> Option<ContactManifold<T>> {
+    let middle = scale(add(capsule.a, capsule.b), T::HALF);
+    let samples = [capsule.a, middle, capsule.b]
+        .map(|center| sdf_sphere(sdf, Sphere::new(center, capsule.radius)));
+    let mut manifold = samples
+        .iter()
+        .flatten()
+        .max_by(|a, b| a.max_depth().total_cmp(&b.max_depth()))?
+        .clone();
+    add_cap_points(&mut manifold, capsule.radius, samples.into_iter().flatten());
+    Some(manifold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isometry::Isometry;
+    use crate::shapes::Shape;
+    use crate::test_utils::physics::close;
+    use alloc::vec;
+    use rand::Rng;
+
+    #[test]
+    fn primitives() {
+        let sphere = Sphere::new([1.0, 0.0, 0.0], 2.0);
+        assert!(close(sphere.distance([4.0, 0.0, 0.0]), 1.0, 1e-4));
+        assert!(close(sphere.distance([1.0, 0.5, 0.0]), -1.5, 1e-4));
+        let aabb = Aabb::new([-1.0, -2.0, -3.0], [1.0, 2.0, 3.0]);
+        assert!(close(aabb.distance([4.0, 6.0, 0.0]), 5.0, 1e-4));
+        assert!(close(aabb.distance([0.0, 1.5, 0.0]), -0.5, 1e-4));
+        assert_eq!(aabb.normal([0.0, 1.5, 0.0]), [0.0, 1.0, 0.0]);
+        let capsule = Capsule::new([0.0, -1.0, 0.0], [0.0, 1.0, 0.0], 0.5);
+        assert!(close(capsule.distance([0.0, 3.0, 0.0]), 1.5, 1e-4));
+        assert!(close(capsule.distance([2.0, 0.3, 0.0]), 1.5, 1e-4));
+        let plane = Plane::from_point_normal([0.0, 2.0, 0.0], [0.0, 3.0, 0.0]);
+        assert_eq!(plane, Plane::new([0.0, 1.0, 0.0], 2.0));
+        assert!(close(plane.distance([5.0, 0.5, -7.0]), -1.5, 1e-4));
+
+        // Turning a box is the same as turning the points the other way
+        let iso = Isometry::from_axis_angle([1.0, 2.0, 0.5], 0.8)
+            .with_translation([0.5, -1.0, 2.0]);
+        let obb =
+            Obb::from_rotation([0.5, -1.0, 2.0], aabb.half_extents(), iso.rotation);
+        let mut rng = rand::thread_rng();
+        let shapes: [&dyn Sdf; 5] = [&sphere, &aabb, &obb, &capsule, &plane];
+        for _ in 0..200 {
+            let point: [f32; 3] = core::array::from_fn(|_| rng.gen_range(-5.0..5.0));
+            let local: [f32; 3] = iso.inverse_transform_point(point);
+            assert!(close(obb.distance(point), aabb.distance(local), 1e-4));
+            let normal: [f32; 3] = iso.transform_vector(aabb.normal(local));
+            assert!(dot(obb.normal(point), normal) > 0.9999);
+            // The analytic normals match the estimate away from edges
+            for shape in shapes {
+                let (analytic, estimated) =
+                    (shape.normal(point), estimated_normal(shape, point));
+                if shape.distance(point) > 0.01 {
+                    assert!(dot(analytic, estimated) > 0.999, "{:?}", point);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn combinations() {
+        let a = Sphere::<f32>::new([-1.0, 0.0, 0.0], 1.5);
+        let b = Sphere::new([1.0, 0.0, 0.0], 1.5);
+        let union = a.union(b);
+        let intersection = a.intersection(b);
+        let subtraction = a.subtraction(b);
+        let smooth = a.smooth_union(b, 0.5);
+        for x in [-3.0, -2.0, -0.4, 0.0, 0.4, 1.0, 2.5] {
+            let point = [x, 0.0, 0.0];
+            let (da, db) = (a.distance(point), b.distance(point));
+            assert_eq!(union.distance(point), da.min(db));
+            assert_eq!(intersection.distance(point), da.max(db));
+            assert_eq!(subtraction.distance(point), da.max(-db));
+            assert!(smooth.distance(point) <= da.min(db));
+            if (da - db).abs() >= 0.5 {
+                assert!(close(smooth.distance(point), da.min(db), 1e-4));
+            }
+        }
+        // Over the seam the fillet fills in the notch between the spheres
+        let seam = [0.0, 1.2, 0.0];
+        assert!(union.distance(seam) > 0.0 && smooth.distance(seam) < 0.0);
+        assert_eq!(union.normal([-3.0, 0.0, 0.0]), [-1.0, 0.0, 0.0]);
+        assert_eq!(subtraction.normal([0.0, 0.0, 0.0]), [1.0, 0.0, 0.0]);
+        assert_eq!(intersection.normal([-0.4, 0.0, 0.0]), [-1.0, 0.0, 0.0]);
+
+        // Combinations nest, and take any mix of shapes by reference or boxed
+        let floor = Plane::new([0.0, 1.0, 0.0], 0.0);
+        let pillars: Vec<Box<dyn Sdf>> = vec![
+            Box::new(Capsule::new([-4.0, 0.0, 0.0], [-4.0, 3.0, 0.0], 0.5)),
+            Box::new(Aabb::new([3.5, 0.0, -0.5], [4.5, 3.0, 0.5])),
+        ];
+        let level = (&floor).union(&pillars[0]).union(&pillars[1]);
+        assert!(close(level.distance([0.0, 1.0, 0.0]), 1.0, 1e-4));
+        assert!(close(level.distance([-2.0, 1.0, 0.0]), 1.0, 1e-4));
+        assert!(close(level.distance([4.0, 4.0, 0.0]), 1.0, 1e-4));
+    }
+
+    #[test]
+    #[should_panic(expected = "smoothing radius must be positive")]
+    fn smooth_radius() {
+        Sphere::new([0.0; 3], 1.0).smooth_union(Sphere::new([1.0; 3], 1.0), 0.0);
+    }
+
+    #[test]
+    fn ray_casts() {
+        // Sphere tracing agrees with the exact casts of smooth shapes, for
+        // rays through points well inside them so they don't graze
+        let sphere = Sphere::new([0.5, 0.0, -0.5], 1.5);
+        let capsule = Capsule::new([-1.0, -1.0, 0.0], [1.0, 2.0, 0.5], 0.75);
+        let identity = Isometry::identity();
+        let mut rng = rand::thread_rng();
+        for _ in 0..400 {
+            let offset: [f32; 3] =
+                core::array::from_fn(|_| rng.gen_range(-0.3..0.3));
+            let (shape, sdf, target): (Shape, &dyn Sdf, [f32; 3]) =
+                if rng.gen_bool(0.5) {
+                    (sphere.into(), &sphere, add(sphere.center, offset))
+                } else {
+                    let along =
+                        add(capsule.a, scale(sub(capsule.b, capsule.a), rng.gen()));
+                    (capsule.into(), &capsule, add(along, offset))
+                };
+            let origin: [f32; 3] =
+                core::array::from_fn(|_| rng.gen_range(-4.0..4.0));
+            if sdf.distance(origin).abs() < 1e-2 {
+                continue;
+            }
+            let ray = Ray::new(origin, sub(target, origin));
+            let (expected, towards): (f32, [f32; 3]) =
+                shape.cast_ray(&identity, ray).unwrap();
+            let (time, normal) = sdf.cast_ray(ray, 100.0).unwrap();
+            // Tracing stops short of the surface by up to the tolerance
+            let slope = dot(normalize(ray.direction), towards).abs();
+            let tolerance = 2e-4 / (length(ray.direction) * slope);
+            assert!((time - expected).abs() < tolerance, "{:?}", ray);
+            assert!(dot(normal, towards) > 0.999, "{:?}", ray);
+        }
+        let past = Ray::new([0.5, 3.0, -0.5], [1.0, 0.0, 0.0]);
+        assert!(sphere.cast_ray(past, 100.0).is_none());
+        let away = Ray::new([0.5, 2.0, -0.5], [0.0, 1.0, 0.0]);
+        assert!(sphere.cast_ray(away, 100.0).is_none());
+        assert!(capsule
+            .cast_ray(Ray::new([3.0, 0.0, 0.0], [0.0, 0.0, 1.0]), 100.0)
+            .is_none());
+
+        // Boxes are hit on their faces, away from the edges where stopping
+        // short blends the normals of both faces
+        let obb = Obb::from_rotation(
+            [0.5, 0.0, 0.0],
+            [1.0, 2.0, 0.5],
+            Isometry::from_axis_angle([1.0, 1.0, 0.0], 0.6).rotation,
+        );
+        for _ in 0..200 {
+            let face = rng.gen_range(0..3);
+            let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+            let local: [f32; 3] = core::array::from_fn(|i| {
+                if i == face {
+                    side * (obb.half_extents[i] + rng.gen_range(0.5..3.0))
+                } else {
+                    obb.half_extents[i] * rng.gen_range(-0.8..0.8)
+                }
+            });
+            let origin =
+                (0..3).fold(obb.center, |p, i| add(p, scale(obb.axes[i], local[i])));
+            let mut direction = scale(obb.axes[face], -side);
+            for i in (0..3).filter(|i| *i != face) {
+                let drift = rng.gen_range(-0.1..0.1) * obb.half_extents[i];
+                direction =
+                    add(direction, scale(obb.axes[i], drift / local[face].abs()));
+            }
+            let ray = Ray::new(origin, direction);
+            let (expected, towards): (f32, [f32; 3]) =
+                Shape::Obb(obb).cast_ray(&identity, ray).unwrap();
+            let (time, normal) = obb.cast_ray(ray, 100.0).unwrap();
+            assert!((time - expected).abs() < 1e-3, "{:?}", ray);
+            assert!(dot(normal, towards) > 0.9999, "{:?}", ray);
+        }
+
+        // Through the hole in a slab with a sphere cut out of it
+        let slab = Aabb::new([-3.0, -0.5, -3.0], [3.0, 0.5, 3.0]);
+        let holed = slab.subtraction(Sphere::new([0.0; 3], 1.0));
+        let down = Ray::new([0.0, 5.0, 0.0], [0.0, -2.0, 0.0]);
+        assert!(holed.cast_ray(down, 10.0).is_none());
+        let beside = Ray::new([2.0, 5.0, 0.0], [0.0, -2.0, 0.0]);
+        let (time, normal) = holed.cast_ray(beside, 10.0).unwrap();
+        assert!(close(time, 2.25, 1e-4) && normal == [0.0, 1.0, 0.0]);
+        assert!(holed.cast_ray(beside, 2.0).is_none());
+        // Into the wall of the hole from inside it
+        let sideways = Ray::new([0.0; 3], [1.0, 0.0, 0.0]);
+        let (time, normal) = holed.cast_ray(sideways, 10.0).unwrap();
+        assert!(close(time, 1.0, 1e-4) && dot(normal, [-1.0, 0.0, 0.0]) > 0.999);
+        // From inside the slab out through the top
+        let up = Ray::new([2.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        let (time, normal) = holed.cast_ray(up, 10.0).unwrap();
+        assert!(close(time, 0.5, 1e-4) && normal == [0.0, 1.0, 0.0]);
+        assert!(holed
+            .cast_ray(Ray::new([2.0, 5.0, 0.0], [0.0; 3]), 10.0)
+            .is_none());
+    }
+
+    #[test]
+    fn penetration() {
+        let ground = Plane::new([0.0, 1.0, 0.0], 0.0)
+            .smooth_union(Sphere::new([0.0, 0.0, 0.0], 2.0), 0.5);
+        assert!(ground.penetration([5.0, 0.1, 0.0]).is_none());
+        let (depth, normal) = ground.penetration([5.0, -0.25, 0.0]).unwrap();
+        assert!(close(depth, 0.25, 1e-4));
+        assert!(dot(normal, [0.0, 1.0, 0.0]) > 0.999);
+        let (depth, normal) = ground.penetration([0.0, 1.5, 1.0]).unwrap();
+        let expected = 2.0 - length([0.0f32, 1.5, 1.0]);
+        assert!(close(depth, expected, 1e-4), "{}", depth);
+        assert!(dot(normal, normalize([0.0, 1.5, 1.0])) > 0.999);
+        // Pushing out by the depth brings the point back to the surface
+        let point = [0.5, 1.0, -0.5];
+        let (depth, normal) = ground.penetration(point).unwrap();
+        assert!(ground.distance(add(point, scale(normal, depth))).abs() < 1e-3);
+    }
+
+    #[test]
+    #[should_panic(expected = "plane normal must be non-zero")]
+    fn zero_normal() {
+        Plane::<f32>::from_point_normal([1.0; 3], [0.0; 3]);
+    }
+
+    #[test]
+    fn shape_contacts() {
+        let ground = Plane::new([0.0, 1.0, 0.0], 0.0)
+            .union(Sphere::new([0.0, 0.0, 0.0], 2.0));
+        let ball = Sphere::new([5.0, 0.4, 0.0], 0.5);
+        let manifold = sdf_sphere(&ground, ball).unwrap();
+        assert!(close(manifold.max_depth(), 0.1, 1e-4));
+        assert!(dot(manifold.normal, [0.0, 1.0, 0.0]) > 0.999);
+        assert!(close(manifold.points[0].point[1], -0.05, 1e-4));
+        assert!(sdf_sphere(&ground, Sphere::new([5.0, 0.6, 0.0], 0.5)).is_none());
+        let on_top = Sphere::new([0.0, 2.3, 0.0], 0.5);
+        let manifold = sdf_sphere(&ground, on_top).unwrap();
+        assert!(close(manifold.max_depth(), 0.2, 1e-4));
+
+        // Lying flat it rests along its length, standing on just its lower end
+        let lying = Capsule::new([4.0, 0.3, 0.0], [7.0, 0.3, 0.0], 0.5);
+        let manifold = sdf_capsule(&ground, lying).unwrap();
+        assert_eq!(manifold.points.len(), 3);
+        assert!(close(manifold.max_depth(), 0.2, 1e-4));
+        let standing = Capsule::new([5.0, 0.4, 0.0], [5.0, 2.4, 0.0], 0.5);
+        let manifold = sdf_capsule(&ground, standing).unwrap();
+        assert_eq!(manifold.points.len(), 1);
+        assert!(close(manifold.max_depth(), 0.1, 1e-4));
+        let above = Capsule::new([4.0, 0.6, 0.0], [7.0, 0.6, 0.0], 0.5);
+        assert!(sdf_capsule(&ground, above).is_none());
+    }
+}
@@ -1,52 +1,1077 @@
+use crate::heightfield::Heightfield;
+use crate::intersections;
+use crate::isometry::{Isometry, Quat};
+use crate::math::*;
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::voxels::VoxelGrid;
 use crate::Vec3;
+use alloc::vec::Vec;
 use treeculler::{BVol, Frustum};
 
-#[derive(Debug, Clone, Copy)]
-pub struct Aabb {
-    pub min: [f32; 3],
-    pub max: [f32; 3],
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Aabb<T: Real = f32> {
+    pub min: [T; 3],
+    pub max: [T; 3],
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Obb {
-    pub center: [f32; 3],
-    pub half_extents: [f32; 3],
-    pub axes: [[f32; 3]; 3],
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Obb<T: Real = f32> {
+    pub center: [T; 3],
+    pub half_extents: [T; 3],
+    pub axes: [[T; 3]; 3],
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Sphere {
-    pub center: [f32; 3],
-    pub radius: f32,
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Sphere<T: Real = f32> {
+    pub center: [T; 3],
+    pub radius: T,
 }
 
-impl Aabb {
-    pub fn new(min: impl Into<Vec3>, max: impl Into<Vec3>) -> Self {
+/// All points within `radius` of the segment from `a` to `b`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Capsule<T: Real = f32> {
+    pub a: [T; 3],
+    pub b: [T; 3],
+    pub radius: T,
+}
+
+/// A triangle whose normal faces the side its corners wind anticlockwise
+/// around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+pub struct Triangle<T: Real = f32> {
+    pub a: [T; 3],
+    pub b: [T; 3],
+    pub c: [T; 3],
+}
+
+impl<T: Real> Aabb<T> {
+    pub fn new(min: impl Into<Vec3<T>>, max: impl Into<Vec3<T>>) -> Self {
         Self {
             min: min.into().into(),
             max: max.into().into(),
         }
     }
 
-    pub fn with_offset(&self, offset: impl Into<Vec3>) -> Self {
+    pub fn from_points(
+        points: impl IntoIterator<Item = impl Into<Vec3<T>>>,
+    ) -> Option<Self> {
+        let mut points = points.into_iter().map(|p| p.into().into());
+        let first: [T; 3] = points.next()?;
+        Some(points.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |aabb, p| aabb.with_point(p),
+        ))
+    }
+
+    pub fn with_offset(&self, offset: impl Into<Vec3<T>>) -> Self {
         let offset = offset.into().into();
         Self {
-            min: self.min.zip(offset).map(|(a, b)| a + b),
-            max: self.max.zip(offset).map(|(a, b)| a + b),
+            min: add(self.min, offset),
+            max: add(self.max, offset),
         }
     }
 
-    pub fn within_frustum(&self, frustum: &Frustum<f32>) -> bool {
-        let aabb = treeculler::AABB::<f32>::new(self.min, self.max);
+    pub fn within_frustum(&self, frustum: &Frustum<T>) -> bool {
+        let aabb = treeculler::AABB::<T>::new(self.min, self.max);
         aabb.coherent_test_against_frustum(frustum, 0).0
     }
+
+    pub fn center(&self) -> [T; 3] {
+        scale(add(self.min, self.max), T::HALF)
+    }
+
+    pub fn half_extents(&self) -> [T; 3] {
+        scale(sub(self.max, self.min), T::HALF)
+    }
+
+    pub fn surface_area(&self) -> T {
+        let [x, y, z] = sub(self.max, self.min);
+        T::TWO * (x * y + y * z + z * x)
+    }
+
+    pub fn volume(&self) -> T {
+        let [x, y, z] = sub(self.max, self.min);
+        x * y * z
+    }
+
+    pub fn merged(&self, other: &Aabb<T>) -> Self {
+        Self {
+            min: min(self.min, other.min),
+            max: max(self.max, other.max),
+        }
+    }
+
+    pub fn intersection(&self, other: &Aabb<T>) -> Option<Self> {
+        let intersection = Self {
+            min: max(self.min, other.min),
+            max: min(self.max, other.max),
+        };
+        intersection
+            .min
+            .iter()
+            .zip(intersection.max)
+            .all(|(min, max)| *min <= max)
+            .then_some(intersection)
+    }
+
+    pub fn contains(&self, other: &Aabb<T>) -> bool {
+        (0..3).all(|i| self.min[i] <= other.min[i] && other.max[i] <= self.max[i])
+    }
+
+    pub fn expanded(&self, margin: T) -> Self {
+        Self {
+            min: self.min.map(|v| v - margin),
+            max: self.max.map(|v| v + margin),
+        }
+    }
+
+    pub fn with_point(&self, point: impl Into<Vec3<T>>) -> Self {
+        let point = point.into().into();
+        Self {
+            min: min(self.min, point),
+            max: max(self.max, point),
+        }
+    }
+
+    pub fn from_obb(obb: &Obb<T>) -> Self {
+        let extent = |i: usize| {
+            obb.axes
+                .iter()
+                .zip(obb.half_extents)
+                .map(|(axis, h)| axis[i].abs() * h)
+                .sum::<T>()
+        };
+        let extents = [extent(0), extent(1), extent(2)];
+        Self {
+            min: sub(obb.center, extents),
+            max: add(obb.center, extents),
+        }
+    }
+
+    pub fn from_sphere(sphere: &Sphere<T>) -> Self {
+        Self {
+            min: sphere.center.map(|c| c - sphere.radius),
+            max: sphere.center.map(|c| c + sphere.radius),
+        }
+    }
+
+    pub fn from_capsule(capsule: &Capsule<T>) -> Self {
+        Self {
+            min: min(capsule.a, capsule.b).map(|c| c - capsule.radius),
+            max: max(capsule.a, capsule.b).map(|c| c + capsule.radius),
+        }
+    }
+
+    /// The world space aabb enclosing this box after it's moved by `iso`.
+    pub fn transformed(&self, iso: &Isometry<T>) -> Self {
+        Self::from_obb(&Obb::from(*self).transformed(iso))
+    }
 }
 
-impl Sphere {
-    pub fn new(center: impl Into<Vec3>, radius: f32) -> Self {
+impl<T: Real> Obb<T> {
+    pub fn new(
+        center: impl Into<Vec3<T>>,
+        half_extents: impl Into<Vec3<T>>,
+        axes: [[T; 3]; 3],
+    ) -> Self {
         Self {
             center: center.into().into(),
+            half_extents: half_extents.into().into(),
+            axes,
+        }
+    }
+
+    pub fn from_rotation(
+        center: impl Into<Vec3<T>>,
+        half_extents: impl Into<Vec3<T>>,
+        rotation: impl Into<Quat<T>>,
+    ) -> Self {
+        let axes = Isometry::new([T::ZERO; 3], rotation).axes();
+        Self::new(center, half_extents, axes)
+    }
+
+    /// The rotation taking the x, y and z axes onto `axes`.
+    pub fn rotation<Q: From<Quat<T>>>(&self) -> Q {
+        Quat::from(axes_to_quat(self.axes)).into()
+    }
+
+    /// Fits a box to `points` along their principal axes.
+    pub fn from_points(
+        points: impl IntoIterator<Item = impl Into<Vec3<T>>>,
+    ) -> Option<Self> {
+        let points: Vec<[T; 3]> =
+            points.into_iter().map(|p| p.into().into()).collect();
+        if points.is_empty() {
+            return None;
+        }
+        let mean = scale(
+            points.iter().fold([T::ZERO; 3], |sum, p| add(sum, *p)),
+            T::ONE / T::from_f64(points.len() as f64),
+        );
+        let mut covariance = [[T::ZERO; 3]; 3];
+        for p in &points {
+            let d = sub(*p, mean);
+            for i in 0..3 {
+                for j in 0..3 {
+                    covariance[i][j] += d[i] * d[j];
+                }
+            }
+        }
+        Some(Self::fit_to_axes(
+            &points,
+            symmetric_eigenvectors(covariance),
+        ))
+    }
+
+    /// Like `from_points`, then searches for a tighter fit by rotating the
+    /// principal axes about each other.
+    pub fn from_points_refined(
+        points: impl IntoIterator<Item = impl Into<Vec3<T>>>,
+    ) -> Option<Self> {
+        const STEPS: usize = 16;
+        let steps = T::from_f64(STEPS as f64);
+        let points: Vec<[T; 3]> =
+            points.into_iter().map(|p| p.into().into()).collect();
+        let mut best = Self::from_points(points.iter().copied())?;
+        let mut step_angle = T::PI * T::HALF / steps;
+        for _ in 0..3 {
+            for axis in 0..3 {
+                let base = best;
+                for step in 0..STEPS {
+                    let angle =
+                        (T::from_f64(step as f64) - steps * T::HALF) * step_angle;
+                    let rotation = Isometry::from_axis_angle(base.axes[axis], angle);
+                    let axes = base.axes.map(|a| rotation.transform_vector(a));
+                    let candidate = Self::fit_to_axes(&points, axes);
+                    if candidate.volume() < best.volume() {
+                        best = candidate;
+                    }
+                }
+            }
+            step_angle /= steps * T::HALF;
+        }
+        Some(best)
+    }
+
+    fn fit_to_axes(points: &[[T; 3]], axes: [[T; 3]; 3]) -> Self {
+        let mut min_extent = [T::MAX; 3];
+        let mut max_extent = [T::MIN; 3];
+        for p in points {
+            let projected = axes.map(|axis| dot(*p, axis));
+            min_extent = min(min_extent, projected);
+            max_extent = max(max_extent, projected);
+        }
+        let local_center = scale(add(min_extent, max_extent), T::HALF);
+        let center = (0..3).fold([T::ZERO; 3], |center, i| {
+            add(center, scale(axes[i], local_center[i]))
+        });
+        Self {
+            center,
+            half_extents: scale(sub(max_extent, min_extent), T::HALF),
+            axes,
+        }
+    }
+
+    pub fn volume(&self) -> T {
+        self.half_extents.into_iter().product::<T>() * T::from_f64(8.0)
+    }
+
+    pub fn transformed(&self, iso: &Isometry<T>) -> Self {
+        Self {
+            center: iso.transform_point(self.center),
+            half_extents: self.half_extents,
+            axes: self.axes.map(|axis| iso.transform_vector(axis)),
+        }
+    }
+}
+
+impl<T: Real> From<Aabb<T>> for Obb<T> {
+    fn from(aabb: Aabb<T>) -> Self {
+        Self {
+            center: aabb.center(),
+            half_extents: aabb.half_extents(),
+            axes: identity(),
+        }
+    }
+}
+
+impl<T: Real> Sphere<T> {
+    pub fn new(center: impl Into<Vec3<T>>, radius: T) -> Self {
+        Self {
+            center: center.into().into(),
+            radius,
+        }
+    }
+
+    /// The minimal bounding sphere of `points`, using Welzl's algorithm.
+    pub fn from_points(
+        points: impl IntoIterator<Item = impl Into<Vec3<T>>>,
+    ) -> Option<Self> {
+        let mut points: Vec<[T; 3]> =
+            points.into_iter().map(|p| p.into().into()).collect();
+        if points.is_empty() {
+            return None;
+        }
+        // Expected linear time relies on a random order, a fixed seed keeps
+        // the result reproducible
+        let mut seed = 0x9E3779B9u32;
+        for i in (1..points.len()).rev() {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            points.swap(i, seed as usize % (i + 1));
+        }
+
+        let contains = |sphere: &Sphere<T>, p: [T; 3]| {
+            let tolerance = T::from_f64(1e-5) * sphere.radius.max(T::ONE);
+            length(sub(p, sphere.center)) <= sphere.radius + tolerance
+        };
+        let mut sphere = Sphere {
+            center: points[0],
+            radius: T::ZERO,
+        };
+        for i in 1..points.len() {
+            if contains(&sphere, points[i]) {
+                continue;
+            }
+            sphere = Sphere {
+                center: points[i],
+                radius: T::ZERO,
+            };
+            for j in 0..i {
+                if contains(&sphere, points[j]) {
+                    continue;
+                }
+                sphere = sphere_from_boundary(&[points[i], points[j]]);
+                for k in 0..j {
+                    if contains(&sphere, points[k]) {
+                        continue;
+                    }
+                    sphere =
+                        sphere_from_boundary(&[points[i], points[j], points[k]]);
+                    for l in 0..k {
+                        if contains(&sphere, points[l]) {
+                            continue;
+                        }
+                        sphere = sphere_from_boundary(&[
+                            points[i], points[j], points[k], points[l],
+                        ]);
+                    }
+                }
+            }
+        }
+        Some(sphere)
+    }
+
+    pub fn from_aabb(aabb: &Aabb<T>) -> Self {
+        Self {
+            center: aabb.center(),
+            radius: length(aabb.half_extents()),
+        }
+    }
+
+    pub fn transformed(&self, iso: &Isometry<T>) -> Self {
+        Self {
+            center: iso.transform_point(self.center),
+            radius: self.radius,
+        }
+    }
+
+    pub fn surface_area(&self) -> T {
+        T::from_f64(4.0) * T::PI * self.radius * self.radius
+    }
+
+    pub fn volume(&self) -> T {
+        let cube = self.radius * self.radius * self.radius;
+        T::from_f64(4.0) / T::from_f64(3.0) * T::PI * cube
+    }
+
+    pub fn contains(&self, other: &Sphere<T>) -> bool {
+        length(sub(other.center, self.center)) + other.radius <= self.radius
+    }
+
+    pub fn merged(&self, other: &Sphere<T>) -> Self {
+        let offset = sub(other.center, self.center);
+        let distance = length(offset);
+        if distance + other.radius <= self.radius {
+            return *self;
+        }
+        if distance + self.radius <= other.radius {
+            return *other;
+        }
+        let radius = (distance + self.radius + other.radius) * T::HALF;
+        let t = (radius - self.radius) / distance;
+        Self {
+            center: add(self.center, scale(offset, t)),
+            radius,
+        }
+    }
+}
+
+impl<T: Real> Capsule<T> {
+    pub fn new(a: impl Into<Vec3<T>>, b: impl Into<Vec3<T>>, radius: T) -> Self {
+        Self {
+            a: a.into().into(),
+            b: b.into().into(),
             radius,
         }
     }
+
+    /// A capsule standing on the y axis, centred on the origin, with a total
+    /// height of `2 * (half_height + radius)`.
+    pub fn upright(half_height: T, radius: T) -> Self {
+        Self::new(
+            [T::ZERO, -half_height, T::ZERO],
+            [T::ZERO, half_height, T::ZERO],
+            radius,
+        )
+    }
+
+    pub fn center(&self) -> [T; 3] {
+        scale(add(self.a, self.b), T::HALF)
+    }
+
+    pub fn transformed(&self, iso: &Isometry<T>) -> Self {
+        Self {
+            a: iso.transform_point(self.a),
+            b: iso.transform_point(self.b),
+            radius: self.radius,
+        }
+    }
+
+    pub fn volume(&self) -> T {
+        let cylinder =
+            T::PI * self.radius * self.radius * length(sub(self.b, self.a));
+        let cube = self.radius * self.radius * self.radius;
+        cylinder + T::from_f64(4.0) / T::from_f64(3.0) * T::PI * cube
+    }
+}
+
+impl<T: Real> Triangle<T> {
+    pub fn new(
+        a: impl Into<Vec3<T>>,
+        b: impl Into<Vec3<T>>,
+        c: impl Into<Vec3<T>>,
+    ) -> Self {
+        Self {
+            a: a.into().into(),
+            b: b.into().into(),
+            c: c.into().into(),
+        }
+    }
+
+    pub fn normal<V: From<Vec3<T>>>(&self) -> V {
+        let normal = cross(sub(self.b, self.a), sub(self.c, self.a));
+        Vec3::from(normalize(normal)).into()
+    }
+
+    pub fn vertices(&self) -> [[T; 3]; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    pub fn transformed(&self, iso: &Isometry<T>) -> Self {
+        Self {
+            a: iso.transform_point(self.a),
+            b: iso.transform_point(self.b),
+            c: iso.transform_point(self.c),
+        }
+    }
+}
+
+/// A collision shape in its own local space, positioned by an `Isometry`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape<T: Real = f32> {
+    Aabb(Aabb<T>),
+    Obb(Obb<T>),
+    Sphere(Sphere<T>),
+    Capsule(Capsule<T>),
+    Heightfield(Heightfield<T>),
+    VoxelGrid(VoxelGrid<T>),
+}
+
+/// A shape moved into world space. Boxes become obbs once rotated, while
+/// heightfields and voxel grids are too big to copy and keep their
+/// placement alongside.
+#[derive(Clone, Copy)]
+pub(crate) enum Placed<'a, T: Real> {
+    Obb(Obb<T>),
+    Sphere(Sphere<T>),
+    Capsule(Capsule<T>),
+    Heightfield(&'a Heightfield<T>, Isometry<T>),
+    VoxelGrid(&'a VoxelGrid<T>, Isometry<T>),
+}
+
+impl<T: Real> Shape<T> {
+    pub(crate) fn placed(&self, iso: &Isometry<T>) -> Placed<'_, T> {
+        match self {
+            Shape::Aabb(aabb) => Placed::Obb(Obb::from(*aabb).transformed(iso)),
+            Shape::Obb(obb) => Placed::Obb(obb.transformed(iso)),
+            Shape::Sphere(sphere) => Placed::Sphere(sphere.transformed(iso)),
+            Shape::Capsule(capsule) => Placed::Capsule(capsule.transformed(iso)),
+            Shape::Heightfield(field) => Placed::Heightfield(field, *iso),
+            Shape::VoxelGrid(grid) => Placed::VoxelGrid(grid, *iso),
+        }
+    }
+
+    /// The world space aabb enclosing the shape after it's moved by `iso`.
+    pub fn aabb(&self, iso: &Isometry<T>) -> Aabb<T> {
+        match self.placed(iso) {
+            Placed::Obb(obb) => Aabb::from_obb(&obb),
+            Placed::Sphere(sphere) => Aabb::from_sphere(&sphere),
+            Placed::Capsule(capsule) => Aabb::from_capsule(&capsule),
+            Placed::Heightfield(field, iso) => field.local_aabb().transformed(&iso),
+            Placed::VoxelGrid(grid, iso) => grid.local_aabb().transformed(&iso),
+        }
+    }
+
+    /// Whether the shapes overlap. Heightfields are solid below their surface
+    /// and pairs of heightfields and voxel grids are walked part by part.
+    pub fn intersects(
+        &self,
+        iso: &Isometry<T>,
+        other: &Shape<T>,
+        other_iso: &Isometry<T>,
+    ) -> bool {
+        use Placed::*;
+        match (self.placed(iso), other.placed(other_iso)) {
+            (Heightfield(a, a_iso), Heightfield(b, b_iso)) => {
+                surface_reaches(a, &a_iso, b, &b_iso)
+                    || surface_reaches(b, &b_iso, a, &a_iso)
+            }
+            // Voxel grids go first so a grid against a heightfield is walked
+            // cell by cell
+            (VoxelGrid(grid, grid_iso), _) => {
+                voxels_intersect(grid, &grid_iso, other, other_iso)
+            }
+            (_, VoxelGrid(grid, grid_iso)) => {
+                voxels_intersect(grid, &grid_iso, self, iso)
+            }
+            (Heightfield(field, field_iso), _) => {
+                heightfield_intersects(field, &field_iso, other, other_iso)
+            }
+            (_, Heightfield(field, field_iso)) => {
+                heightfield_intersects(field, &field_iso, self, iso)
+            }
+            (a, b) => convex_intersect(a, b),
+        }
+    }
+
+    pub fn contains_point(
+        &self,
+        iso: &Isometry<T>,
+        point: impl Into<Vec3<T>>,
+    ) -> bool {
+        match self.placed(iso) {
+            Placed::Obb(obb) => intersections::obb_point(obb, point),
+            Placed::Sphere(sphere) => intersections::sphere_point(sphere, point),
+            Placed::Capsule(capsule) => intersections::capsule_point(capsule, point),
+            Placed::Heightfield(field, iso) => {
+                field.contains_point(iso.inverse_transform_point::<[T; 3]>(point))
+            }
+            Placed::VoxelGrid(grid, iso) => {
+                let point: [T; 3] = iso.inverse_transform_point(point);
+                grid.is_occupied(grid.cell_at(point))
+            }
+        }
+    }
+
+    /// Time of impact and outward surface normal of the first hit along
+    /// `ray`. A ray starting inside the shape hits on its way out.
+    pub fn cast_ray<V: From<Vec3<T>>>(
+        &self,
+        iso: &Isometry<T>,
+        ray: Ray<T>,
+    ) -> Option<(T, V)> {
+        let placed = self.placed(iso);
+        let time = match placed {
+            Placed::Obb(obb) => intersections::ray_obb_time(ray, obb),
+            Placed::Sphere(sphere) => intersections::ray_sphere_time(ray, sphere),
+            Placed::Capsule(capsule) => {
+                intersections::ray_capsule_time(ray, capsule)
+            }
+            Placed::Heightfield(field, iso) => {
+                let (time, normal) =
+                    field.cast_ray(ray.inverse_transformed(&iso))?;
+                let normal: [T; 3] = iso.transform_vector(normal);
+                return Some((time, Vec3::from(normal).into()));
+            }
+            Placed::VoxelGrid(grid, iso) => {
+                let hit = grid.cast_ray(ray.inverse_transformed(&iso))?;
+                let normal: [T; 3] = iso.transform_vector(hit.normal);
+                return Some((hit.time, Vec3::from(normal).into()));
+            }
+        };
+        if time < T::ZERO {
+            return None;
+        }
+        let point: [T; 3] = ray.get_point(time);
+        let normal = match placed {
+            Placed::Obb(obb) => {
+                let offset = sub(point, obb.center);
+                let face = (0..3)
+                    .max_by(|&a, &b| {
+                        let depth = |i: usize| {
+                            dot(offset, obb.axes[i]).abs() / obb.half_extents[i]
+                        };
+                        depth(a).total_cmp(&depth(b))
+                    })
+                    .unwrap();
+                let side = dot(offset, obb.axes[face]).signum();
+                scale(obb.axes[face], side)
+            }
+            Placed::Sphere(sphere) => normalize(sub(point, sphere.center)),
+            Placed::Capsule(capsule) => {
+                let core = intersections::closest_point_on_segment(
+                    capsule.a, capsule.b, point,
+                );
+                normalize(sub(point, core))
+            }
+            Placed::Heightfield(..) | Placed::VoxelGrid(..) => {
+                unreachable!("returned above")
+            }
+        };
+        Some((time, Vec3::from(normal).into()))
+    }
+}
+
+impl<T: Real> From<Aabb<T>> for Shape<T> {
+    fn from(aabb: Aabb<T>) -> Self {
+        Shape::Aabb(aabb)
+    }
+}
+
+impl<T: Real> From<Obb<T>> for Shape<T> {
+    fn from(obb: Obb<T>) -> Self {
+        Shape::Obb(obb)
+    }
+}
+
+impl<T: Real> From<Sphere<T>> for Shape<T> {
+    fn from(sphere: Sphere<T>) -> Self {
+        Shape::Sphere(sphere)
+    }
+}
+
+impl<T: Real> From<Capsule<T>> for Shape<T> {
+    fn from(capsule: Capsule<T>) -> Self {
+        Shape::Capsule(capsule)
+    }
+}
+
+impl<T: Real> From<Heightfield<T>> for Shape<T> {
+    fn from(field: Heightfield<T>) -> Self {
+        Shape::Heightfield(field)
+    }
+}
+
+impl<T: Real> From<VoxelGrid<T>> for Shape<T> {
+    fn from(grid: VoxelGrid<T>) -> Self {
+        Shape::VoxelGrid(grid)
+    }
+}
+
+/// Whether two convex shapes overlap.
+fn convex_intersect<T: Real>(a: Placed<'_, T>, b: Placed<'_, T>) -> bool {
+    use Placed::*;
+    match (a, b) {
+        (Obb(a), Obb(b)) => intersections::obb_obb(a, b),
+        (Obb(obb), Sphere(sphere)) | (Sphere(sphere), Obb(obb)) => {
+            intersections::obb_sphere(obb, sphere)
+        }
+        (Sphere(a), Sphere(b)) => intersections::sphere_sphere(a, b),
+        (Capsule(capsule), Obb(obb)) | (Obb(obb), Capsule(capsule)) => {
+            intersections::capsule_obb(capsule, obb)
+        }
+        (Capsule(capsule), Sphere(sphere)) | (Sphere(sphere), Capsule(capsule)) => {
+            intersections::capsule_sphere(capsule, sphere)
+        }
+        (Capsule(a), Capsule(b)) => intersections::capsule_capsule(a, b),
+        (Heightfield(..) | VoxelGrid(..), _)
+        | (_, Heightfield(..) | VoxelGrid(..)) => {
+            unreachable!("compound shapes are split into parts")
+        }
+    }
+}
+
+/// Whether `shape` overlaps any occupied cell of `grid`.
+fn voxels_intersect<T: Real>(
+    grid: &VoxelGrid<T>,
+    grid_iso: &Isometry<T>,
+    shape: &Shape<T>,
+    iso: &Isometry<T>,
+) -> bool {
+    let local = grid_iso.inverse() * *iso;
+    let placed = shape.placed(&local);
+    grid.overlapping(&shape.aabb(&local)).any(|cell| {
+        let cell = Obb::from(grid.cell_aabb(cell));
+        match placed {
+            Placed::Heightfield(..) | Placed::VoxelGrid(..) => {
+                Shape::Obb(cell).intersects(&Isometry::identity(), shape, &local)
+            }
+            _ => convex_intersect(Placed::Obb(cell), placed),
+        }
+    })
+}
+
+/// Whether a triangle of `a` crosses the surface of `b` or dips below it.
+fn surface_reaches<T: Real>(
+    a: &Heightfield<T>,
+    a_iso: &Isometry<T>,
+    b: &Heightfield<T>,
+    b_iso: &Isometry<T>,
+) -> bool {
+    let to_b = b_iso.inverse() * *a_iso;
+    let bounds = b.local_aabb().transformed(&to_b.inverse());
+    a.triangles(&bounds).any(|triangle| {
+        let triangle = triangle.transformed(&to_b);
+        let [p, q, r] = triangle.vertices();
+        let centroid = scale(add(add(p, q), r), T::ONE / T::from_f64(3.0));
+        let around = Aabb::from_points([p, q, r]).unwrap();
+        b.contains_point(centroid)
+            || b.triangles(&around)
+                .any(|other| intersections::triangle_triangle(triangle, other))
+    })
+}
+
+/// Whether `shape` touches any triangle of `field` or sits below its
+/// surface, which counts as solid.
+fn heightfield_intersects<T: Real>(
+    field: &Heightfield<T>,
+    field_iso: &Isometry<T>,
+    shape: &Shape<T>,
+    iso: &Isometry<T>,
+) -> bool {
+    let local = field_iso.inverse() * *iso;
+    let bounds = shape.aabb(&local);
+    let placed = shape.placed(&local);
+    field.contains_point(bounds.center())
+        || field.triangles(&bounds).any(|triangle| match placed {
+            Placed::Obb(obb) => intersections::triangle_obb(triangle, obb),
+            Placed::Sphere(sphere) => {
+                intersections::triangle_sphere(triangle, sphere)
+            }
+            Placed::Capsule(capsule) => {
+                intersections::triangle_capsule(triangle, capsule)
+            }
+            Placed::Heightfield(..) | Placed::VoxelGrid(..) => {
+                unreachable!("compound shapes are split into parts")
+            }
+        })
+}
+
+impl<T: Real, V: Into<Vec3<T>>> From<(V, V)> for Aabb<T> {
+    fn from((min, max): (V, V)) -> Self {
+        Self::new(min, max)
+    }
+}
+
+impl<T: Real, V: From<Vec3<T>>> From<Aabb<T>> for (V, V) {
+    fn from(aabb: Aabb<T>) -> Self {
+        (Vec3::from(aabb.min).into(), Vec3::from(aabb.max).into())
+    }
+}
+
+impl<T: Real, V: Into<Vec3<T>>, Q: Into<Quat<T>>> From<(V, V, Q)> for Obb<T> {
+    fn from((center, half_extents, rotation): (V, V, Q)) -> Self {
+        Self::from_rotation(center, half_extents, rotation)
+    }
+}
+
+impl<T: Real, V: From<Vec3<T>>, Q: From<Quat<T>>> From<Obb<T>> for (V, V, Q) {
+    fn from(obb: Obb<T>) -> Self {
+        (
+            Vec3::from(obb.center).into(),
+            Vec3::from(obb.half_extents).into(),
+            obb.rotation(),
+        )
+    }
+}
+
+impl<T: Real, V: Into<Vec3<T>>> From<(V, T)> for Sphere<T> {
+    fn from((center, radius): (V, T)) -> Self {
+        Self::new(center, radius)
+    }
+}
+
+impl<T: Real, V: From<Vec3<T>>> From<Sphere<T>> for (V, T) {
+    fn from(sphere: Sphere<T>) -> Self {
+        (Vec3::from(sphere.center).into(), sphere.radius)
+    }
+}
+
+/// The smallest sphere with all of `points` (up to four) on its surface,
+/// falling back to the smallest enclosing sphere of a subset when the
+/// points are degenerate.
+fn sphere_from_boundary<T: Real>(points: &[[T; 3]]) -> Sphere<T> {
+    let epsilon = T::from_f64(1e-12);
+    let a = points[0];
+    let circumsphere = match *points {
+        [_, b] => Some(add(a, scale(sub(b, a), T::HALF))),
+        [_, b, c] => {
+            let (ab, ac) = (sub(b, a), sub(c, a));
+            let n = cross(ab, ac);
+            let denominator = T::TWO * dot(n, n);
+            (denominator > epsilon).then(|| {
+                let offset = add(
+                    scale(cross(n, ab), dot(ac, ac)),
+                    scale(cross(ac, n), dot(ab, ab)),
+                );
+                add(a, scale(offset, T::ONE / denominator))
+            })
+        }
+        [_, b, c, d] => {
+            let (ab, ac, ad) = (sub(b, a), sub(c, a), sub(d, a));
+            let denominator = T::TWO * dot(ab, cross(ac, ad));
+            (denominator.abs() > epsilon).then(|| {
+                let offset = add(
+                    add(
+                        scale(cross(ac, ad), dot(ab, ab)),
+                        scale(cross(ad, ab), dot(ac, ac)),
+                    ),
+                    scale(cross(ab, ac), dot(ad, ad)),
+                );
+                add(a, scale(offset, T::ONE / denominator))
+            })
+        }
+        _ => Some(a),
+    };
+    if let Some(center) = circumsphere {
+        return Sphere {
+            center,
+            radius: length(sub(a, center)),
+        };
+    }
+
+    let mut best: Option<Sphere<T>> = None;
+    for skip in 0..points.len() {
+        let subset: Vec<[T; 3]> = points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, p)| (i != skip).then_some(*p))
+            .collect();
+        let candidate = sphere_from_boundary(&subset);
+        let encloses = points.iter().all(|p| {
+            length(sub(*p, candidate.center))
+                <= candidate.radius * (T::ONE + T::from_f64(1e-5))
+        });
+        if encloses && best.is_none_or(|best| candidate.radius < best.radius) {
+            best = Some(candidate);
+        }
+    }
+    best.unwrap_or(Sphere {
+        center: a,
+        radius: T::ZERO,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aabb_operations() {
+        let a = Aabb::new([0.0, 0.0, 0.0], [2.0, 2.0, 2.0]);
+        let b = Aabb::new([1.0, -1.0, 1.0], [3.0, 1.0, 4.0]);
+        let merged = a.merged(&b);
+        assert_eq!(merged.min, [0.0, -1.0, 0.0]);
+        assert_eq!(merged.max, [3.0, 2.0, 4.0]);
+        assert!(merged.contains(&a) && merged.contains(&b));
+        assert!(!a.contains(&b));
+
+        let intersection = a.intersection(&b).unwrap();
+        assert_eq!(intersection.min, [1.0, 0.0, 1.0]);
+        assert_eq!(intersection.max, [2.0, 1.0, 2.0]);
+        assert!(a.intersection(&a.with_offset([5.0, 0.0, 0.0])).is_none());
+
+        assert_eq!(a.center(), [1.0, 1.0, 1.0]);
+        assert_eq!(a.half_extents(), [1.0, 1.0, 1.0]);
+        assert_eq!(a.surface_area(), 24.0);
+        assert_eq!(a.volume(), 8.0);
+        assert_eq!(a.expanded(0.5).min, [-0.5, -0.5, -0.5]);
+        assert_eq!(a.with_point([-1.0, 5.0, 1.0]).max, [2.0, 5.0, 2.0]);
+    }
+
+    #[test]
+    fn bounding_volumes() {
+        let sphere = Sphere::new([1.0, 2.0, 3.0], 0.5);
+        let aabb = Aabb::from_sphere(&sphere);
+        assert_eq!(aabb.min, [0.5, 1.5, 2.5]);
+        assert_eq!(aabb.max, [1.5, 2.5, 3.5]);
+
+        let obb = Obb::from(Aabb::new([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]))
+            .transformed(&Isometry::from_axis_angle(
+                [0.0, 0.0, 1.0],
+                std::f32::consts::FRAC_PI_4,
+            ));
+        let aabb = Aabb::from_obb(&obb);
+        assert!((aabb.max[0] - 2.0f32.sqrt()).abs() < 1e-5);
+        assert!((aabb.max[2] - 1.0).abs() < 1e-5);
+
+        let sphere = Sphere::from_aabb(&Aabb::new([0.0, 0.0, 0.0], [2.0, 2.0, 2.0]));
+        assert_eq!(sphere.center, [1.0, 1.0, 1.0]);
+        assert!((sphere.radius - 3.0f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sphere_merging() {
+        let a = Sphere::new([0.0, 0.0, 0.0], 1.0);
+        let b = Sphere::new([4.0, 0.0, 0.0], 1.0);
+        let merged = a.merged(&b);
+        assert_eq!(merged.center, [2.0, 0.0, 0.0]);
+        assert_eq!(merged.radius, 3.0);
+        assert!(merged.contains(&a) && merged.contains(&b));
+
+        let inner = Sphere::new([0.5, 0.0, 0.0], 0.25);
+        assert_eq!(a.merged(&inner).radius, 1.0);
+        assert_eq!(inner.merged(&a).radius, 1.0);
+    }
+
+    #[test]
+    fn fit_to_points() {
+        use crate::intersections::{obb_point, sphere_point};
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let iso = Isometry::from_axis_angle([1.0, 2.0, -0.5], 0.7)
+            .with_translation([3.0, -2.0, 1.0]);
+        let mut points: Vec<[f32; 3]> = (0..500)
+            .map(|_| {
+                let local = [
+                    rng.gen_range(-4.0..4.0),
+                    rng.gen_range(-2.0..2.0),
+                    rng.gen_range(-0.5..0.5),
+                ];
+                iso.transform_point(local)
+            })
+            .collect();
+        for corner in 0..8 {
+            let sign = |bit: usize| if corner & bit == 0 { -1.0 } else { 1.0 };
+            points.push(iso.transform_point([
+                4.0 * sign(1),
+                2.0 * sign(2),
+                0.5 * sign(4),
+            ]));
+        }
+
+        let aabb = Aabb::from_points(points.iter().copied()).unwrap();
+        assert!(points
+            .iter()
+            .all(|p| crate::intersections::aabb_point(aabb, *p)));
+
+        let obb = Obb::from_points_refined(points.iter().copied()).unwrap();
+        let grown = Obb {
+            half_extents: obb.half_extents.map(|h| h + 1e-3),
+            ..obb
+        };
+        assert!(points.iter().all(|p| obb_point(grown, *p)));
+        assert!((obb.volume() - 32.0).abs() < 0.5, "{}", obb.volume());
+
+        let sphere = Sphere::from_points(points.iter().copied()).unwrap();
+        let grown = Sphere::new(sphere.center, sphere.radius + 1e-3);
+        assert!(points.iter().all(|p| sphere_point(grown, *p)));
+        let expected = length([4.0, 2.0, 0.5]);
+        assert!((sphere.radius - expected).abs() < 1e-3, "{}", sphere.radius);
+
+        assert!(Sphere::from_points(Vec::<[f32; 3]>::new()).is_none());
+        let collinear = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [3.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0],
+        ];
+        let sphere = Sphere::from_points(collinear).unwrap();
+        assert_eq!(sphere.center, [1.5, 0.0, 0.0]);
+        assert_eq!(sphere.radius, 1.5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        use crate::isometry::Isometry;
+        use crate::ray::Ray;
+        use serde::{de::DeserializeOwned, Serialize};
+
+        fn round_trip<
+            S: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+        >(
+            value: S,
+        ) {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(serde_json::from_str::<S>(&json).unwrap(), value);
+        }
+
+        let aabb = Aabb::new([-1.0, 0.5, 2.0], [3.0, 4.0, 5.5]);
+        round_trip(aabb);
+        round_trip(
+            Obb::from(aabb)
+                .transformed(&Isometry::from_axis_angle([0.0, 1.0, 0.0], 0.4)),
+        );
+        round_trip(Sphere::new([0.25, -8.0, 1.0], 0.75));
+        round_trip(Sphere::<f64>::new([1.0e12, 0.0, 0.0], 1.0e-3));
+        round_trip(Ray::new([0.0, 1.0, 2.0], [0.0, 0.0, 1.0]));
+        round_trip(Isometry::from_axis_angle([1.0, 0.0, 0.0], 1.0));
+        round_trip(Shape::from(Heightfield::new(
+            2,
+            3,
+            vec![0.0, 0.5, -1.0, 2.0, 0.25, 1.0],
+            [1.0, 2.0, 0.5],
+        )));
+        round_trip(Shape::from(
+            VoxelGrid::dense([-1, 0, 0], [2, 2, 1], 0.5).with_occupied([[0, 1, 0]]),
+        ));
+        round_trip(Shape::from(
+            VoxelGrid::sparse(2.0).with_occupied([[5, -3, 0], [0, 0, 0]]),
+        ));
+    }
+
+    #[test]
+    fn compound_pairs() {
+        let flat = Shape::from(Heightfield::new(3, 3, vec![0.0; 9], [1.0; 3]));
+        let ridge = Shape::from(Heightfield::new(
+            3,
+            3,
+            vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0],
+            [1.0; 3],
+        ));
+        let identity = Isometry::identity();
+        let at = |y: f32| Isometry::from_translation([0.5, y, 0.5]);
+        assert!(flat.intersects(&identity, &ridge, &at(-0.5)));
+        assert!(ridge.intersects(&at(-0.5), &flat, &identity));
+        assert!(!flat.intersects(&identity, &ridge, &at(0.5)));
+        // Turned upside down, the ridge only reaches down to the flat surface
+        // through its tip
+        let upside_down =
+            Isometry::from_axis_angle([1.0, 0.0, 0.0], core::f32::consts::PI)
+                .with_translation([0.0, 1.5, 2.0]);
+        assert!(!flat.intersects(&identity, &ridge, &upside_down));
+        let lowered = upside_down.with_translation([0.0, 0.9, 2.0]);
+        assert!(flat.intersects(&identity, &ridge, &lowered));
+
+        let cube = Shape::from(VoxelGrid::sparse(0.5).with_occupied([[0, 0, 0]]));
+        assert!(cube.intersects(&at(-0.2), &flat, &identity));
+        assert!(flat.intersects(&identity, &cube, &at(-0.2)));
+        assert!(!cube.intersects(&at(0.2), &flat, &identity));
+        let other = Shape::from(VoxelGrid::dense([0, 0, 0], [2, 1, 1], 0.5));
+        assert!(!cube.intersects(&identity, &other, &identity));
+        let other = Shape::from(
+            VoxelGrid::dense([0, 0, 0], [2, 1, 1], 0.5).with_occupied([[1, 0, 0]]),
+        );
+        assert!(cube.intersects(&at(0.0), &other, &identity));
+        assert!(!cube.intersects(&at(0.6), &other, &at(-0.6)));
+    }
 }
@@ -0,0 +1,91 @@
+//! The lane types used by `wide_intersections`. With the `nightly-simd`
+//! feature these are `std::simd`, otherwise a portable array backed
+//! fallback with the same method names that compiles on stable.
+
+use core::ops::{Add, Div, Index, IndexMut, Mul, Neg, Sub};
+
+#[cfg(feature = "nightly-simd")]
+pub use core::simd::{
+    cmp::{SimdPartialEq, SimdPartialOrd},
+    num::SimdFloat,
+    Mask, MaskElement, Select, Simd, SimdElement,
+};
+
+#[cfg(not(feature = "nightly-simd"))]
+mod fallback;
+#[cfg(not(feature = "nightly-simd"))]
+pub use fallback::*;
+
+/// The mask produced by comparing lanes of `T`.
+pub type MaskOf<T, const N: usize> = Mask<<T as SimdElement>::Mask, N>;
+
+/// Lane operations for `Simd<T, N>` that generic code over `Real` can name
+/// in a where clause, `Simd<T, N>: SimdReal<T, N>`.
+pub trait SimdReal<T: SimdElement, const N: usize>:
+    Copy
+    + Default
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Index<usize, Output = T>
+    + IndexMut<usize>
+{
+    fn simd_lt(self, other: Self) -> MaskOf<T, N>;
+    fn simd_le(self, other: Self) -> MaskOf<T, N>;
+    fn simd_gt(self, other: Self) -> MaskOf<T, N>;
+    fn simd_ge(self, other: Self) -> MaskOf<T, N>;
+    fn simd_min(self, other: Self) -> Self;
+    fn simd_max(self, other: Self) -> Self;
+    fn abs(self) -> Self;
+    fn select(mask: MaskOf<T, N>, true_values: Self, false_values: Self) -> Self;
+}
+
+macro_rules! impl_simd_real {
+    ($($t:ty),*) => {$(
+        impl<const N: usize> SimdReal<$t, N> for Simd<$t, N> {
+            #[inline]
+            fn simd_lt(self, other: Self) -> MaskOf<$t, N> {
+                SimdPartialOrd::simd_lt(self, other)
+            }
+
+            #[inline]
+            fn simd_le(self, other: Self) -> MaskOf<$t, N> {
+                SimdPartialOrd::simd_le(self, other)
+            }
+
+            #[inline]
+            fn simd_gt(self, other: Self) -> MaskOf<$t, N> {
+                SimdPartialOrd::simd_gt(self, other)
+            }
+
+            #[inline]
+            fn simd_ge(self, other: Self) -> MaskOf<$t, N> {
+                SimdPartialOrd::simd_ge(self, other)
+            }
+
+            #[inline]
+            fn simd_min(self, other: Self) -> Self {
+                SimdFloat::simd_min(self, other)
+            }
+
+            #[inline]
+            fn simd_max(self, other: Self) -> Self {
+                SimdFloat::simd_max(self, other)
+            }
+
+            #[inline]
+            fn abs(self) -> Self {
+                SimdFloat::abs(self)
+            }
+
+            #[inline]
+            fn select(mask: MaskOf<$t, N>, true_values: Self, false_values: Self) -> Self {
+                Select::select(mask, true_values, false_values)
+            }
+        }
+    )*};
+}
+
+impl_simd_real!(f32, f64);
@@ -0,0 +1,325 @@
+use core::marker::PhantomData;
+use core::ops::{Add, BitAnd, BitOr, Div, Index, IndexMut, Mul, Neg, Not, Sub};
+
+pub trait SimdElement: Copy {
+    type Mask: MaskElement;
+}
+
+pub trait MaskElement: Copy {}
+
+impl SimdElement for f32 {
+    type Mask = i32;
+}
+
+impl SimdElement for f64 {
+    type Mask = i64;
+}
+
+impl SimdElement for u32 {
+    type Mask = i32;
+}
+
+impl MaskElement for i32 {}
+
+impl MaskElement for i64 {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(transparent)]
+pub struct Simd<T, const N: usize>([T; N]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mask<T, const N: usize>([bool; N], PhantomData<T>);
+
+impl<T: Copy, const N: usize> Simd<T, N> {
+    pub const LEN: usize = N;
+
+    #[inline]
+    pub fn splat(value: T) -> Self {
+        Self([value; N])
+    }
+
+    #[inline]
+    pub const fn from_array(array: [T; N]) -> Self {
+        Self(array)
+    }
+
+    #[inline]
+    pub fn from_slice(slice: &[T]) -> Self {
+        assert!(
+            slice.len() >= N,
+            "slice length must be at least the number of lanes"
+        );
+        Self(core::array::from_fn(|i| slice[i]))
+    }
+
+    #[inline]
+    pub fn to_array(self) -> [T; N] {
+        self.0
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> &[T; N] {
+        &self.0
+    }
+
+    #[inline]
+    pub fn rotate_elements_left<const OFFSET: usize>(self) -> Self {
+        let mut array = self.0;
+        array.rotate_left(OFFSET % N);
+        Self(array)
+    }
+
+    #[inline]
+    pub fn rotate_elements_right<const OFFSET: usize>(self) -> Self {
+        let mut array = self.0;
+        array.rotate_right(OFFSET % N);
+        Self(array)
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for Simd<T, N> {
+    #[inline]
+    fn default() -> Self {
+        Self([T::default(); N])
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Simd<T, N> {
+    #[inline]
+    fn from(array: [T; N]) -> Self {
+        Self(array)
+    }
+}
+
+impl<T, const N: usize> From<Simd<T, N>> for [T; N] {
+    #[inline]
+    fn from(simd: Simd<T, N>) -> Self {
+        simd.0
+    }
+}
+
+impl<T, const N: usize> Index<usize> for Simd<T, N> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &T {
+        &self.0[index]
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for Simd<T, N> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.0[index]
+    }
+}
+
+macro_rules! impl_binary_op {
+    ($($op:ident :: $f:ident),*) => {$(
+        impl<T: Copy + $op<Output = T>, const N: usize> $op for Simd<T, N> {
+            type Output = Self;
+
+            #[inline]
+            fn $f(self, rhs: Self) -> Self {
+                Self(core::array::from_fn(|i| self.0[i].$f(rhs.0[i])))
+            }
+        }
+    )*};
+}
+
+impl_binary_op!(Add::add, Sub::sub, Mul::mul, Div::div, BitAnd::bitand);
+
+impl<T: Copy + Neg<Output = T>, const N: usize> Neg for Simd<T, N> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        Self(self.0.map(|v| -v))
+    }
+}
+
+impl<T, const N: usize> Mask<T, N> {
+    #[inline]
+    pub fn splat(value: bool) -> Self {
+        Self([value; N], PhantomData)
+    }
+
+    #[inline]
+    pub fn from_array(array: [bool; N]) -> Self {
+        Self(array, PhantomData)
+    }
+
+    #[inline]
+    pub fn to_array(self) -> [bool; N] {
+        self.0
+    }
+
+    #[inline]
+    pub fn test(&self, index: usize) -> bool {
+        self.0[index]
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: usize, value: bool) {
+        self.0[index] = value;
+    }
+
+    #[inline]
+    pub fn any(self) -> bool {
+        self.0.iter().any(|v| *v)
+    }
+
+    #[inline]
+    pub fn all(self) -> bool {
+        self.0.iter().all(|v| *v)
+    }
+}
+
+impl<T, const N: usize> BitAnd for Mask<T, N> {
+    type Output = Self;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Self(core::array::from_fn(|i| self.0[i] & rhs.0[i]), PhantomData)
+    }
+}
+
+impl<T, const N: usize> BitOr for Mask<T, N> {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(core::array::from_fn(|i| self.0[i] | rhs.0[i]), PhantomData)
+    }
+}
+
+impl<T, const N: usize> Not for Mask<T, N> {
+    type Output = Self;
+
+    #[inline]
+    fn not(self) -> Self {
+        Self(self.0.map(|v| !v), PhantomData)
+    }
+}
+
+pub trait Select<T> {
+    fn select(self, true_values: T, false_values: T) -> T;
+}
+
+impl<M, T: Copy, const N: usize> Select<Simd<T, N>> for Mask<M, N> {
+    #[inline]
+    fn select(
+        self,
+        true_values: Simd<T, N>,
+        false_values: Simd<T, N>,
+    ) -> Simd<T, N> {
+        Simd(core::array::from_fn(|i| {
+            if self.0[i] {
+                true_values.0[i]
+            } else {
+                false_values.0[i]
+            }
+        }))
+    }
+}
+
+pub trait SimdPartialOrd {
+    type Mask;
+
+    fn simd_lt(self, other: Self) -> Self::Mask;
+    fn simd_le(self, other: Self) -> Self::Mask;
+    fn simd_gt(self, other: Self) -> Self::Mask;
+    fn simd_ge(self, other: Self) -> Self::Mask;
+}
+
+pub trait SimdPartialEq {
+    type Mask;
+
+    fn simd_eq(self, other: Self) -> Self::Mask;
+    fn simd_ne(self, other: Self) -> Self::Mask;
+}
+
+impl<const N: usize> SimdPartialEq for Simd<u32, N> {
+    type Mask = Mask<i32, N>;
+
+    #[inline]
+    fn simd_eq(self, other: Self) -> Self::Mask {
+        Mask::from_array(core::array::from_fn(|i| self.0[i] == other.0[i]))
+    }
+
+    #[inline]
+    fn simd_ne(self, other: Self) -> Self::Mask {
+        Mask::from_array(core::array::from_fn(|i| self.0[i] != other.0[i]))
+    }
+}
+
+pub trait SimdFloat {
+    type Mask;
+    type Scalar;
+
+    fn abs(self) -> Self;
+    fn simd_min(self, other: Self) -> Self;
+    fn simd_max(self, other: Self) -> Self;
+    fn reduce_min(self) -> Self::Scalar;
+    fn reduce_max(self) -> Self::Scalar;
+}
+
+macro_rules! impl_float {
+    ($($float:ty => $mask:ty),*) => {$(
+        impl<const N: usize> SimdPartialOrd for Simd<$float, N> {
+            type Mask = Mask<$mask, N>;
+
+            #[inline]
+            fn simd_lt(self, other: Self) -> Self::Mask {
+                Mask::from_array(core::array::from_fn(|i| self.0[i] < other.0[i]))
+            }
+
+            #[inline]
+            fn simd_le(self, other: Self) -> Self::Mask {
+                Mask::from_array(core::array::from_fn(|i| self.0[i] <= other.0[i]))
+            }
+
+            #[inline]
+            fn simd_gt(self, other: Self) -> Self::Mask {
+                Mask::from_array(core::array::from_fn(|i| self.0[i] > other.0[i]))
+            }
+
+            #[inline]
+            fn simd_ge(self, other: Self) -> Self::Mask {
+                Mask::from_array(core::array::from_fn(|i| self.0[i] >= other.0[i]))
+            }
+        }
+
+        impl<const N: usize> SimdFloat for Simd<$float, N> {
+            type Mask = Mask<$mask, N>;
+            type Scalar = $float;
+
+            #[inline]
+            fn abs(self) -> Self {
+                Self(self.0.map(<$float>::abs))
+            }
+
+            #[inline]
+            fn simd_min(self, other: Self) -> Self {
+                Self(core::array::from_fn(|i| self.0[i].min(other.0[i])))
+            }
+
+            #[inline]
+            fn simd_max(self, other: Self) -> Self {
+                Self(core::array::from_fn(|i| self.0[i].max(other.0[i])))
+            }
+
+            #[inline]
+            fn reduce_min(self) -> $float {
+                self.0.into_iter().fold(<$float>::NAN, <$float>::min)
+            }
+
+            #[inline]
+            fn reduce_max(self) -> $float {
+                self.0.into_iter().fold(<$float>::NAN, <$float>::max)
+            }
+        }
+    )*};
+}
+
+impl_float!(f32 => i32, f64 => i64);
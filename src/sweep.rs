@@ -0,0 +1,595 @@
+//! Distances and times of impact between shapes, for shape casts and
+//! continuous collision detection.
+
+use crate::contacts;
+use crate::intersections::{
+    closest_point_on_obb, closest_point_on_segment, closest_point_on_triangle,
+    closest_points_segment_obb, closest_points_segment_triangle,
+    closest_points_segments,
+};
+use crate::isometry::Isometry;
+use crate::math::*;
+use crate::real::Real;
+use crate::shapes::{Aabb, Obb, Placed, Shape, Triangle};
+use crate::voxels::VoxelGrid;
+use alloc::vec::Vec;
+
+/// Gap at which a sweep counts as touching.
+const TOLERANCE: f64 = 1e-4;
+/// Gap at which a sweep that ran out of iterations still counts as touching.
+const STALLED_TOLERANCE: f64 = 1e-3;
+const MAX_ITERATIONS: usize = 32;
+
+/// A shape as the set of points within a radius of its core.
+#[derive(Clone, Copy)]
+enum Core<T: Real> {
+    Point([T; 3]),
+    Segment([T; 3], [T; 3]),
+    Box(Obb<T>),
+    Triangle(Triangle<T>),
+}
+
+fn core<T: Real>(placed: Placed<'_, T>) -> (Core<T>, T) {
+    match placed {
+        Placed::Sphere(sphere) => (Core::Point(sphere.center), sphere.radius),
+        Placed::Capsule(capsule) => {
+            (Core::Segment(capsule.a, capsule.b), capsule.radius)
+        }
+        Placed::Obb(obb) => (Core::Box(obb), T::ZERO),
+        Placed::Heightfield(..) | Placed::VoxelGrid(..) => {
+            unreachable!("compound shapes are split into parts")
+        }
+    }
+}
+
+/// A convex piece of a heightfield or voxel grid, in its local space.
+#[derive(Clone, Copy)]
+enum Part<'a, T: Real> {
+    Triangle(Triangle<T>),
+    Voxel(&'a VoxelGrid<T>, [i32; 3]),
+}
+
+/// The pose of a shape made of parts, or `None` for a convex shape.
+fn compound_iso<T: Real>(placed: Placed<'_, T>) -> Option<Isometry<T>> {
+    match placed {
+        Placed::Heightfield(_, iso) | Placed::VoxelGrid(_, iso) => Some(iso),
+        _ => None,
+    }
+}
+
+/// The parts of a compound shape overlapping `bounds` in its local space.
+fn parts<'a, T: Real>(placed: Placed<'a, T>, bounds: &Aabb<T>) -> Vec<Part<'a, T>> {
+    match placed {
+        Placed::Heightfield(field, _) => {
+            field.triangles(bounds).map(Part::Triangle).collect()
+        }
+        Placed::VoxelGrid(grid, _) => grid
+            .overlapping(bounds)
+            .map(|cell| Part::Voxel(grid, cell))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The signed distance between `a` and `b` and the unit normal pointing from
+/// `a` towards `b`. Overlapping shapes give minus their deepest contact. For
+/// two boxes this is the widest gap along their separating axes, which may
+/// fall short of the true distance but is zero exactly when they touch.
+/// Against a heightfield only the triangles under the other shape count, and
+/// against a voxel grid only the cells within a voxel of it. With none there,
+/// or between two such static shapes, the gap is infinite.
+pub fn distance<T: Real>(
+    a: &Shape<T>,
+    a_iso: &Isometry<T>,
+    b: &Shape<T>,
+    b_iso: &Isometry<T>,
+) -> (T, [T; 3]) {
+    if let Some(manifold) = contacts::contact(a, a_iso, b, b_iso) {
+        return (-manifold.max_depth(), manifold.normal);
+    }
+    let (a_placed, b_placed) = (a.placed(a_iso), b.placed(b_iso));
+    match (compound_iso(a_placed), compound_iso(b_placed)) {
+        (Some(_), Some(_)) => (T::INFINITY, [T::ZERO, T::ONE, T::ZERO]),
+        (Some(_), None) => compound_distance(a_placed, b, b_iso),
+        (None, Some(_)) => {
+            let (gap, normal) = compound_distance(b_placed, a, a_iso);
+            (gap, scale(normal, -T::ONE))
+        }
+        (None, None) => separation(core(a_placed), core(b_placed)),
+    }
+}
+
+/// The distance from the nearest part of `compound` around `shape`, and the
+/// normal pointing from the compound towards the shape.
+fn compound_distance<T: Real>(
+    compound: Placed<'_, T>,
+    shape: &Shape<T>,
+    iso: &Isometry<T>,
+) -> (T, [T; 3]) {
+    let compound_iso = compound_iso(compound).expect("a compound shape");
+    let local = compound_iso.inverse() * *iso;
+    let placed = shape.placed(&local);
+    let mut bounds = shape.aabb(&local);
+    match compound {
+        Placed::VoxelGrid(grid, _) => bounds = bounds.expanded(grid.voxel_size()),
+        _ => bounds.min[1] = -T::INFINITY,
+    }
+    parts(compound, &bounds)
+        .into_iter()
+        .map(|part| part_distance(part, placed))
+        .min_by(|x, y| x.0.total_cmp(&y.0))
+        .map_or(
+            (T::INFINITY, [T::ZERO, T::ONE, T::ZERO]),
+            |(gap, normal)| (gap, compound_iso.transform_vector(normal)),
+        )
+}
+
+/// Like `distance`, between a part of a compound shape and a convex shape in
+/// the compound's local space.
+fn part_distance<T: Real>(part: Part<'_, T>, placed: Placed<'_, T>) -> (T, [T; 3]) {
+    match part {
+        Part::Triangle(triangle) => {
+            if let Some(manifold) = contacts::triangle_contact(triangle, placed) {
+                return (-manifold.max_depth(), manifold.normal);
+            }
+            separation((Core::Triangle(triangle), T::ZERO), core(placed))
+        }
+        Part::Voxel(grid, cell) => {
+            if let Some(manifold) = contacts::voxel_contact(grid, cell, placed) {
+                return (-manifold.max_depth(), manifold.normal);
+            }
+            let cube = Obb::from(grid.cell_aabb(cell));
+            let (gap, mut normal) =
+                separation((Core::Box(cube), T::ZERO), core(placed));
+            // Edges and corners shared with occupied neighbours are inside
+            // the solid, so they don't turn the normal towards them. Shapes
+            // sliding over a seam then don't catch on it.
+            for i in 0..3 {
+                let mut neighbour = cell;
+                neighbour[i] += if normal[i] > T::ZERO { 1 } else { -1 };
+                if grid.is_occupied(neighbour) {
+                    normal[i] = T::ZERO;
+                }
+            }
+            // Overlaps through faces inside the solid are left to the cells
+            // on the outside
+            if gap < T::ZERO || length(normal) <= T::EPSILON {
+                (T::INFINITY, [T::ZERO, T::ONE, T::ZERO])
+            } else {
+                (gap, normalize(normal))
+            }
+        }
+    }
+}
+
+fn separation<T: Real>(
+    (a, radius_a): (Core<T>, T),
+    (b, radius_b): (Core<T>, T),
+) -> (T, [T; 3]) {
+    let (gap, normal) = match (a, b) {
+        (Core::Box(a), Core::Box(b)) => box_separation(a, b),
+        (Core::Triangle(triangle), Core::Box(obb)) => {
+            triangle_box_separation(triangle, obb)
+        }
+        (Core::Box(obb), Core::Triangle(triangle)) => {
+            let (gap, normal) = triangle_box_separation(triangle, obb);
+            (gap, scale(normal, -T::ONE))
+        }
+        (a, b) => {
+            let (p, q) = closest_points(a, b);
+            let offset = sub(q, p);
+            let gap = length(offset);
+            let normal = if gap > T::EPSILON {
+                scale(offset, T::ONE / gap)
+            } else {
+                [T::ZERO, T::ONE, T::ZERO]
+            };
+            (gap, normal)
+        }
+    };
+    (gap - radius_a - radius_b, normal)
+}
+
+fn closest_points<T: Real>(a: Core<T>, b: Core<T>) -> ([T; 3], [T; 3]) {
+    use Core::*;
+    let swap = |(p, q)| (q, p);
+    match (a, b) {
+        (Point(p), Point(q)) => (p, q),
+        (Point(p), Segment(s, e)) => (p, closest_point_on_segment(s, e, p)),
+        (Segment(s, e), Point(p)) => (closest_point_on_segment(s, e, p), p),
+        (Point(p), Box(obb)) => (p, closest_point_on_obb(obb, p)),
+        (Box(obb), Point(p)) => (closest_point_on_obb(obb, p), p),
+        (Segment(s1, e1), Segment(s2, e2)) => {
+            closest_points_segments((s1, e1), (s2, e2))
+        }
+        (Segment(s, e), Box(obb)) => closest_points_segment_obb((s, e), obb),
+        (Box(obb), Segment(s, e)) => swap(closest_points_segment_obb((s, e), obb)),
+        (Triangle(t), Point(p)) => (closest_point_on_triangle(t, p), p),
+        (Point(p), Triangle(t)) => (p, closest_point_on_triangle(t, p)),
+        (Triangle(t), Segment(s, e)) => {
+            swap(closest_points_segment_triangle((s, e), t))
+        }
+        (Segment(s, e), Triangle(t)) => closest_points_segment_triangle((s, e), t),
+        (Box(_), Box(_) | Triangle(_)) | (Triangle(_), Box(_) | Triangle(_)) => {
+            unreachable!("boxes and triangles are separated by axes")
+        }
+    }
+}
+
+/// The widest gap between two boxes along the face normals and edge
+/// crossings, and that axis turned to point from `a` to `b`.
+fn box_separation<T: Real>(a: Obb<T>, b: Obb<T>) -> (T, [T; 3]) {
+    let offset = sub(b.center, a.center);
+    let project = |obb: &Obb<T>, axis: [T; 3]| {
+        (0..3)
+            .map(|k| obb.half_extents[k] * dot(obb.axes[k], axis).abs())
+            .sum::<T>()
+    };
+    let faces = a.axes.into_iter().chain(b.axes);
+    let edges = a
+        .axes
+        .into_iter()
+        .flat_map(|x| b.axes.map(|y| cross(x, y)))
+        .filter(|axis| length(*axis) > T::from_f64(1e-6))
+        .map(normalize);
+    faces
+        .chain(edges)
+        .map(|axis| {
+            let distance = dot(offset, axis);
+            let gap = distance.abs() - project(&a, axis) - project(&b, axis);
+            (gap, scale(axis, distance.signum()))
+        })
+        .max_by(|x, y| x.0.total_cmp(&y.0))
+        .unwrap()
+}
+
+/// The widest gap between a triangle and a box along the triangle's face,
+/// the box's faces and the crossings of their edges, and that axis turned
+/// to point from the triangle to the box.
+fn triangle_box_separation<T: Real>(
+    triangle: Triangle<T>,
+    obb: Obb<T>,
+) -> (T, [T; 3]) {
+    let vertices = triangle.vertices();
+    let edges = [0, 1, 2].map(|i| sub(vertices[(i + 1) % 3], vertices[i]));
+    let crossings = edges
+        .into_iter()
+        .flat_map(|edge| obb.axes.map(|axis| cross(edge, axis)))
+        .filter(|axis| length(*axis) > T::from_f64(1e-6))
+        .map(normalize);
+    obb.axes
+        .into_iter()
+        .chain([triangle.normal()])
+        .chain(crossings)
+        .map(|axis| {
+            let (low, high) = vertices
+                .iter()
+                .map(|v| dot(*v, axis))
+                .fold((T::INFINITY, -T::INFINITY), |(low, high), d| {
+                    (low.min(d), high.max(d))
+                });
+            let center = dot(obb.center, axis);
+            let radius = (0..3)
+                .map(|k| obb.half_extents[k] * dot(obb.axes[k], axis).abs())
+                .sum::<T>();
+            let (ahead, behind) = (center - radius - high, low - center - radius);
+            if ahead >= behind {
+                (ahead, axis)
+            } else {
+                (behind, scale(axis, -T::ONE))
+            }
+        })
+        .max_by(|x, y| x.0.total_cmp(&y.0))
+        .unwrap()
+}
+
+/// The first time at which `a`, moving by `motion` per unit of time without
+/// turning, touches the still `b`, along with the normal from `a` towards
+/// `b` at that moment. Shapes already touching hit at time zero, unless
+/// they're moving apart. Heightfields and voxel grids never hit each other.
+///
+/// The distance along the motion is convex, so stepping to where its
+/// tangent reaches zero never passes the impact.
+pub fn time_of_impact<T: Real>(
+    a: &Shape<T>,
+    a_iso: &Isometry<T>,
+    motion: [T; 3],
+    b: &Shape<T>,
+    b_iso: &Isometry<T>,
+    max_time: T,
+) -> Option<(T, [T; 3])> {
+    let (a_placed, b_placed) = (a.placed(a_iso), b.placed(b_iso));
+    match (compound_iso(a_placed), compound_iso(b_placed)) {
+        (Some(_), Some(_)) => None,
+        (None, Some(_)) => compound_impact(a, a_iso, motion, b_placed, max_time),
+        (Some(_), None) => {
+            let reversed = scale(motion, -T::ONE);
+            compound_impact(b, b_iso, reversed, a_placed, max_time)
+                .map(|(time, normal)| (time, scale(normal, -T::ONE)))
+        }
+        (None, None) => advance(motion, max_time, |time| {
+            let moved =
+                a_iso.with_translation(add(a_iso.translation, scale(motion, time)));
+            distance(a, &moved, b, b_iso)
+        }),
+    }
+}
+
+/// The earliest impact of `shape` with the parts of `compound` its sweep
+/// passes over, each advanced towards separately.
+fn compound_impact<T: Real>(
+    shape: &Shape<T>,
+    iso: &Isometry<T>,
+    motion: [T; 3],
+    compound: Placed<'_, T>,
+    max_time: T,
+) -> Option<(T, [T; 3])> {
+    let compound_iso = compound_iso(compound).expect("a compound shape");
+    let local = compound_iso.inverse() * *iso;
+    let motion: [T; 3] = compound_iso.inverse_transform_vector(motion);
+    let start = shape.aabb(&local);
+    let swept = start.merged(&start.with_offset(scale(motion, max_time)));
+    let mut closest: Option<(T, [T; 3])> = None;
+    for part in parts(compound, &swept) {
+        let max_time = closest.map_or(max_time, |(time, _)| time);
+        let hit = advance(motion, max_time, |time| {
+            let moved =
+                local.with_translation(add(local.translation, scale(motion, time)));
+            let (gap, normal) = part_distance(part, shape.placed(&moved));
+            (gap, scale(normal, -T::ONE))
+        });
+        if hit.is_some() {
+            closest = hit;
+        }
+    }
+    closest.map(|(time, normal)| (time, compound_iso.transform_vector(normal)))
+}
+
+/// Conservative advancement along `motion`, with `distance` giving the gap
+/// and normal towards the obstacle at a time.
+fn advance<T: Real>(
+    motion: [T; 3],
+    max_time: T,
+    distance: impl Fn(T) -> (T, [T; 3]),
+) -> Option<(T, [T; 3])> {
+    let mut time = T::ZERO;
+    for _ in 0..MAX_ITERATIONS {
+        let (gap, normal) = distance(time);
+        let approach = dot(motion, normal);
+        if approach <= T::ZERO {
+            return None;
+        }
+        if gap <= T::from_f64(TOLERANCE) {
+            return Some((time, normal));
+        }
+        time += gap / approach;
+        if time > max_time {
+            return None;
+        }
+    }
+    // Still closing in, which only counts as a hit if nearly touching, so a
+    // grazing miss doesn't stop short
+    let (gap, normal) = distance(time);
+    (gap <= T::from_f64(STALLED_TOLERANCE)).then_some((time, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heightfield::Heightfield;
+    use crate::shapes::{Capsule, Sphere};
+
+    #[test]
+    fn impacts() {
+        let identity = Isometry::<f32>::identity();
+        let ball = Shape::Sphere(Sphere::new([0.0; 3], 0.5));
+        let wall = Shape::Aabb(Aabb::new([2.0, -5.0, -5.0], [3.0, 5.0, 5.0]));
+        let (gap, normal) = distance(&ball, &identity, &wall, &identity);
+        assert!((gap - 1.5).abs() < 1e-6);
+        assert_eq!(normal, [1.0, 0.0, 0.0]);
+
+        let (time, normal) =
+            time_of_impact(&ball, &identity, [3.0, 0.0, 0.0], &wall, &identity, 1.0)
+                .unwrap();
+        assert!((time - 0.5).abs() < 1e-3);
+        assert_eq!(normal, [1.0, 0.0, 0.0]);
+        assert!(time_of_impact(
+            &ball,
+            &identity,
+            [1.0, 0.0, 0.0],
+            &wall,
+            &identity,
+            1.0
+        )
+        .is_none());
+        assert!(time_of_impact(
+            &ball,
+            &identity,
+            [0.0, 3.0, 0.0],
+            &wall,
+            &identity,
+            1.0
+        )
+        .is_none());
+
+        // Grazing past a corner takes more than one step
+        let capsule = Shape::Capsule(Capsule::upright(0.5, 0.25));
+        let start = Isometry::from_translation([0.0, 6.0, 0.0]);
+        let (time, _) = time_of_impact(
+            &capsule,
+            &start,
+            [4.0, -4.0, 0.0],
+            &wall,
+            &identity,
+            1.0,
+        )
+        .unwrap();
+        let moved = start
+            .with_translation(add(start.translation, scale([4.0, -4.0, 0.0], time)));
+        let (gap, _) = distance(&capsule, &moved, &wall, &identity);
+        assert!(gap.abs() < 1e-3, "{}", gap);
+
+        // Turned boxes only touch once every separating axis closes
+        let tilted = Shape::Obb(Obb::from_rotation(
+            [0.0; 3],
+            [0.5; 3],
+            Isometry::from_axis_angle([1.0, 1.0, 0.0], 0.7).rotation,
+        ));
+        let (time, normal) = time_of_impact(
+            &tilted,
+            &identity,
+            [4.0, 0.0, 0.0],
+            &wall,
+            &identity,
+            1.0,
+        )
+        .unwrap();
+        let moved = identity.with_translation(scale([4.0, 0.0, 0.0], time));
+        assert!(contacts::contact(&tilted, &moved, &wall, &identity)
+            .is_none_or(|m| m.max_depth() < 1e-3));
+        let nudged = identity.with_translation(scale([4.0, 0.0, 0.0], time + 0.01));
+        assert!(contacts::contact(&tilted, &nudged, &wall, &identity).is_some());
+        assert!(normal[0] > 0.9);
+
+        let nearby = identity.with_translation([0.5, 0.0, 0.0]);
+        let (time, _) =
+            time_of_impact(&ball, &identity, [1.0, 0.0, 0.0], &ball, &nearby, 1.0)
+                .unwrap();
+        assert_eq!(time, 0.0);
+        assert!(time_of_impact(
+            &ball,
+            &identity,
+            [-1.0, 0.0, 0.0],
+            &ball,
+            &nearby,
+            1.0
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn heightfield_impacts() {
+        let heights = (0..8 * 8)
+            .map(|i| ((i % 8) as f32 * 0.9).sin() + (i / 8) as f32 * 0.25)
+            .collect();
+        let terrain = Shape::Heightfield(Heightfield::new(8, 8, heights, [1.0; 3]));
+        let placement = Isometry::from_axis_angle([0.0, 0.0, 1.0], 0.2)
+            .with_translation([-3.0, 0.0, -3.0]);
+        let shapes = [
+            Shape::Sphere(Sphere::new([0.0; 3], 0.5)),
+            Shape::Capsule(Capsule::upright(0.5, 0.25)),
+            Shape::Obb(Obb::from_rotation(
+                [0.0; 3],
+                [0.4, 0.3, 0.5],
+                Isometry::from_axis_angle([1.0, 0.0, 1.0], 0.5).rotation,
+            )),
+        ];
+        for shape in &shapes {
+            let start = Isometry::from_translation([0.5, 8.0, 0.5]);
+            let down = [0.0, -20.0, 0.0];
+            let (time, normal) =
+                time_of_impact(shape, &start, down, &terrain, &placement, 1.0)
+                    .unwrap();
+            assert!(normal[1] < -0.5, "{:?}", normal);
+            let moved =
+                start.with_translation(add(start.translation, scale(down, time)));
+            let (gap, _) = distance(shape, &moved, &terrain, &placement);
+            assert!(gap.abs() < 1e-3, "{}", gap);
+            assert!(contacts::contact(shape, &moved, &terrain, &placement)
+                .is_none_or(|m| m.max_depth() < 1e-3));
+            let nudged =
+                moved.with_translation(add(moved.translation, [0.0, -0.01, 0.0]));
+            assert!(
+                contacts::contact(shape, &nudged, &terrain, &placement).is_some()
+            );
+
+            // Moving the terrain up instead meets it at the same time
+            let up = [0.0, 20.0, 0.0];
+            let (lifted, flipped) =
+                time_of_impact(&terrain, &placement, up, shape, &start, 1.0)
+                    .unwrap();
+            assert!((lifted - time).abs() < 1e-3);
+            assert!(flipped[1] > 0.5);
+
+            assert!(time_of_impact(
+                shape,
+                &start,
+                [0.0, 20.0, 0.0],
+                &terrain,
+                &placement,
+                1.0
+            )
+            .is_none());
+            let beside = Isometry::from_translation([20.0, 8.0, 0.5]);
+            assert!(
+                time_of_impact(shape, &beside, down, &terrain, &placement, 1.0)
+                    .is_none()
+            );
+        }
+    }
+
+    #[test]
+    fn voxel_impacts() {
+        let floor = (-4..4).flat_map(|x| (-4..4).map(move |z| [x, -1, z]));
+        let pillar = (0..3).map(|y| [2, y, 0]);
+        let grid = Shape::VoxelGrid(
+            VoxelGrid::<f32>::sparse(0.5).with_occupied(floor.chain(pillar)),
+        );
+        let placement = Isometry::from_translation([1.0, 0.0, 0.0]);
+        let shapes = [
+            Shape::Sphere(Sphere::new([0.0; 3], 0.2)),
+            Shape::Capsule(Capsule::upright(0.2, 0.1)),
+            Shape::Obb(Obb::new([0.0; 3], [0.2; 3], identity())),
+        ];
+        for shape in &shapes {
+            let start = Isometry::from_translation([0.77, 4.0, 0.13]);
+            let down = [0.0, -10.0, 0.0];
+            let (time, normal) =
+                time_of_impact(shape, &start, down, &grid, &placement, 1.0).unwrap();
+            assert!(normal[1] < -0.99, "{:?}", normal);
+            let moved =
+                start.with_translation(add(start.translation, scale(down, time)));
+            let (gap, _) = distance(shape, &moved, &grid, &placement);
+            assert!(gap.abs() < 1e-3, "{}", gap);
+            let bottom = shape.aabb(&moved).min[1];
+            assert!(bottom.abs() < 1e-3, "{}", bottom);
+
+            // Sliding along the floor only stops at the pillar
+            let right = [5.0, 0.0, 0.0];
+            let (time, normal) =
+                time_of_impact(shape, &moved, right, &grid, &placement, 1.0)
+                    .unwrap();
+            assert!(normal[0] > 0.99, "{:?}", normal);
+            let side = shape.aabb(&moved).max[0] + 5.0 * time;
+            assert!((side - 2.0).abs() < 1e-3, "{}", side);
+
+            let beside = start.with_translation([20.0, 4.0, 0.0]);
+            assert!(time_of_impact(shape, &beside, down, &grid, &placement, 1.0)
+                .is_none());
+            let far = distance(shape, &beside, &grid, &placement);
+            assert_eq!(far.0, f32::INFINITY);
+        }
+    }
+
+    #[test]
+    fn grazing_miss() {
+        // The gap levels off short of touching while the normal still says
+        // the shapes are closing in, so advancement runs out of iterations
+        let motion = [1.0, 0.0, 0.0];
+        let towards = [1.0, 0.0, 0.0];
+        let stalling = |time: f32| (0.01 + (-time).exp(), towards);
+        assert!(advance(motion, 100.0, stalling).is_none());
+        let touching = |_| (5e-4, towards);
+        assert!(advance(motion, 100.0, touching).is_some());
+
+        // A ball skimming just past another
+        let ball = Shape::Sphere(Sphere::new([0.0; 3], 0.5));
+        let identity = Isometry::identity();
+        for miss in [1e-3, 1e-2] {
+            let start = Isometry::from_translation([-5.0, 1.0 + miss, 0.0]);
+            let motion = [10.0, 0.0, 0.0];
+            assert!(time_of_impact(&ball, &start, motion, &ball, &identity, 1.0)
+                .is_none());
+        }
+    }
+}
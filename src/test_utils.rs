@@ -1,35 +1,84 @@
-use crate::shapes::Aabb;
-use std::simd::{Simd, SupportedLaneCount};
+use alloc::vec::Vec;
 
-pub fn generate_test_data<const WIDTH: usize>(
+pub fn generate_soa_data<const WIDTH: usize>(
     count: usize,
     mut generator: impl FnMut(usize) -> [f32; WIDTH],
-) -> ([Vec<f32>; 6], Vec<[[f32; 3]; 2]>) {
-    let mut simd_aabbs: [Vec<f32>; 6] = [
-        Vec::new(),
-        Vec::new(),
-        Vec::new(),
-        Vec::new(),
-        Vec::new(),
-        Vec::new(),
-    ];
+) -> [Vec<f32>; WIDTH] {
+    let mut soa: [Vec<f32>; WIDTH] = [(); WIDTH].map(|_| Vec::with_capacity(count));
     for i in 0..count {
-        let aabb = generator(i);
-        simd_aabbs[0].push(aabb[0]);
-        simd_aabbs[1].push(aabb[1]);
-        simd_aabbs[2].push(aabb[2]);
-        simd_aabbs[3].push(aabb[3]);
-        simd_aabbs[4].push(aabb[4]);
-        simd_aabbs[5].push(aabb[5]);
+        let item = generator(i);
+        for (column, value) in soa.iter_mut().zip(item) {
+            column.push(value);
+        }
     }
+    soa
+}
 
-    let mut aabbs = Vec::with_capacity(count);
-    for i in 0..count {
-        let a = [
-            [simd_aabbs[0][i], simd_aabbs[1][i], simd_aabbs[2][i]],
-            [simd_aabbs[3][i], simd_aabbs[4][i], simd_aabbs[5][i]],
-        ];
-        aabbs.push(a);
-    }
+pub fn generate_test_data<const WIDTH: usize>(
+    count: usize,
+    generator: impl FnMut(usize) -> [f32; WIDTH],
+) -> ([Vec<f32>; WIDTH], Vec<[[f32; 3]; 2]>) {
+    let simd_aabbs = generate_soa_data(count, generator);
+
+    let aabbs = (0..count)
+        .map(|i| {
+            [
+                [simd_aabbs[0][i], simd_aabbs[1][i], simd_aabbs[2][i]],
+                [simd_aabbs[3][i], simd_aabbs[4][i], simd_aabbs[5][i]],
+            ]
+        })
+        .collect();
     (simd_aabbs, aabbs)
 }
+
+/// Fixtures shared by the unit tests of the physics modules.
+#[cfg(test)]
+pub(crate) mod physics {
+    use crate::dynamics::{MassProperties, PhysicsWorld, RigidBody};
+    use crate::isometry::Isometry;
+    use crate::math::scale;
+    use crate::shapes::{Aabb, Shape};
+    use crate::world::{Collider, ColliderHandle};
+
+    pub(crate) fn close(a: f32, b: f32, tolerance: f32) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    /// A dynamic box of unit density.
+    pub(crate) fn cuboid(
+        world: &mut PhysicsWorld,
+        half_extents: [f32; 3],
+        position: [f32; 3],
+    ) -> ColliderHandle {
+        let shape = Shape::Aabb(Aabb::new(scale(half_extents, -1.0), half_extents));
+        let body = RigidBody::dynamic(MassProperties::from_shape(&shape, 1.0));
+        world.insert(
+            Collider::new(shape, Isometry::from_translation(position)),
+            body,
+        )
+    }
+
+    pub(crate) fn unit_box(
+        world: &mut PhysicsWorld,
+        position: [f32; 3],
+    ) -> ColliderHandle {
+        cuboid(world, [0.5; 3], position)
+    }
+
+    /// A world with a static floor at `y = 0`.
+    pub(crate) fn with_ground() -> PhysicsWorld {
+        let mut world = PhysicsWorld::new();
+        let ground = Aabb::new([-10.0, -1.0, -10.0], [10.0, 0.0, 10.0]);
+        world
+            .world_mut()
+            .insert(Collider::new(ground, Isometry::identity()));
+        world
+    }
+
+    /// Steps `world` at 60Hz for `seconds`.
+    pub(crate) fn run(world: &mut PhysicsWorld, seconds: f32) {
+        for _ in 0..(seconds * 60.0) as usize {
+            world.step(1.0 / 60.0);
+        }
+    }
+}
@@ -1,5 +1,14 @@
-use std::simd::{LaneCount, Simd, SupportedLaneCount};
+use crate::simd::Simd;
+use alloc::vec::Vec;
 
+/// Streams `input_b` past blocks of `LANES` items of `input_a`, calling `f`
+/// once per streamed item for each block, in order. Lane `k` of the block
+/// holds `input_a[block * LANES + k]` and lane `k` of the window holds
+/// `input_b[j - k]`, where `j` is the item just streamed in. Lanes past the
+/// end of `input_a` are zero. The window isn't cleared between blocks, so
+/// before the first block's stream its lanes are zero and afterwards they
+/// still hold the end of the previous stream. When both inputs are the same
+/// data, each block's stream starts just after the block's first item.
 pub fn simd_permutations<
     const LANES: usize,
     const WIDTH: usize,
@@ -10,18 +19,19 @@ pub fn simd_permutations<
     input_b: &[Vec<f32>; WIDTH2],
     mut f: T,
 ) where
-    LaneCount<LANES>: SupportedLaneCount,
-    T: FnMut([Simd<f32, LANES>; WIDTH], [Simd<f32, LANES>; WIDTH]),
+    T: FnMut([Simd<f32, LANES>; WIDTH], [Simd<f32, LANES>; WIDTH2]),
     [Simd<f32, LANES>; WIDTH]: Default,
+    [Simd<f32, LANES>; WIDTH2]: Default,
 {
     let zero = [0.0; LANES];
     let count = input_a[0].len();
+    let count_b = input_b[0].len();
     let mut simd_a: [Simd<f32, LANES>; WIDTH] = Default::default();
-    let mut simd_b: [Simd<f32, LANES>; WIDTH] = Default::default();
+    let mut simd_b: [Simd<f32, LANES>; WIDTH2] = Default::default();
 
     let same_inputs = input_a.as_ptr() == input_b.as_ptr();
 
-    let i_len = (count as f32 / LANES as f32).ceil() as usize;
+    let i_len = count.div_ceil(LANES);
     for i in 0..i_len {
         for k in 0..WIDTH {
             let overflow = ((i + 1) * LANES) as i64 - count as i64;
@@ -36,13 +46,54 @@ pub fn simd_permutations<
             0
         };
 
-        for j in j_offset..count {
-            for k in 0..WIDTH {
-                simd_b[k] = simd_b[k].rotate_lanes_right::<1>();
-                simd_b[k][0] = input_b[k][j];
+        for j in j_offset..count_b {
+            for (simd_b, input_b) in simd_b.iter_mut().zip(input_b) {
+                *simd_b = simd_b.rotate_elements_right::<1>();
+                simd_b[0] = input_b[j];
             }
 
             f(simd_a, simd_b);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn permutation_order() {
+        let a = [vec![1.0, 2.0, 3.0, 4.0, 5.0]];
+        let b = [vec![10.0, 20.0, 30.0]];
+        let mut calls = vec![];
+        simd_permutations::<4, 1, 1, _>(&a, &b, |[a], [b]| {
+            calls.push((a.to_array(), b.to_array()))
+        });
+        let first = [1.0, 2.0, 3.0, 4.0];
+        let second = [5.0, 0.0, 0.0, 0.0];
+        assert_eq!(
+            calls,
+            [
+                (first, [10.0, 0.0, 0.0, 0.0]),
+                (first, [20.0, 10.0, 0.0, 0.0]),
+                (first, [30.0, 20.0, 10.0, 0.0]),
+                (second, [10.0, 30.0, 20.0, 10.0]),
+                (second, [20.0, 10.0, 30.0, 20.0]),
+                (second, [30.0, 20.0, 10.0, 30.0]),
+            ]
+        );
+
+        let mut windows = vec![];
+        simd_permutations::<4, 1, 1, _>(&a, &a, |_, [b]| windows.push(b.to_array()));
+        assert_eq!(
+            windows,
+            [
+                [2.0, 0.0, 0.0, 0.0],
+                [3.0, 2.0, 0.0, 0.0],
+                [4.0, 3.0, 2.0, 0.0],
+                [5.0, 4.0, 3.0, 2.0],
+            ]
+        );
+    }
+}
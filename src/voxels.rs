@@ -0,0 +1,601 @@
+//! Grids of solid cubes, for block based worlds.
+
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::shapes::Aabb;
+use crate::Vec3;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Storage {
+    /// One flag per cell of the box from `min` spanning `dims` cells.
+    Dense {
+        min: [i32; 3],
+        dims: [usize; 3],
+        occupied: Vec<bool>,
+    },
+    Sparse(BTreeSet<[i32; 3]>),
+}
+
+/// Cubes of side `voxel_size` on an integer grid, where the cell `[x, y, z]`
+/// spans from `[x, y, z] * voxel_size` to `[x + 1, y + 1, z + 1] *
+/// voxel_size` in local space. Dense grids cover a fixed box of cells,
+/// sparse ones any number of cells anywhere.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VoxelGrid<T: Real = f32> {
+    voxel_size: T,
+    storage: Storage,
+    /// Number of occupied cells.
+    len: usize,
+    /// Lowest and highest occupied cell.
+    bounds: Option<[[i32; 3]; 2]>,
+}
+
+/// A hit from `VoxelGrid::cast_ray`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VoxelHit<T: Real = f32> {
+    pub time: T,
+    pub cell: [i32; 3],
+    /// Normal of the face the ray crossed.
+    pub normal: [T; 3],
+}
+
+impl<T: Real> VoxelGrid<T> {
+    /// An empty grid of `dims` cells starting at the cell `min`.
+    pub fn dense(min: [i32; 3], dims: [usize; 3], voxel_size: T) -> Self {
+        assert!(voxel_size > T::ZERO, "voxel size must be positive");
+        Self {
+            voxel_size,
+            storage: Storage::Dense {
+                min,
+                dims,
+                occupied: vec![false; dims[0] * dims[1] * dims[2]],
+            },
+            len: 0,
+            bounds: None,
+        }
+    }
+
+    pub fn sparse(voxel_size: T) -> Self {
+        assert!(voxel_size > T::ZERO, "voxel size must be positive");
+        Self {
+            voxel_size,
+            storage: Storage::Sparse(BTreeSet::new()),
+            len: 0,
+            bounds: None,
+        }
+    }
+
+    pub fn with_occupied(
+        mut self,
+        cells: impl IntoIterator<Item = [i32; 3]>,
+    ) -> Self {
+        for cell in cells {
+            self.set(cell, true);
+        }
+        self
+    }
+
+    pub fn voxel_size(&self) -> T {
+        self.voxel_size
+    }
+
+    fn index(&self, cell: [i32; 3]) -> Option<usize> {
+        let Storage::Dense { min, dims, .. } = &self.storage else {
+            return None;
+        };
+        let mut index = 0;
+        for i in 0..3 {
+            let offset = usize::try_from(i64::from(cell[i]) - i64::from(min[i]))
+                .ok()
+                .filter(|offset| *offset < dims[i])?;
+            index = index * dims[i] + offset;
+        }
+        Some(index)
+    }
+
+    /// Fills or clears `cell`. Dense grids panic when filling outside their
+    /// box.
+    pub fn set(&mut self, cell: [i32; 3], occupied: bool) {
+        let index = self.index(cell);
+        let changed = match &mut self.storage {
+            Storage::Dense { .. } if index.is_none() && !occupied => false,
+            Storage::Dense {
+                occupied: flags, ..
+            } => {
+                let flag = &mut flags[index.expect("cell outside the dense grid")];
+                core::mem::replace(flag, occupied) != occupied
+            }
+            Storage::Sparse(cells) if occupied => cells.insert(cell),
+            Storage::Sparse(cells) => cells.remove(&cell),
+        };
+        if !changed {
+            return;
+        }
+        if occupied {
+            self.len += 1;
+            self.bounds = Some(match self.bounds {
+                Some([low, high]) => [
+                    core::array::from_fn(|i| low[i].min(cell[i])),
+                    core::array::from_fn(|i| high[i].max(cell[i])),
+                ],
+                None => [cell, cell],
+            });
+            return;
+        }
+        self.len -= 1;
+        if self.bounds.is_some_and(|[low, high]| {
+            (0..3).any(|i| cell[i] == low[i] || cell[i] == high[i])
+        }) {
+            // The bounds can only shrink when a cell on their edge goes
+            self.bounds = self.occupied().fold(None, |bounds, cell| {
+                Some(match bounds {
+                    Some([low, high]) => [
+                        core::array::from_fn(|i| i32::min(low[i], cell[i])),
+                        core::array::from_fn(|i| i32::max(high[i], cell[i])),
+                    ],
+                    None => [cell, cell],
+                })
+            });
+        }
+    }
+
+    pub fn is_occupied(&self, cell: [i32; 3]) -> bool {
+        match &self.storage {
+            Storage::Dense { occupied, .. } => {
+                self.index(cell).is_some_and(|index| occupied[index])
+            }
+            Storage::Sparse(cells) => cells.contains(&cell),
+        }
+    }
+
+    /// Every occupied cell, in order of x, then y, then z.
+    pub fn occupied(&self) -> impl Iterator<Item = [i32; 3]> + '_ {
+        let (dense, sparse) = match &self.storage {
+            Storage::Dense {
+                min,
+                dims,
+                occupied,
+            } => {
+                let cells = occupied.iter().enumerate().filter(|(_, o)| **o).map(
+                    move |(index, _)| {
+                        let z = index % dims[2];
+                        let y = index / dims[2] % dims[1];
+                        let x = index / (dims[1] * dims[2]);
+                        let offset = [x, y, z];
+                        core::array::from_fn(|i| min[i] + offset[i] as i32)
+                    },
+                );
+                (Some(cells), None)
+            }
+            Storage::Sparse(cells) => (None, Some(cells.iter().copied())),
+        };
+        dense
+            .into_iter()
+            .flatten()
+            .chain(sparse.into_iter().flatten())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_none()
+    }
+
+    /// The cell containing `point` in local space.
+    pub fn cell_at(&self, point: impl Into<Vec3<T>>) -> [i32; 3] {
+        let point: [T; 3] = point.into().into();
+        point.map(|v| Self::coordinate(v / self.voxel_size))
+    }
+
+    fn coordinate(value: T) -> i32 {
+        let limit = T::from_f64(i32::MAX as f64);
+        value.floor().max(-limit).min(limit).to_i32().unwrap_or(0)
+    }
+
+    pub fn cell_aabb(&self, cell: [i32; 3]) -> Aabb<T> {
+        let corner = cell.map(|v| T::from_f64(v as f64) * self.voxel_size);
+        Aabb {
+            min: corner,
+            max: corner.map(|v| v + self.voxel_size),
+        }
+    }
+
+    /// The local box around every occupied cell, empty at the origin when
+    /// there are none.
+    pub fn local_aabb(&self) -> Aabb<T> {
+        match self.bounds {
+            Some([low, high]) => self.cell_aabb(low).merged(&self.cell_aabb(high)),
+            None => Aabb {
+                min: [T::ZERO; 3],
+                max: [T::ZERO; 3],
+            },
+        }
+    }
+
+    /// The occupied cells overlapping or touching `aabb` in local space, in
+    /// the same order as `occupied`.
+    pub fn overlapping(
+        &self,
+        aabb: &Aabb<T>,
+    ) -> impl Iterator<Item = [i32; 3]> + '_ {
+        let [low, high] = self.bounds.unwrap_or([[0; 3], [-1; 3]]);
+        let size = self.voxel_size;
+        let first: [i32; 3] = core::array::from_fn(|i| {
+            let touching = Self::coordinate((aabb.min[i] / size).ceil()) - 1;
+            touching.max(low[i])
+        });
+        let last: [i32; 3] = core::array::from_fn(|i| {
+            Self::coordinate(aabb.max[i] / size).min(high[i])
+        });
+        let (x, y, z) = (first[0]..=last[0], first[1]..=last[1], first[2]..=last[2]);
+        x.flat_map(move |x| y.clone().map(move |y| (x, y)))
+            .flat_map(move |(x, y)| z.clone().map(move |z| [x, y, z]))
+            .filter(move |cell| self.is_occupied(*cell))
+    }
+
+    /// The first occupied cell hit by `ray` in local space, walking the cells
+    /// along it with the Amanatides-Woo traversal. A ray starting inside an
+    /// occupied cell hits on its way out of the solid cells around it.
+    pub fn cast_ray(&self, ray: Ray<T>) -> Option<VoxelHit<T>> {
+        if self.is_empty() || ray.direction == [T::ZERO; 3] {
+            return None;
+        }
+        let bounds = self.local_aabb();
+        let (mut enter, mut exit) = (T::ZERO, T::INFINITY);
+        let mut entry_axis = None;
+        for i in 0..3 {
+            let (origin, direction) = (ray.origin[i], ray.direction[i]);
+            if direction == T::ZERO {
+                if origin < bounds.min[i] || origin > bounds.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (bounds.min[i] - origin) / direction;
+            let t1 = (bounds.max[i] - origin) / direction;
+            if t0.min(t1) > enter {
+                enter = t0.min(t1);
+                entry_axis = Some(i);
+            }
+            exit = exit.min(t0.max(t1));
+        }
+        if enter > exit {
+            return None;
+        }
+
+        let [low, high] = self.bounds?;
+        let start: [T; 3] = ray.get_point(enter);
+        let mut cell: [i32; 3] = core::array::from_fn(|i| {
+            let cell = self.cell_at(start)[i];
+            if entry_axis.is_some() {
+                cell.clamp(low[i], high[i])
+            } else {
+                cell
+            }
+        });
+        let unit = |axis: usize, sign: T| {
+            let mut normal = [T::ZERO; 3];
+            normal[axis] = sign;
+            normal
+        };
+        let size = self.voxel_size;
+        let step = ray.direction.map(|d| {
+            if d > T::ZERO {
+                1
+            } else if d < T::ZERO {
+                -1
+            } else {
+                0
+            }
+        });
+        // Time of the next crossing into a neighbouring cell along each axis,
+        // and the time between crossings
+        let mut next: [T; 3] = core::array::from_fn(|i| {
+            let boundary = cell[i] + i32::from(step[i] > 0);
+            let boundary = T::from_f64(boundary as f64) * size;
+            if step[i] == 0 {
+                T::INFINITY
+            } else {
+                (boundary - ray.origin[i]) / ray.direction[i]
+            }
+        });
+        let delta = ray.direction.map(|d| {
+            if d == T::ZERO {
+                T::INFINITY
+            } else {
+                size / d.abs()
+            }
+        });
+
+        let inside = self.is_occupied(cell);
+        if inside {
+            if let Some(axis) = entry_axis {
+                let sign = -T::from_f64(step[axis] as f64);
+                return Some(VoxelHit {
+                    time: enter,
+                    cell,
+                    normal: unit(axis, sign),
+                });
+            }
+        }
+        loop {
+            let axis = (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap();
+            let time = next[axis];
+            if !inside && time > exit {
+                return None;
+            }
+            let previous = cell;
+            cell[axis] += step[axis];
+            next[axis] += delta[axis];
+            let sign = T::from_f64(step[axis] as f64);
+            match (inside, self.is_occupied(cell)) {
+                (false, true) => {
+                    return Some(VoxelHit {
+                        time,
+                        cell,
+                        normal: unit(axis, -sign),
+                    })
+                }
+                (true, false) => {
+                    return Some(VoxelHit {
+                        time,
+                        cell: previous,
+                        normal: unit(axis, sign),
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// How far a box at `aabb` in local space can move along `motion` before
+    /// running into occupied cells, one axis at a time starting with y, so
+    /// it slides along walls and floors. A blocked axis comes back shorter
+    /// than asked for. Cells the box already overlaps don't block it, so it
+    /// can always move out of them.
+    pub fn move_aabb(&self, aabb: &Aabb<T>, motion: impl Into<Vec3<T>>) -> [T; 3] {
+        let motion: [T; 3] = motion.into().into();
+        let tolerance = T::from_f64(1e-4) * self.voxel_size;
+        let mut aabb = *aabb;
+        let mut moved = [T::ZERO; 3];
+        for axis in [1, 0, 2] {
+            let wanted = motion[axis];
+            if wanted == T::ZERO {
+                continue;
+            }
+            let mut swept = aabb;
+            if wanted > T::ZERO {
+                swept.max[axis] += wanted;
+            } else {
+                swept.min[axis] += wanted;
+            }
+            let mut allowed = wanted;
+            for cell in self.overlapping(&swept) {
+                let cell = self.cell_aabb(cell);
+                // Only cells strictly across the other axes get in the way
+                let across = (0..3).filter(|&i| i != axis).all(|i| {
+                    cell.min[i] < aabb.max[i] - tolerance
+                        && cell.max[i] > aabb.min[i] + tolerance
+                });
+                if !across {
+                    continue;
+                }
+                if wanted > T::ZERO && cell.min[axis] >= aabb.max[axis] - tolerance {
+                    allowed = allowed.min(cell.min[axis] - aabb.max[axis]);
+                } else if wanted < T::ZERO
+                    && cell.max[axis] <= aabb.min[axis] + tolerance
+                {
+                    allowed = allowed.max(cell.max[axis] - aabb.min[axis]);
+                }
+            }
+            aabb.min[axis] += allowed;
+            aabb.max[axis] += allowed;
+            moved[axis] = allowed;
+        }
+        moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contacts::contact;
+    use crate::intersections::ray_aabb_time;
+    use crate::isometry::Isometry;
+    use crate::math::*;
+    use crate::shapes::{Obb, Shape, Sphere};
+    use rand::Rng;
+
+    /// A floor one cell deep from -5 to 5 along x and z, with a wall two
+    /// cells high at x = 3.
+    fn room() -> VoxelGrid {
+        let floor = (-5..5).flat_map(|x| (-5..5).map(move |z| [x, -1, z]));
+        let wall = (0..2).flat_map(|y| (-5..5).map(move |z| [3, y, z]));
+        VoxelGrid::sparse(1.0).with_occupied(floor.chain(wall))
+    }
+
+    #[test]
+    fn storage() {
+        let cells = [[2, 0, 1], [-1, 3, 0], [0, 0, 0], [2, 0, -1]];
+        let mut dense = VoxelGrid::dense([-2, -1, -2], [5, 5, 4], 0.5);
+        let mut sparse = VoxelGrid::sparse(0.5);
+        for grid in [&mut dense, &mut sparse] {
+            assert!(grid.is_empty());
+            assert_eq!(grid.local_aabb(), Aabb::new([0.0; 3], [0.0; 3]));
+            for cell in cells {
+                grid.set(cell, true);
+            }
+            grid.set(cells[0], true);
+            assert_eq!(grid.len(), 4);
+            assert!(grid.is_occupied([0, 0, 0]) && !grid.is_occupied([1, 0, 0]));
+            assert_eq!(
+                grid.occupied().collect::<Vec<_>>(),
+                [[-1, 3, 0], [0, 0, 0], [2, 0, -1], [2, 0, 1]]
+            );
+            assert_eq!(
+                grid.local_aabb(),
+                Aabb::new([-0.5, 0.0, -0.5], [1.5, 2.0, 1.0])
+            );
+            assert_eq!(grid.cell_at([0.9, 0.1, -0.2]), [1, 0, -1]);
+            assert_eq!(grid.cell_aabb([1, 0, -1]).max, [1.0, 0.5, 0.0]);
+
+            // Touching counts, so the cell below the box's corner is included
+            let aabb = Aabb::new([0.5, 0.2, 0.1], [0.9, 0.4, 0.4]);
+            assert_eq!(grid.overlapping(&aabb).collect::<Vec<_>>(), [[0, 0, 0]]);
+            let aabb = Aabb::new([0.6, -1.0, -1.0], [2.0, 2.0, 2.0]);
+            assert_eq!(
+                grid.overlapping(&aabb).collect::<Vec<_>>(),
+                [[2, 0, -1], [2, 0, 1]]
+            );
+
+            grid.set([-1, 3, 0], false);
+            grid.set([-1, 3, 0], false);
+            grid.set([5, 5, 5], false);
+            assert_eq!(grid.len(), 3);
+            assert_eq!(grid.len(), grid.occupied().count());
+            assert_eq!(
+                grid.local_aabb(),
+                Aabb::new([0.0, 0.0, -0.5], [1.5, 0.5, 1.0])
+            );
+        }
+        assert_eq!(
+            dense.occupied().collect::<Vec<_>>(),
+            sparse.occupied().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cell outside the dense grid")]
+    fn dense_bounds() {
+        VoxelGrid::<f32>::dense([0; 3], [2; 3], 1.0).set([2, 0, 0], true);
+    }
+
+    #[test]
+    fn ray_casts() {
+        let mut rng = rand::thread_rng();
+        let mut grid = VoxelGrid::dense([-3, -2, -4], [8, 6, 8], 0.5);
+        for x in -3..5 {
+            for y in -2..4 {
+                for z in -4..4 {
+                    grid.set([x, y, z], rng.gen_bool(0.25));
+                }
+            }
+        }
+        let bounds = grid.local_aabb().expanded(1.0);
+        let mut hits = 0;
+        for _ in 0..500 {
+            let origin: [f32; 3] = core::array::from_fn(|i| {
+                rng.gen_range(bounds.min[i]..bounds.max[i])
+            });
+            if grid.is_occupied(grid.cell_at(origin)) {
+                continue;
+            }
+            let direction: [f32; 3] =
+                core::array::from_fn(|_| rng.gen_range(-1.0..1.0));
+            let ray = Ray::new(origin, direction);
+            let expected = grid
+                .occupied()
+                .map(|cell| ray_aabb_time(ray, grid.cell_aabb(cell)))
+                .filter(|time| *time >= 0.0)
+                .min_by(|a, b| a.total_cmp(b));
+            let hit = grid.cast_ray(ray);
+            assert_eq!(hit.is_some(), expected.is_some(), "{:?}", ray);
+            if let (Some(hit), Some(expected)) = (hit, expected) {
+                assert!(
+                    (hit.time - expected).abs() < 1e-4,
+                    "{} != {}",
+                    hit.time,
+                    expected
+                );
+                assert!(grid.is_occupied(hit.cell));
+                assert!(dot(hit.normal, direction) < 0.0);
+                hits += 1;
+            }
+        }
+        assert!(hits > 50, "{}", hits);
+
+        // Sparse cells far apart are crossed cell by cell
+        let far = VoxelGrid::sparse(1.0).with_occupied([[0, 0, 0], [300, 0, 0]]);
+        let hit = far
+            .cast_ray(Ray::new([1.5, 0.5, 0.5], [1.0, 0.0, 0.0]))
+            .unwrap();
+        assert_eq!((hit.time, hit.cell), (298.5, [300, 0, 0]));
+        assert_eq!(hit.normal, [-1.0, 0.0, 0.0]);
+        assert!(far
+            .cast_ray(Ray::new([1.5, 1.5, 0.5], [1.0, 0.0, 0.0]))
+            .is_none());
+        assert!(far.cast_ray(Ray::new([1.5, 0.5, 0.5], [0.0; 3])).is_none());
+
+        // Starting inside, the ray hits where it leaves the solid
+        let solid = VoxelGrid::sparse(1.0)
+            .with_occupied((0..27).map(|i| [i % 3, i / 3 % 3, i / 9]));
+        let hit = solid.cast_ray(Ray::new([1.5; 3], [1.0, 0.0, 0.0])).unwrap();
+        assert_eq!((hit.time, hit.cell), (1.5, [2, 1, 1]));
+        assert_eq!(hit.normal, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn moving_boxes() {
+        let grid = room();
+        let falling = Aabb::new([-0.4, 2.0, -0.4], [0.4, 2.8, 0.4]);
+        assert_eq!(grid.move_aabb(&falling, [0.0, -5.0, 0.0]), [0.0, -2.0, 0.0]);
+
+        // Sliding over the seams between floor cells doesn't catch
+        let resting = Aabb::new([-0.4, 0.0, -0.4], [0.4, 0.8, 0.4]);
+        assert_eq!(grid.move_aabb(&resting, [2.0, 0.0, 0.0]), [2.0, 0.0, 0.0]);
+        assert_eq!(grid.move_aabb(&resting, [5.0, -1.0, 1.0]), [2.6, 0.0, 1.0]);
+        assert_eq!(grid.move_aabb(&resting, [-3.0, 0.0, 0.0]), [-3.0, 0.0, 0.0]);
+        // Above the wall nothing is in the way
+        let high = resting.with_offset([0.0, 2.5, 0.0]);
+        assert_eq!(grid.move_aabb(&high, [5.0, 0.0, 0.0]), [5.0, 0.0, 0.0]);
+        // A box sunk into the floor can climb back out
+        let sunk = resting.with_offset([0.0, -0.5, 0.0]);
+        assert_eq!(grid.move_aabb(&sunk, [0.0, 1.0, 0.0]), [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn shape_queries() {
+        let room = Shape::VoxelGrid(room());
+        let placement = Isometry::from_axis_angle([0.0, 1.0, 0.0], 0.5)
+            .with_translation([4.0, 1.0, -2.0]);
+        let up: [f32; 3] = placement.transform_vector([0.0, 1.0, 0.0]);
+        let at = |local: [f32; 3]| placement * Isometry::from_translation(local);
+        let cube = Shape::Obb(Obb::new([0.0; 3], [0.4; 3], identity()));
+
+        // Sunk a little next to a seam, the cube is only pushed up
+        let sunk = at([1.39, 0.35, 0.5]);
+        let manifold = contact(&room, &placement, &cube, &sunk).unwrap();
+        assert!(dot(manifold.normal, up) > 0.999, "{:?}", manifold.normal);
+        assert!((manifold.max_depth() - 0.05).abs() < 1e-4);
+        assert_eq!(manifold.points.len(), 4);
+        let flipped = contact(&cube, &sunk, &room, &placement).unwrap();
+        assert!(dot(flipped.normal, up) < -0.999);
+        // Against the wall it's pushed back along -x
+        let against = at([2.65, 0.5, 0.5]);
+        let manifold = contact(&room, &placement, &cube, &against).unwrap();
+        let back: [f32; 3] = placement.transform_vector([-1.0, 0.0, 0.0]);
+        assert!(dot(manifold.normal, back) > 0.999, "{:?}", manifold.normal);
+        assert!(contact(&room, &placement, &cube, &at([0.5, 1.0, 0.5])).is_none());
+
+        let ball = Shape::Sphere(Sphere::new([0.0; 3], 0.5));
+        assert!(room.intersects(&placement, &ball, &at([0.5, 0.4, 0.5])));
+        assert!(ball.intersects(&at([3.5, 1.5, 0.0]), &room, &placement));
+        assert!(!room.intersects(&placement, &ball, &at([0.5, 0.6, 0.5])));
+        assert!(room.contains_point(&placement, at([0.5, -0.5, 0.5]).translation));
+        assert!(!room.contains_point(&placement, at([0.5, 0.5, 0.5]).translation));
+
+        let ray = Ray::new(at([0.5, 3.0, 0.5]).translation, scale(up, -1.0));
+        let (time, normal): (f32, [f32; 3]) =
+            room.cast_ray(&placement, ray).unwrap();
+        assert!((time - 3.0).abs() < 1e-4);
+        assert!(dot(normal, up) > 0.999);
+    }
+}
@@ -1,63 +1,58 @@
-use float_ord::FloatOrd;
-use std::simd::{LaneCount, Mask, Simd, SupportedLaneCount};
+use crate::real::Real;
+use crate::simd::{Mask, MaskOf, Simd, SimdPartialEq, SimdReal};
 
-pub fn aabb_aabb<const LANES: usize>(
-    [a_min_x, a_min_y, a_min_z, a_max_x, a_max_y, a_max_z]: [Simd<f32, LANES>; 6],
-    [b_min_x, b_min_y, b_min_z, b_max_x, b_max_y, b_max_z]: [Simd<f32, LANES>; 6],
-) -> Mask<i32, LANES>
+pub fn aabb_aabb<T: Real, const LANES: usize>(
+    [a_min_x, a_min_y, a_min_z, a_max_x, a_max_y, a_max_z]: [Simd<T, LANES>; 6],
+    [b_min_x, b_min_y, b_min_z, b_max_x, b_max_y, b_max_z]: [Simd<T, LANES>; 6],
+) -> MaskOf<T, LANES>
 where
-    LaneCount<LANES>: SupportedLaneCount,
+    Simd<T, LANES>: SimdReal<T, LANES>,
 {
-    let min_x_test = a_min_x.lanes_le(b_max_x);
-    let min_y_test = a_min_y.lanes_le(b_max_y);
-    let min_z_test = a_min_z.lanes_le(b_max_z);
-    let max_x_test = a_max_x.lanes_ge(b_min_x);
-    let max_y_test = a_max_y.lanes_ge(b_min_y);
-    let max_z_test = a_max_z.lanes_ge(b_min_z);
+    let min_x_test = a_min_x.simd_le(b_max_x);
+    let min_y_test = a_min_y.simd_le(b_max_y);
+    let min_z_test = a_min_z.simd_le(b_max_z);
+    let max_x_test = a_max_x.simd_ge(b_min_x);
+    let max_y_test = a_max_y.simd_ge(b_min_y);
+    let max_z_test = a_max_z.simd_ge(b_min_z);
     min_x_test & min_y_test & min_z_test & max_x_test & max_y_test & max_z_test
 }
 
-pub fn aabb_point<const LANES: usize>(
-    [a_min_x, a_min_y, a_min_z, a_max_x, a_max_y, a_max_z]: [Simd<f32, LANES>; 6],
-    [p_x, p_y, p_z]: [Simd<f32, LANES>; 3],
-) -> Mask<i32, LANES>
+pub fn aabb_point<T: Real, const LANES: usize>(
+    [a_min_x, a_min_y, a_min_z, a_max_x, a_max_y, a_max_z]: [Simd<T, LANES>; 6],
+    [p_x, p_y, p_z]: [Simd<T, LANES>; 3],
+) -> MaskOf<T, LANES>
 where
-    LaneCount<LANES>: SupportedLaneCount,
+    Simd<T, LANES>: SimdReal<T, LANES>,
 {
-    let min_x_test = p_x.lanes_ge(a_min_x);
-    let min_y_test = p_y.lanes_ge(a_min_y);
-    let min_z_test = p_z.lanes_ge(a_min_z);
-    let max_x_test = p_x.lanes_le(a_max_x);
-    let max_y_test = p_y.lanes_le(a_max_y);
-    let max_z_test = p_z.lanes_le(a_max_z);
+    let min_x_test = p_x.simd_ge(a_min_x);
+    let min_y_test = p_y.simd_ge(a_min_y);
+    let min_z_test = p_z.simd_ge(a_min_z);
+    let max_x_test = p_x.simd_le(a_max_x);
+    let max_y_test = p_y.simd_le(a_max_y);
+    let max_z_test = p_z.simd_le(a_max_z);
     min_x_test & min_y_test & min_z_test & max_x_test & max_y_test & max_z_test
 }
 
-pub fn aabb_sphere<const LANES: usize>(
-    [a_min_x, a_min_y, a_min_z, a_max_x, a_max_y, a_max_z]: [Simd<f32, LANES>; 6],
-    [s_x, s_y, s_z, s_radius]: [Simd<f32, LANES>; 4],
-) -> Mask<i32, LANES>
+pub fn aabb_sphere<T: Real, const LANES: usize>(
+    [a_min_x, a_min_y, a_min_z, a_max_x, a_max_y, a_max_z]: [Simd<T, LANES>; 6],
+    [s_x, s_y, s_z, s_radius]: [Simd<T, LANES>; 4],
+) -> MaskOf<T, LANES>
 where
-    LaneCount<LANES>: SupportedLaneCount,
+    Simd<T, LANES>: SimdReal<T, LANES>,
 {
-    let s_x = (s_x - a_min_x).abs();
-    let s_y = (s_y - a_min_y).abs();
-    let s_z = (s_z - a_min_z).abs();
-    let c_x = a_max_x - a_min_x + s_radius;
-    let c_y = a_max_y - a_min_y + s_radius;
-    let c_z = a_max_z - a_min_z + s_radius;
-    let x_test = s_x.lanes_le(c_x);
-    let y_test = s_y.lanes_le(c_y);
-    let z_test = s_z.lanes_le(c_z);
-    x_test & y_test & z_test
+    let d_x = s_x - s_x.simd_max(a_min_x).simd_min(a_max_x);
+    let d_y = s_y - s_y.simd_max(a_min_y).simd_min(a_max_y);
+    let d_z = s_z - s_z.simd_max(a_min_z).simd_min(a_max_z);
+    let distance_squared = d_x * d_x + d_y * d_y + d_z * d_z;
+    distance_squared.simd_le(s_radius * s_radius)
 }
 
-pub fn sphere_sphere<const LANES: usize>(
-    [a_x, a_y, a_z, a_radius]: [Simd<f32, LANES>; 4],
-    [b_x, b_y, b_z, b_radius]: [Simd<f32, LANES>; 4],
-) -> Mask<i32, LANES>
+pub fn sphere_sphere<T: Real, const LANES: usize>(
+    [a_x, a_y, a_z, a_radius]: [Simd<T, LANES>; 4],
+    [b_x, b_y, b_z, b_radius]: [Simd<T, LANES>; 4],
+) -> MaskOf<T, LANES>
 where
-    LaneCount<LANES>: SupportedLaneCount,
+    Simd<T, LANES>: SimdReal<T, LANES>,
 {
     let dx = a_x - b_x;
     let dy = a_y - b_y;
@@ -65,75 +60,81 @@ where
     let distance_squared = dx * dx + dy * dy + dz * dz;
     let radius_sum = a_radius + b_radius;
     let radius_sum_squared = radius_sum * radius_sum;
-    distance_squared.lanes_le(radius_sum_squared)
+    distance_squared.simd_le(radius_sum_squared)
 }
 
-pub fn sphere_point<const LANES: usize>(
-    [s_x, s_y, s_z, s_radius]: [Simd<f32, LANES>; 4],
-    [p_x, p_y, p_z]: [Simd<f32, LANES>; 3],
-) -> Mask<i32, LANES>
+pub fn sphere_point<T: Real, const LANES: usize>(
+    [s_x, s_y, s_z, s_radius]: [Simd<T, LANES>; 4],
+    [p_x, p_y, p_z]: [Simd<T, LANES>; 3],
+) -> MaskOf<T, LANES>
 where
-    LaneCount<LANES>: SupportedLaneCount,
+    Simd<T, LANES>: SimdReal<T, LANES>,
 {
     let dx = s_x - p_x;
     let dy = s_y - p_y;
     let dz = s_z - p_z;
     let distance_squared = dx * dx + dy * dy + dz * dz;
     let radius_squared = s_radius * s_radius;
-    distance_squared.lanes_le(radius_squared)
+    distance_squared.simd_le(radius_squared)
 }
 
-pub fn ray_aabb_time<const LANES: usize>(
-    rays: [Simd<f32, LANES>; 6],
-    aabb: [Simd<f32, LANES>; 6],
-) -> Simd<f32, LANES>
+pub fn ray_aabb_time<T: Real, const LANES: usize>(
+    rays: [Simd<T, LANES>; 6],
+    aabb: [Simd<T, LANES>; 6],
+) -> Simd<T, LANES>
 where
-    LaneCount<LANES>: SupportedLaneCount,
+    Simd<T, LANES>: SimdReal<T, LANES>,
 {
     let aabb_min = &aabb[0..3];
     let aabb_max = &aabb[3..6];
     let ray_origin = &rays[0..3];
     let ray_direction = &rays[3..6];
 
-    let mut t_min_part1: [Simd<f32, LANES>; 3] = Default::default();
-    let mut t_max_part1: [Simd<f32, LANES>; 3] = Default::default();
+    let mut t_min_part1: [Simd<T, LANES>; 3] = Default::default();
+    let mut t_max_part1: [Simd<T, LANES>; 3] = Default::default();
     for i in 0..3 {
         let t1 = (aabb_min[i] - ray_origin[i]) / ray_direction[i];
         let t2 = (aabb_max[i] - ray_origin[i]) / ray_direction[i];
-        t_min_part1[i] = t1.min(t2);
-        t_max_part1[i] = t1.max(t2);
+        t_min_part1[i] = t1.simd_min(t2);
+        t_max_part1[i] = t1.simd_max(t2);
     }
 
-    let mut t_min: Simd<f32, LANES> = Default::default();
-    let mut t_max: Simd<f32, LANES> = Default::default();
+    let mut t_min: Simd<T, LANES> = Default::default();
+    let mut t_max: Simd<T, LANES> = Default::default();
     for i in 0..LANES {
         let t_min_part2 = [t_min_part1[0][i], t_min_part1[1][i], t_min_part1[2][i]]
             .into_iter()
-            .map(|item| FloatOrd(item))
-            .max()
-            .map(|item| item.0)
-            .unwrap_or(0.0);
+            .max_by(|a, b| a.total_cmp(b))
+            .unwrap_or(T::ZERO);
         let t_max_part2 = [t_max_part1[0][i], t_max_part1[1][i], t_max_part1[2][i]]
             .into_iter()
-            .map(|item| FloatOrd(item))
-            .min()
-            .map(|item| item.0)
-            .unwrap_or(0.0);
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap_or(T::ZERO);
 
         t_min[i] = t_min_part2;
         t_max[i] = t_max_part2;
     }
 
-    let zero = Simd::splat(0.0);
-    let neg_one = Simd::splat(-1.0);
+    let zero = Simd::splat(T::ZERO);
+    let neg_one = Simd::splat(-T::ONE);
 
-    let min_max_mask = t_min.lanes_le(t_max);
-    let t_min_zero_mask = t_min.lanes_gt(zero);
-    let t_max_zero_mask = t_max.lanes_gt(zero);
-    min_max_mask.select(
-        t_min_zero_mask.select(t_min, t_max_zero_mask.select(t_max, neg_one)),
-        neg_one,
-    )
+    let min_max_mask = t_min.simd_le(t_max);
+    let t_min_zero_mask = t_min.simd_gt(zero);
+    let t_max_zero_mask = t_max.simd_gt(zero);
+    let t_max_or_miss = SimdReal::select(t_max_zero_mask, t_max, neg_one);
+    let t_hit = SimdReal::select(t_min_zero_mask, t_min, t_max_or_miss);
+    SimdReal::select(min_max_mask, t_hit, neg_one)
+}
+
+/// Lanes where the collision groups `[memberships, filter]` of `a` and `b`
+/// interact, to AND onto a shape test's mask.
+pub fn groups<const LANES: usize>(
+    [a_memberships, a_filter]: [Simd<u32, LANES>; 2],
+    [b_memberships, b_filter]: [Simd<u32, LANES>; 2],
+) -> Mask<i32, LANES> {
+    let zero = Simd::splat(0);
+    (a_memberships & b_filter).simd_ne(zero)
+        & (b_memberships & a_filter).simd_ne(zero)
 }
 
 #[cfg(test)]
@@ -141,8 +142,8 @@ mod tests {
     use super::*;
     use crate::intersections;
     use crate::ray::Ray;
-    use crate::shapes::Aabb;
-    use crate::test_utils::generate_test_data;
+    use crate::shapes::{Aabb, Sphere};
+    use crate::test_utils::{generate_soa_data, generate_test_data};
     use crate::utils::simd_permutations;
     use rand::Rng;
     use std::ops::Range;
@@ -161,7 +162,7 @@ mod tests {
         let mut has_intersection = false;
 
         simd_permutations::<LANES, 6, 6, _>(&simd_aabbs, &simd_aabbs, |a, b| {
-            let r = aabb_aabb::<LANES>(a, b).to_array();
+            let r = aabb_aabb::<f32, LANES>(a, b).to_array();
             let mut r2 = [false; LANES];
             let mut a_min = [0.0; 3];
             let mut a_max = [0.0; 3];
@@ -203,6 +204,54 @@ mod tests {
         assert!(has_non_intersection, "Only intersections found");
     }
 
+    #[test]
+    fn aabb_sphere_intersections() {
+        const LANES: usize = 8;
+        const RANGE: Range<f32> = -2.0..2.0;
+        const COUNT: usize = 100;
+        let mut rng = rand::thread_rng();
+        let (aabbs, _) = generate_test_data(COUNT, |_| {
+            [0.0, 0.0, 0.0, 1.0, 1.0, 1.0].map(|v| v + rng.gen_range(RANGE))
+        });
+        let spheres = generate_soa_data(COUNT, |_| {
+            [
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(RANGE),
+                rng.gen_range(0.05..1.0),
+            ]
+        });
+
+        let mut has_non_intersection = false;
+        let mut has_intersection = false;
+
+        simd_permutations::<LANES, 6, 4, _>(&aabbs, &spheres, |a, b| {
+            let r = aabb_sphere::<f32, LANES>(a, b).to_array();
+            let mut r2 = [false; LANES];
+            for i in 0..LANES {
+                let aabb = Aabb {
+                    min: [a[0][i], a[1][i], a[2][i]],
+                    max: [a[3][i], a[4][i], a[5][i]],
+                };
+                let sphere = Sphere {
+                    center: [b[0][i], b[1][i], b[2][i]],
+                    radius: b[3][i],
+                };
+                let i_r = intersections::aabb_sphere(aabb, sphere);
+                if i_r {
+                    has_intersection = true;
+                } else {
+                    has_non_intersection = true;
+                }
+                r2[i] = i_r;
+            }
+            assert!(r == r2, "SIMD results do not match non-SIMD results");
+        });
+
+        assert!(has_intersection, "No intersections found");
+        assert!(has_non_intersection, "Only intersections found");
+    }
+
     #[test]
     fn ray_aabb_intersections() {
         const LANES: usize = 8;
@@ -220,7 +269,7 @@ mod tests {
         let mut has_intersection = false;
 
         simd_permutations::<LANES, 6, 6, _>(&rays, &aabbs, |a, b| {
-            let r = ray_aabb_time::<LANES>(a, b).to_array();
+            let r = ray_aabb_time::<f32, LANES>(a, b).to_array();
             let mut r2 = [0.0; LANES];
             let mut a_min = [0.0; 3];
             let mut a_max = [0.0; 3];
@@ -261,4 +310,47 @@ mod tests {
         assert!(has_intersection, "No intersections found");
         assert!(has_non_intersection, "Only intersections found");
     }
+
+    #[test]
+    fn f64_lanes() {
+        let aabbs = [
+            Aabb::<f64>::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]),
+            Aabb::new([-3.0, 0.5, 0.0], [-1.0, 2.0, 1.0]),
+            Aabb::new([2.0, 2.0, 2.0], [3.0, 3.0, 3.0]),
+            Aabb::new([0.5, -1.0, 0.5], [4.0, 0.25, 0.75]),
+        ];
+        let spheres = [
+            Sphere::<f64>::new([1.5, 0.5, 0.5], 0.6),
+            Sphere::new([-2.0, 3.0, 0.5], 0.9),
+            Sphere::new([0.0, 0.0, 0.0], 2.0),
+            Sphere::new([4.5, 1.0, 0.5], 0.5),
+        ];
+        let ray = Ray::<f64>::new([-5.0, 0.6, 0.6], [1.0, 0.0, 0.0]);
+
+        let aabb_lanes = [0, 1, 2, 3, 4, 5].map(|k| {
+            Simd::from_array(
+                aabbs.map(|a| if k < 3 { a.min[k] } else { a.max[k - 3] }),
+            )
+        });
+        let sphere_lanes = [0, 1, 2, 3].map(|k| {
+            Simd::from_array(
+                spheres.map(|s| if k < 3 { s.center[k] } else { s.radius }),
+            )
+        });
+        let ray_lanes = [0, 1, 2, 3, 4, 5].map(|k| {
+            Simd::splat(if k < 3 {
+                ray.origin[k]
+            } else {
+                ray.direction[k - 3]
+            })
+        });
+
+        let hits = aabb_sphere::<f64, 4>(aabb_lanes, sphere_lanes).to_array();
+        let times = ray_aabb_time::<f64, 4>(ray_lanes, aabb_lanes).to_array();
+        for i in 0..4 {
+            assert_eq!(hits[i], intersections::aabb_sphere(aabbs[i], spheres[i]));
+            assert_eq!(times[i], intersections::ray_aabb_time(ray, aabbs[i]));
+        }
+        assert!(hits.contains(&true) && hits.contains(&false));
+    }
 }
@@ -0,0 +1,919 @@
+//! A set of colliders behind a broadphase, so scene queries don't have to be
+//! wired up to `intersections` by hand.
+
+use crate::bvh::Bvh;
+use crate::groups::CollisionGroups;
+use crate::intersections;
+use crate::isometry::Isometry;
+use crate::materials::Material;
+use crate::math::scale;
+use crate::ray::Ray;
+use crate::real::Real;
+use crate::shapes::{Aabb, Shape};
+use crate::sweep;
+use crate::Vec3;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use treeculler::Frustum;
+
+/// Identifies a collider in a `World`. Handles of removed colliders are never
+/// handed out again, even when their slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColliderHandle {
+    index: u32,
+    generation: u32,
+}
+
+impl ColliderHandle {
+    /// The slot this handle points at, shared with no other live collider.
+    pub(crate) fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Collider<T: Real = f32> {
+    pub shape: Shape<T>,
+    pub transform: Isometry<T>,
+    pub groups: CollisionGroups,
+    /// Sensors report overlaps through collision events but never show up
+    /// as contacts.
+    pub sensor: bool,
+    /// Colliders without one use the defaults of whatever simulates them.
+    pub material: Option<Material<T>>,
+    pub user_data: u64,
+}
+
+impl<T: Real> Collider<T> {
+    pub fn new(shape: impl Into<Shape<T>>, transform: Isometry<T>) -> Self {
+        Self {
+            shape: shape.into(),
+            transform,
+            groups: CollisionGroups::ALL,
+            sensor: false,
+            material: None,
+            user_data: 0,
+        }
+    }
+
+    pub fn with_groups(mut self, groups: CollisionGroups) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    pub fn with_sensor(mut self, sensor: bool) -> Self {
+        self.sensor = sensor;
+        self
+    }
+
+    pub fn with_material(mut self, material: Material<T>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    pub fn with_user_data(mut self, user_data: u64) -> Self {
+        self.user_data = user_data;
+        self
+    }
+
+    /// World space bounds, as used by the broadphase.
+    pub fn aabb(&self) -> Aabb<T> {
+        self.shape.aabb(&self.transform)
+    }
+}
+
+/// A hit from `cast_ray` or `cast_shape`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RayHit<T: Real = f32> {
+    pub collider: ColliderHandle,
+    pub time: T,
+    pub normal: [T; 3],
+}
+
+/// Two touching colliders, with `a < b`. `sensor` is set when either of
+/// them is a sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollisionPair {
+    pub a: ColliderHandle,
+    pub b: ColliderHandle,
+    pub sensor: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CollisionEvent {
+    Started(CollisionPair),
+    Persisted(CollisionPair),
+    /// Also sent when either collider was removed.
+    Stopped(CollisionPair),
+}
+
+pub type QueryPredicate<'a, T = f32> =
+    dyn Fn(ColliderHandle, &Collider<T>) -> bool + 'a;
+
+/// Limits a query to colliders whose groups interact with `groups` and that
+/// pass `predicate`, if one is set.
+#[derive(Clone, Copy, Default)]
+pub struct QueryFilter<'a, T: Real = f32> {
+    pub groups: CollisionGroups,
+    pub predicate: Option<&'a QueryPredicate<'a, T>>,
+}
+
+impl<'a, T: Real> QueryFilter<'a, T> {
+    pub fn new(groups: CollisionGroups) -> Self {
+        Self {
+            groups,
+            predicate: None,
+        }
+    }
+
+    pub fn with_predicate(mut self, predicate: &'a QueryPredicate<'a, T>) -> Self {
+        self.predicate = Some(predicate);
+        self
+    }
+
+    fn test(&self, handle: ColliderHandle, collider: &Collider<T>) -> bool {
+        self.groups.interacts(collider.groups)
+            && self
+                .predicate
+                .is_none_or(|predicate| predicate(handle, collider))
+    }
+}
+
+/// Decides whether two colliders whose groups interact should still be
+/// reported as a contact pair. Shared between clones of a world.
+pub type PairFilter<T = f32> =
+    Arc<dyn Fn(&Collider<T>, &Collider<T>) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+struct SharedPairFilter<T: Real>(PairFilter<T>);
+
+impl<T: Real> fmt::Debug for SharedPairFilter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PairFilter")
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Slot<T: Real> {
+    generation: u32,
+    collider: Option<Collider<T>>,
+}
+
+/// Owns colliders and answers scene queries through a bounding volume
+/// hierarchy. Changes through `insert`, `remove`, `set_transform` or
+/// `set_shape` only reach the broadphase on the next `update`, and querying
+/// before then panics.
+#[derive(Debug, Clone)]
+pub struct World<T: Real = f32> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+    len: usize,
+    bvh: Bvh<T>,
+    dirty: bool,
+    /// Set when colliders changed in ways that keep the broadphase valid but
+    /// may change which pairs touch.
+    pairs_stale: bool,
+    pair_filter: Option<SharedPairFilter<T>>,
+    /// Sorted by handles, as of the last `update`.
+    touching: Vec<CollisionPair>,
+    events: Vec<CollisionEvent>,
+}
+
+impl<T: Real> Default for World<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Real> World<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            bvh: Bvh::default(),
+            dirty: false,
+            pairs_stale: false,
+            pair_filter: None,
+            touching: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, collider: Collider<T>) -> ColliderHandle {
+        self.dirty = true;
+        self.len += 1;
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    collider: None,
+                });
+                (self.slots.len() - 1) as u32
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.collider = Some(collider);
+        ColliderHandle {
+            index,
+            generation: slot.generation,
+        }
+    }
+
+    pub fn remove(&mut self, handle: ColliderHandle) -> Option<Collider<T>> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let collider = slot.collider.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+        self.dirty = true;
+        Some(collider)
+    }
+
+    pub fn get(&self, handle: ColliderHandle) -> Option<&Collider<T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)?
+            .collider
+            .as_ref()
+    }
+
+    /// For changing groups, materials and user data. Moving or reshaping a
+    /// collider has to go through `set_transform` or `set_shape` so the
+    /// broadphase hears about it.
+    pub fn get_mut(&mut self, handle: ColliderHandle) -> Option<&mut Collider<T>> {
+        let collider = self
+            .slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)?
+            .collider
+            .as_mut()?;
+        self.pairs_stale = true;
+        Some(collider)
+    }
+
+    /// Moves a collider, returning its old transform.
+    pub fn set_transform(
+        &mut self,
+        handle: ColliderHandle,
+        transform: Isometry<T>,
+    ) -> Option<Isometry<T>> {
+        let collider = self.get_mut(handle)?;
+        let old = core::mem::replace(&mut collider.transform, transform);
+        self.dirty = true;
+        Some(old)
+    }
+
+    /// Swaps a collider's shape, returning the old one.
+    pub fn set_shape(
+        &mut self,
+        handle: ColliderHandle,
+        shape: impl Into<Shape<T>>,
+    ) -> Option<Shape<T>> {
+        let collider = self.get_mut(handle)?;
+        let old = core::mem::replace(&mut collider.shape, shape.into());
+        self.dirty = true;
+        Some(old)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ColliderHandle, &Collider<T>)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let collider = slot.collider.as_ref()?;
+            Some((self.handle(index), collider))
+        })
+    }
+
+    /// Rebuilds the broadphase if any collider changed since the last call,
+    /// and replaces `events` with the changes in touching pairs since then.
+    pub fn update(&mut self) {
+        self.rebuild();
+        if !self.pairs_stale {
+            self.events.clear();
+            let persisted =
+                self.touching.iter().map(|p| CollisionEvent::Persisted(*p));
+            self.events.extend(persisted);
+            return;
+        }
+        self.pairs_stale = false;
+
+        let mut touching = Vec::new();
+        self.touching_pairs(|a, b, sensor| {
+            touching.push(CollisionPair { a, b, sensor })
+        });
+        touching.sort_unstable_by_key(|p| (p.a, p.b));
+        self.events = pair_events(&self.touching, &touching);
+        self.touching = touching;
+    }
+
+    /// Rebuilds the broadphase if any collider changed, leaving the touching
+    /// pairs and their events for the next `update`.
+    pub(crate) fn rebuild(&mut self) {
+        if self.dirty {
+            let bvh =
+                Bvh::new(self.iter().map(|(h, c)| (c.aabb(), h.index as usize)));
+            self.bvh = bvh;
+            self.dirty = false;
+            self.pairs_stale = true;
+        }
+    }
+
+    /// Collision events produced by the last `update`, in handle order.
+    pub fn events(&self) -> &[CollisionEvent] {
+        &self.events
+    }
+
+    /// Sets an extra test applied to pairs after collision groups. Queries
+    /// see it straight away, collision events on the next `update`.
+    pub fn set_pair_filter(
+        &mut self,
+        filter: impl Fn(&Collider<T>, &Collider<T>) -> bool + Send + Sync + 'static,
+    ) {
+        self.pair_filter = Some(SharedPairFilter(Arc::new(filter)));
+        self.pairs_stale = true;
+    }
+
+    pub fn clear_pair_filter(&mut self) {
+        self.pair_filter = None;
+        self.pairs_stale = true;
+    }
+
+    /// Whether the collision groups and pair filter let `a` and `b` collide,
+    /// with `a` the one with the lower handle.
+    pub(crate) fn accepts_pair(&self, a: &Collider<T>, b: &Collider<T>) -> bool {
+        a.groups.interacts(b.groups)
+            && self
+                .pair_filter
+                .as_ref()
+                .is_none_or(|filter| (filter.0)(a, b))
+    }
+
+    fn handle(&self, index: usize) -> ColliderHandle {
+        ColliderHandle {
+            index: index as u32,
+            generation: self.slots[index].generation,
+        }
+    }
+
+    fn collider(&self, index: usize) -> &Collider<T> {
+        self.slots[index].collider.as_ref().unwrap()
+    }
+
+    fn check_updated(&self) {
+        assert!(
+            !self.dirty,
+            "World::update must be called after colliders change"
+        );
+    }
+
+    /// The closest collider passing `filter` hit by `ray` within `max_time`.
+    pub fn cast_ray(
+        &self,
+        ray: Ray<T>,
+        max_time: T,
+        filter: QueryFilter<T>,
+    ) -> Option<RayHit<T>> {
+        self.check_updated();
+        // Kept by the same rule as the bvh, so it's the hit the bvh returns
+        let mut closest: Option<(T, [T; 3])> = None;
+        let (index, _) = self.bvh.cast_ray(ray, max_time, |index| {
+            let collider = self.collider(index);
+            if !filter.test(self.handle(index), collider) {
+                return None;
+            }
+            let (time, normal) =
+                collider.shape.cast_ray(&collider.transform, ray)?;
+            if time <= closest.map_or(max_time, |(best, _)| best) {
+                closest = Some((time, normal));
+            }
+            Some(time)
+        })?;
+        let (time, normal) = closest?;
+        Some(RayHit {
+            collider: self.handle(index),
+            time,
+            normal,
+        })
+    }
+
+    /// The first collider passing `filter` that `shape` runs into when swept
+    /// from `transform` by `motion` per unit of time, without turning, up to
+    /// `max_time`. Colliders it already touches are hit at time zero unless
+    /// it's moving away from them. The normal is that of the hit collider's
+    /// surface.
+    pub fn cast_shape(
+        &self,
+        shape: &Shape<T>,
+        transform: &Isometry<T>,
+        motion: impl Into<Vec3<T>>,
+        max_time: T,
+        filter: QueryFilter<T>,
+    ) -> Option<RayHit<T>> {
+        self.check_updated();
+        let motion: [T; 3] = motion.into().into();
+        let start = shape.aabb(transform);
+        let swept = start.merged(&start.with_offset(scale(motion, max_time)));
+        let mut closest: Option<RayHit<T>> = None;
+        self.bvh.query(
+            |aabb| intersections::aabb_aabb(swept, *aabb),
+            |index| {
+                let collider = self.collider(index);
+                let handle = self.handle(index);
+                if !filter.test(handle, collider) {
+                    return;
+                }
+                let max_time = closest.map_or(max_time, |hit| hit.time);
+                let Some((time, normal)) = sweep::time_of_impact(
+                    shape,
+                    transform,
+                    motion,
+                    &collider.shape,
+                    &collider.transform,
+                    max_time,
+                ) else {
+                    return;
+                };
+                if closest.is_none_or(|hit| time < hit.time) {
+                    closest = Some(RayHit {
+                        collider: handle,
+                        time,
+                        normal: scale(normal, -T::ONE),
+                    });
+                }
+            },
+        );
+        closest
+    }
+
+    /// Calls `hit` for every collider passing `filter` that overlaps `shape`
+    /// placed at `transform`.
+    pub fn intersect_shape(
+        &self,
+        shape: &Shape<T>,
+        transform: &Isometry<T>,
+        filter: QueryFilter<T>,
+        mut hit: impl FnMut(ColliderHandle),
+    ) {
+        self.check_updated();
+        let bounds = shape.aabb(transform);
+        self.bvh.query(
+            |aabb| intersections::aabb_aabb(bounds, *aabb),
+            |index| {
+                let collider = self.collider(index);
+                let handle = self.handle(index);
+                if filter.test(handle, collider)
+                    && collider.shape.intersects(
+                        &collider.transform,
+                        shape,
+                        transform,
+                    )
+                {
+                    hit(handle);
+                }
+            },
+        );
+    }
+
+    /// Calls `hit` for every collider passing `filter` that contains `point`.
+    pub fn intersect_point(
+        &self,
+        point: impl Into<Vec3<T>>,
+        filter: QueryFilter<T>,
+        mut hit: impl FnMut(ColliderHandle),
+    ) {
+        self.check_updated();
+        let point: [T; 3] = point.into().into();
+        self.bvh.query(
+            |aabb| intersections::aabb_point(*aabb, point),
+            |index| {
+                let collider = self.collider(index);
+                let handle = self.handle(index);
+                if filter.test(handle, collider)
+                    && collider.shape.contains_point(&collider.transform, point)
+                {
+                    hit(handle);
+                }
+            },
+        );
+    }
+
+    /// Calls `hit` for every collider passing `filter` whose bounds are at
+    /// least partly inside `frustum`.
+    pub fn cull_frustum(
+        &self,
+        frustum: &Frustum<T>,
+        filter: QueryFilter<T>,
+        mut hit: impl FnMut(ColliderHandle),
+    ) {
+        self.check_updated();
+        self.bvh.query(
+            |aabb| aabb.within_frustum(frustum),
+            |index| {
+                let handle = self.handle(index);
+                if filter.test(handle, self.collider(index)) {
+                    hit(handle);
+                }
+            },
+        );
+    }
+
+    /// Calls `hit` once for every pair of touching colliders whose groups
+    /// interact and that pass the pair filter. Sensors are left out.
+    pub fn contact_pairs(
+        &self,
+        mut hit: impl FnMut(ColliderHandle, ColliderHandle),
+    ) {
+        self.check_updated();
+        self.touching_pairs(|a, b, sensor| {
+            if !sensor {
+                hit(a, b)
+            }
+        });
+    }
+
+    fn touching_pairs(
+        &self,
+        mut hit: impl FnMut(ColliderHandle, ColliderHandle, bool),
+    ) {
+        self.bvh.pairs(|a, b| {
+            let (a, b) = (a.min(b), a.max(b));
+            let (collider_a, collider_b) = (self.collider(a), self.collider(b));
+            if self.accepts_pair(collider_a, collider_b)
+                && collider_a.shape.intersects(
+                    &collider_a.transform,
+                    &collider_b.shape,
+                    &collider_b.transform,
+                )
+            {
+                let sensor = collider_a.sensor || collider_b.sensor;
+                hit(self.handle(a), self.handle(b), sensor);
+            }
+        });
+    }
+}
+
+/// Merges two sorted pair lists into the events turning `old` into `new`.
+fn pair_events(old: &[CollisionPair], new: &[CollisionPair]) -> Vec<CollisionEvent> {
+    let mut events = Vec::with_capacity(old.len().max(new.len()));
+    let (mut old, mut new) = (old.iter().peekable(), new.iter().peekable());
+    loop {
+        let event = match (old.peek(), new.peek()) {
+            (Some(o), Some(n)) => match (o.a, o.b).cmp(&(n.a, n.b)) {
+                Ordering::Less => CollisionEvent::Stopped(*old.next().unwrap()),
+                Ordering::Greater => CollisionEvent::Started(*new.next().unwrap()),
+                Ordering::Equal => {
+                    old.next();
+                    CollisionEvent::Persisted(*new.next().unwrap())
+                }
+            },
+            (Some(_), None) => CollisionEvent::Stopped(*old.next().unwrap()),
+            (None, Some(_)) => CollisionEvent::Started(*new.next().unwrap()),
+            (None, None) => return events,
+        };
+        events.push(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Obb, Sphere};
+    use rand::Rng;
+
+    #[test]
+    fn handles() {
+        let mut world = World::new();
+        let unit = Aabb::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        let a = world
+            .insert(Collider::new(unit, Isometry::identity()).with_user_data(7));
+        let b = world.insert(Collider::new(
+            unit,
+            Isometry::from_translation([3.0, 0.0, 0.0]),
+        ));
+        assert_eq!(world.len(), 2);
+        assert_eq!(world.get(a).unwrap().user_data, 7);
+
+        assert!(world.remove(a).is_some());
+        assert!(world.remove(a).is_none());
+        assert!(world.get(a).is_none());
+        let c = world.insert(Collider::new(unit, Isometry::identity()));
+        assert_ne!(a, c);
+        assert!(world.get(a).is_none());
+        assert_eq!(world.iter().map(|(h, _)| h).collect::<Vec<_>>(), [c, b]);
+
+        // Editing data keeps the broadphase valid, moving or reshaping doesn't
+        world.update();
+        world.get_mut(b).unwrap().user_data = 3;
+        let mut found = vec![];
+        let all = QueryFilter::default();
+        world.intersect_point([3.5, 0.5, 0.5], all, |h| found.push(h));
+        assert_eq!(found, [b]);
+        let moved = Isometry::from_translation([6.0, 0.0, 0.0]);
+        assert!(world.set_transform(b, moved).is_some());
+        let old = world.set_shape(b, Sphere::new([0.0; 3], 1.0)).unwrap();
+        assert!(matches!(old, Shape::Aabb(_)));
+        assert!(world.set_transform(a, moved).is_none());
+        world.update();
+        let mut found = vec![];
+        world.intersect_point([6.5, 0.0, 0.0], all, |h| found.push(h));
+        assert_eq!(found, [b]);
+    }
+
+    #[test]
+    fn queries() {
+        let mut world = World::new();
+        let ground = world.insert(Collider::new(
+            Aabb::new([-10.0, -1.0, -10.0], [10.0, 0.0, 10.0]),
+            Isometry::identity(),
+        ));
+        let ball = world.insert(Collider::new(
+            Sphere::new([0.0, 0.0, 0.0], 1.0),
+            Isometry::from_translation([0.0, 0.9, 0.0]),
+        ));
+        let crate_ = world.insert(Collider::new(
+            Obb::from(Aabb::new([-0.5, -0.5, -0.5], [0.5, 0.5, 0.5])),
+            Isometry::from_axis_angle([0.0, 1.0, 0.0], 0.7)
+                .with_translation([5.0, 3.0, 0.0]),
+        ));
+        world.update();
+        let all = QueryFilter::default();
+
+        let down = Ray::new([0.0, 10.0, 0.0], [0.0, -1.0, 0.0]);
+        let hit = world.cast_ray(down, f32::INFINITY, all).unwrap();
+        assert_eq!(hit.collider, ball);
+        assert!((hit.time - 8.1).abs() < 1e-5);
+        assert!((hit.normal[1] - 1.0).abs() < 1e-5);
+        assert!(world.cast_ray(down, 5.0, all).is_none());
+        let beside = Ray::new([5.0, 10.0, 0.0], [0.0, -1.0, 0.0]);
+        let hit = world.cast_ray(beside, f32::INFINITY, all).unwrap();
+        assert_eq!(hit.collider, crate_);
+        assert!((hit.time - 6.5).abs() < 1e-5);
+
+        let probe = Shape::Sphere(Sphere::new([0.0, 0.0, 0.0], 0.5));
+        let above = Isometry::from_translation([0.0, 10.0, 0.0]);
+        let hit = world
+            .cast_shape(&probe, &above, [0.0, -1.0, 0.0], 20.0, all)
+            .unwrap();
+        assert_eq!(hit.collider, ball);
+        assert!((hit.time - 7.6).abs() < 1e-3);
+        assert!((hit.normal[1] - 1.0).abs() < 1e-5);
+        let above = above.with_translation([5.0, 10.0, 0.0]);
+        let hit = world
+            .cast_shape(&probe, &above, [0.0, -1.0, 0.0], 20.0, all)
+            .unwrap();
+        assert_eq!(hit.collider, crate_);
+        assert!((hit.time - 6.0).abs() < 1e-3);
+        assert!(world
+            .cast_shape(&probe, &above, [0.0, -1.0, 0.0], 5.0, all)
+            .is_none());
+
+        let mut found = vec![];
+        world.intersect_point([0.0, -0.05, 0.0], all, |h| found.push(h));
+        found.sort_unstable();
+        assert_eq!(found, [ground, ball]);
+
+        let mut found = vec![];
+        world.intersect_shape(
+            &probe,
+            &Isometry::from_translation([4.2, 3.0, 0.0]),
+            all,
+            |h| found.push(h),
+        );
+        assert_eq!(found, [crate_]);
+
+        let mut pairs = vec![];
+        world.contact_pairs(|a, b| pairs.push((a, b)));
+        assert_eq!(pairs, [(ground, ball)]);
+
+        // Orthographic view of the cube from -2 to 2
+        let frustum = Frustum::from_modelview_projection([
+            [0.5, 0.0, 0.0, 0.0],
+            [0.0, 0.5, 0.0, 0.0],
+            [0.0, 0.0, 0.5, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let mut visible = vec![];
+        world.cull_frustum(&frustum, all, |h| visible.push(h));
+        visible.sort_unstable();
+        assert_eq!(visible, [ground, ball]);
+
+        world.set_transform(ball, Isometry::from_translation([0.0, 5.0, 0.0]));
+        world.update();
+        let mut pairs = vec![];
+        world.contact_pairs(|a, b| pairs.push((a, b)));
+        assert!(pairs.is_empty());
+    }
+
+    #[test]
+    fn filters() {
+        const PLAYER: u32 = 1;
+        const ENEMY: u32 = 2;
+        const PROJECTILE: u32 = 4;
+        const TRIGGER: u32 = 8;
+        let mut world = World::new();
+        let ball = |x: f32| {
+            Collider::new(
+                Sphere::new([0.0; 3], 1.0),
+                Isometry::from_translation([x, 0.0, 0.0]),
+            )
+        };
+        let player = world.insert(
+            ball(0.0).with_groups(CollisionGroups::new(PLAYER, ENEMY | TRIGGER)),
+        );
+        let enemy = world.insert(
+            ball(1.0)
+                .with_groups(CollisionGroups::new(
+                    ENEMY,
+                    PLAYER | ENEMY | PROJECTILE,
+                ))
+                .with_user_data(1),
+        );
+        let other_enemy = world.insert(
+            ball(2.5)
+                .with_groups(CollisionGroups::new(
+                    ENEMY,
+                    PLAYER | ENEMY | PROJECTILE,
+                ))
+                .with_user_data(1),
+        );
+        let trigger = world
+            .insert(ball(-1.0).with_groups(CollisionGroups::new(TRIGGER, PLAYER)));
+        world.update();
+
+        let mut pairs = vec![];
+        world.contact_pairs(|a, b| pairs.push((a, b)));
+        pairs.sort_unstable();
+        assert_eq!(
+            pairs,
+            [(player, enemy), (player, trigger), (enemy, other_enemy)]
+        );
+
+        // Enemies sharing a squad don't collide with each other
+        world.set_pair_filter(|a, b| a.user_data == 0 || a.user_data != b.user_data);
+        let mut pairs = vec![];
+        world.contact_pairs(|a, b| pairs.push((a, b)));
+        pairs.sort_unstable();
+        assert_eq!(pairs, [(player, enemy), (player, trigger)]);
+
+        let shot = QueryFilter::new(CollisionGroups::new(PROJECTILE, ENEMY));
+        let ray = Ray::new([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]);
+        let hit = world.cast_ray(ray, f32::INFINITY, shot).unwrap();
+        assert_eq!(hit.collider, enemy);
+        let skip_first = |handle, _: &Collider| handle != enemy;
+        let hit = world
+            .cast_ray(ray, f32::INFINITY, shot.with_predicate(&skip_first))
+            .unwrap();
+        assert_eq!(hit.collider, other_enemy);
+
+        let mut found = vec![];
+        let triggers = QueryFilter::new(CollisionGroups::new(u32::MAX, TRIGGER));
+        world.intersect_point([-0.5, 0.0, 0.0], triggers, |h| found.push(h));
+        assert_eq!(found, [trigger]);
+    }
+
+    #[test]
+    fn collision_events() {
+        use CollisionEvent::*;
+        let mut world = World::new();
+        let sphere = Sphere::new([0.0; 3], 1.0);
+        let wall = world.insert(Collider::new(
+            Aabb::new([-0.5, -5.0, -5.0], [0.5, 5.0, 5.0]),
+            Isometry::identity(),
+        ));
+        let trigger = world.insert(
+            Collider::new(sphere, Isometry::from_translation([4.0, 0.0, 0.0]))
+                .with_sensor(true),
+        );
+        let ball = world.insert(Collider::new(
+            sphere,
+            Isometry::from_translation([-3.0, 0.0, 0.0]),
+        ));
+        let pair = |a, b, sensor| CollisionPair { a, b, sensor };
+
+        let mut frames = vec![];
+        for x in [-3.0, -1.0, 0.0, 1.8, 3.5, 7.0] {
+            world.set_transform(ball, Isometry::from_translation([x, 0.0, 0.0]));
+            world.update();
+            frames.push(world.events().to_vec());
+        }
+        world.update();
+        frames.push(world.events().to_vec());
+        assert_eq!(
+            frames,
+            [
+                vec![],
+                vec![Started(pair(wall, ball, false))],
+                vec![Persisted(pair(wall, ball, false))],
+                vec![Stopped(pair(wall, ball, false))],
+                vec![Started(pair(trigger, ball, true))],
+                vec![Stopped(pair(trigger, ball, true))],
+                vec![],
+            ]
+        );
+
+        world.set_transform(ball, Isometry::from_translation([4.5, 0.0, 0.0]));
+        world.update();
+        let mut pairs = vec![];
+        world.contact_pairs(|a, b| pairs.push((a, b)));
+        assert!(pairs.is_empty());
+        world.update();
+        assert_eq!(world.events(), [Persisted(pair(trigger, ball, true))]);
+        world.remove(ball);
+        world.update();
+        assert_eq!(world.events(), [Stopped(pair(trigger, ball, true))]);
+    }
+
+    #[test]
+    fn filter_events() {
+        use CollisionEvent::*;
+        let mut world = World::new();
+        let sphere = Sphere::new([0.0; 3], 1.0);
+        let a = world.insert(Collider::new(sphere, Isometry::identity()));
+        let b = world.insert(Collider::new(
+            sphere,
+            Isometry::from_translation([1.0, 0.0, 0.0]),
+        ));
+        let pair = CollisionPair {
+            a,
+            b,
+            sensor: false,
+        };
+        world.update();
+        assert_eq!(world.events(), [Started(pair)]);
+        world.set_pair_filter(|_, _| false);
+        world.update();
+        assert_eq!(world.events(), [Stopped(pair)]);
+        world.clear_pair_filter();
+        world.update();
+        assert_eq!(world.events(), [Started(pair)]);
+        world.get_mut(b).unwrap().groups = CollisionGroups::new(1, 0);
+        world.update();
+        assert_eq!(world.events(), [Stopped(pair)]);
+    }
+
+    #[test]
+    fn contact_pairs_match_brute_force() {
+        let mut rng = rand::thread_rng();
+        let mut world = World::new();
+        for _ in 0..300 {
+            let shape = match rng.gen_range(0..3) {
+                0 => Shape::Aabb(Aabb::new([-0.5, -1.0, -0.5], [0.5, 1.0, 0.5])),
+                1 => Shape::Obb(Obb::from(Aabb::new([-1.0; 3], [1.0; 3]))),
+                _ => Shape::Sphere(Sphere::new([0.0; 3], rng.gen_range(0.2..1.5))),
+            };
+            let axis = [0.0; 3].map(|_| rng.gen_range(-1.0..1.0));
+            let transform = Isometry::from_axis_angle(axis, rng.gen_range(0.0..3.0))
+                .with_translation([0.0; 3].map(|_| rng.gen_range(-15.0..15.0)));
+            world.insert(Collider::new(shape, transform));
+        }
+        world.update();
+
+        let mut pairs = vec![];
+        world.contact_pairs(|a, b| pairs.push((a, b)));
+        pairs.sort_unstable();
+        let colliders: Vec<_> = world.iter().collect();
+        let mut expected = vec![];
+        for (i, (a, collider_a)) in colliders.iter().enumerate() {
+            for (b, collider_b) in &colliders[i + 1..] {
+                if collider_a.shape.intersects(
+                    &collider_a.transform,
+                    &collider_b.shape,
+                    &collider_b.transform,
+                ) {
+                    expected.push((*a, *b));
+                }
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    #[should_panic]
+    fn stale_queries_panic() {
+        let mut world = World::new();
+        world.insert(Collider::new(
+            Sphere::new([0.0; 3], 1.0),
+            Isometry::identity(),
+        ));
+        world.intersect_point([0.0; 3], QueryFilter::default(), |_| {});
+    }
+}